@@ -0,0 +1,10 @@
+ALTER TABLE server ADD trash_retention_days INTEGER DEFAULT 30; -- 0 keeps trashed files until they are purged
+CREATE TABLE trash (
+    tid INTEGER PRIMARY KEY NOT NULL,
+    url TEXT NOT NULL,
+    pid INTEGER NOT NULL,
+    rel_path TEXT NOT NULL, -- where the file was in its project
+    hash TEXT NOT NULL,
+    reason TEXT NOT NULL, -- what deleted the file: download, reset or project
+    created DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,18 @@
+CREATE TABLE commit_job (
+    cjid INTEGER PRIMARY KEY NOT NULL,
+    pid INTEGER NOT NULL,
+    message TEXT NOT NULL,
+    created DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+CREATE TABLE commit_job_file (
+    cjid INTEGER NOT NULL,
+    idx INTEGER NOT NULL,
+    part INTEGER NOT NULL, -- the server takes 200 files per commit, so big uploads are split into parts
+    source TEXT NOT NULL, -- the file table entry this came from, the new path for renames
+    path TEXT NOT NULL,
+    hash TEXT NOT NULL,
+    changetype INTEGER NOT NULL,
+    commitid INTEGER NOT NULL DEFAULT -1, -- set once the part is committed on the server
+    done INTEGER NOT NULL DEFAULT 0, -- set once the file table is updated
+    PRIMARY KEY (cjid, idx)
+);
\ No newline at end of file
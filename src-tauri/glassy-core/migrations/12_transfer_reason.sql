@@ -0,0 +1 @@
+ALTER TABLE download_journal ADD reason TEXT NOT NULL DEFAULT 'download'; -- download or reset, see transfer.rs
@@ -0,0 +1,14 @@
+ALTER TABLE server ADD job_concurrency INTEGER DEFAULT 2; -- how many jobs run at once
+CREATE TABLE job (
+    jid INTEGER PRIMARY KEY NOT NULL,
+    url TEXT NOT NULL,
+    pid INTEGER NOT NULL, -- -1 for jobs that aren't about one project, e.g. clearing the cache
+    kind TEXT NOT NULL, -- sync, download, upload, resume_upload, reset or clean_cache
+    task TEXT NOT NULL, -- json of what the job does, see queue.rs
+    user_id TEXT NOT NULL,
+    state TEXT NOT NULL DEFAULT 'queued', -- queued, running, done, failed or cancelled
+    attempts INTEGER NOT NULL DEFAULT 0, -- times the job was started, a job interrupted too often is failed
+    result TEXT, -- json of what the job returned
+    error TEXT, -- json of the GlassyError the job failed with
+    created DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
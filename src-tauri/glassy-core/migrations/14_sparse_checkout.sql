@@ -0,0 +1,6 @@
+CREATE TABLE sparse_rule (
+    srid INTEGER PRIMARY KEY NOT NULL,
+    pid INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    pattern TEXT NOT NULL -- a folder or glob to check out, or to leave out if it starts with !. see sparse.rs
+);
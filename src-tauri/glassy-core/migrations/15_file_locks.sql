@@ -0,0 +1,3 @@
+ALTER TABLE file ADD lock_user TEXT; -- who has the file checked out, as of the last time locks were fetched. see lock.rs
+ALTER TABLE file ADD locked_at TEXT;
+ALTER TABLE project ADD readonly_checkout INTEGER NOT NULL DEFAULT 0; -- 1 to keep files read-only until they are checked out
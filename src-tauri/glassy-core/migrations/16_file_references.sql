@@ -0,0 +1,7 @@
+CREATE TABLE file_reference (
+    pid INTEGER NOT NULL,
+    filepath TEXT NOT NULL, -- an assembly, drawing or other file that needs other files to open. see references.rs
+    reference TEXT NOT NULL, -- a file in the project it needs
+    PRIMARY KEY (pid, filepath, reference)
+);
+ALTER TABLE file ADD refs_hash TEXT NOT NULL DEFAULT ''; -- curr_hash of the file when its references were last read
@@ -0,0 +1,2 @@
+ALTER TABLE file ADD mtime INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE file ADD file_id INTEGER NOT NULL DEFAULT 0;
\ No newline at end of file
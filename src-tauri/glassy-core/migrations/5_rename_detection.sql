@@ -0,0 +1 @@
+ALTER TABLE file ADD old_filepath TEXT;
\ No newline at end of file
@@ -0,0 +1,17 @@
+CREATE TABLE download_journal (
+    jid INTEGER PRIMARY KEY NOT NULL,
+    pid INTEGER NOT NULL,
+    state INTEGER NOT NULL DEFAULT 0, -- 0 staging, 1 committing
+    created DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+CREATE TABLE download_journal_entry (
+    jid INTEGER NOT NULL,
+    idx INTEGER NOT NULL,
+    rel_path TEXT NOT NULL,
+    target TEXT NOT NULL, -- absolute path in the project, empty if only the file table changes
+    source TEXT NOT NULL, -- absolute path of the file that gets moved to target, empty for deletes
+    backup TEXT NOT NULL, -- where the file previously at target is kept until the download is committed
+    download INTEGER NOT NULL, -- 1 if the file table entry is updated, 0 if it is removed
+    done INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (jid, idx)
+);
\ No newline at end of file
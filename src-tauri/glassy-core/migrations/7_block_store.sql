@@ -0,0 +1,16 @@
+CREATE TABLE cache_manifest (
+    url TEXT NOT NULL,
+    file_hash TEXT NOT NULL,
+    chunk_index INTEGER NOT NULL,
+    block_hash TEXT NOT NULL,
+    offset INTEGER, -- null for fixed size chunks from older servers
+    size INTEGER,
+    PRIMARY KEY (url, file_hash, chunk_index)
+);
+CREATE TABLE cache_block (
+    url TEXT NOT NULL,
+    block_hash TEXT NOT NULL,
+    size INTEGER,
+    refcount INTEGER NOT NULL DEFAULT 0, -- number of manifests that use this block
+    PRIMARY KEY (url, block_hash)
+);
\ No newline at end of file
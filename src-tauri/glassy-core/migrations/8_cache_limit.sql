@@ -0,0 +1,8 @@
+ALTER TABLE server ADD cache_max_size INTEGER DEFAULT 0; -- bytes, 0 means no limit
+ALTER TABLE cache_block ADD present INTEGER NOT NULL DEFAULT 0; -- 1 once the block is in the block store
+CREATE TABLE cache_file (
+    url TEXT NOT NULL,
+    file_hash TEXT NOT NULL,
+    last_access INTEGER NOT NULL, -- unix time in ms
+    PRIMARY KEY (url, file_hash)
+);
\ No newline at end of file
@@ -0,0 +1,12 @@
+ALTER TABLE server ADD backup_retention_days INTEGER DEFAULT 30; -- 0 keeps backups forever
+CREATE TABLE backup (
+    bid INTEGER PRIMARY KEY NOT NULL,
+    url TEXT NOT NULL,
+    pid INTEGER NOT NULL,
+    rel_path TEXT NOT NULL,
+    hash TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    base_commitid INTEGER NOT NULL, -- commit the local edits were made on top of, -1 for new files
+    reason TEXT NOT NULL, -- what was about to overwrite the file: download, reset or restore
+    created DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,147 @@
+use crate::dal::DataAccessLayer;
+use crate::file::{native_path, sep};
+use crate::sync::hash_paths;
+use crate::types::{Backup, GlassyError};
+use crate::util::{get_backup_dir, set_readonly};
+use sqlx::{Pool, Sqlite};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// local edits that were never uploaded are copied here before a download or reset replaces or deletes them.
+// each backup is kept at .glassybackup/<pid>/<bid>/<file name> until the retention period runs out
+
+/// backs up every file in rel_paths that has local edits that were never uploaded.
+/// returns false if any of them couldn't be backed up, in which case they shouldn't be overwritten
+pub async fn backup_local_edits(pid: i32, rel_paths: &Vec<String>, reason: &str, pool: &Pool<Sqlite>) -> bool {
+    let dal = DataAccessLayer::new(pool);
+    let url = dal.get_active_server().await.unwrap_or_default();
+    let project_dir = dal.get_project_dir(pid).await.unwrap_or_default();
+    let backup_dir = get_backup_dir(pool).await.unwrap_or_default();
+    if url.is_empty() || project_dir.is_empty() || backup_dir.is_empty() {
+        log::error!("couldn't get project or backup folder for project {}", pid);
+        return false;
+    }
+
+    for rel_path in rel_paths {
+        let (hash, size, base_commitid) = match dal.get_local_edit(pid, rel_path.clone()).await {
+            Ok(Some(edit)) => edit,
+            Ok(None) => continue,
+            Err(_) => return false,
+        };
+        let source = native_path(&(project_dir.clone() + &(sep().to_string()) + rel_path));
+        // the file table can be behind, e.g. if the file was deleted since the last sync
+        if !Path::new(&source).exists() {
+            continue;
+        }
+
+        let mut backup = Backup {
+            bid: 0,
+            pid,
+            rel_path: rel_path.clone(),
+            hash,
+            size,
+            base_commitid,
+            reason: reason.to_string(),
+            created: "".to_string(),
+        };
+        let bid = match dal.add_backup(url.clone(), &backup).await {
+            Ok(bid) => bid,
+            Err(_) => return false,
+        };
+        backup.bid = bid;
+        let target = native_path(&get_backup_path(&backup_dir, &backup));
+        if let Some(prefix) = Path::new(&target).parent() {
+            let _ = fs::create_dir_all(prefix);
+        }
+        if let Err(err) = fs::copy(&source, &target) {
+            log::error!("couldn't back up {} to {}: {}", source, target, err);
+            let _ = dal.delete_backup(bid).await;
+            return false;
+        }
+        set_readonly(Path::new(&target), false);
+        log::info!("backed up local edits of {} to {}", rel_path, target);
+    }
+    true
+}
+
+/// copies a backup back into its project, backing up whatever is there now if it has local edits
+pub async fn restore_from_backup(bid: i64, pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let backup = dal.get_backup(bid).await?;
+    let project_dir = dal.get_project_dir(backup.pid).await?;
+    let backup_dir = get_backup_dir(pool).await?;
+    if project_dir.is_empty() || backup_dir.is_empty() {
+        log::error!("couldn't get project or backup folder for project {}", backup.pid);
+        return Ok(false);
+    }
+
+    if !backup_local_edits(backup.pid, &vec![backup.rel_path.clone()], "restore", pool).await {
+        return Ok(false);
+    }
+
+    let source = native_path(&get_backup_path(&backup_dir, &backup));
+    let target = native_path(&(project_dir.clone() + &(sep().to_string()) + &backup.rel_path));
+    if let Some(prefix) = Path::new(&target).parent() {
+        let _ = fs::create_dir_all(prefix);
+    }
+    // the file may be read-only because it isn't checked out, see lock.rs
+    set_readonly(Path::new(&target), false);
+    if let Err(err) = fs::copy(&source, &target) {
+        log::error!("couldn't restore backup {} to {}: {}", bid, target, err);
+        return Err(GlassyError::io(target, err));
+    }
+    log::info!("restored backup {} to {}", bid, target);
+
+    // the watcher would pick this up too, but the file table should be right when we return
+    hash_paths(backup.pid, &PathBuf::from(native_path(&project_dir)), vec![PathBuf::from(target)], pool).await;
+    Ok(true)
+}
+
+/// deletes backups that are older than the retention period
+pub async fn prune_backups(pool: &Pool<Sqlite>) {
+    let dal = DataAccessLayer::new(pool);
+    let url = dal.get_active_server().await.unwrap_or_default();
+    let retention_days = dal.get_backup_retention_days().await.unwrap_or_default();
+    if url.is_empty() || retention_days == 0 {
+        return;
+    }
+
+    let expired = dal.get_expired_backups(url, retention_days).await.unwrap_or_default();
+    for backup in expired.iter() {
+        remove_backup(backup.bid, pool).await;
+    }
+    if !expired.is_empty() {
+        log::info!("removed {} backups older than {} days", expired.len(), retention_days);
+    }
+}
+
+pub async fn remove_backup(bid: i64, pool: &Pool<Sqlite>) -> bool {
+    let dal = DataAccessLayer::new(pool);
+    let backup = match dal.get_backup(bid).await {
+        Ok(backup) => backup,
+        Err(_) => return false,
+    };
+    let backup_dir = get_backup_dir(pool).await.unwrap_or_default();
+    if backup_dir.is_empty() {
+        return false;
+    }
+
+    let folder = native_path(&get_backup_folder(&backup_dir, &backup));
+    if Path::new(&folder).exists() {
+        if let Err(err) = fs::remove_dir_all(&folder) {
+            log::warn!("couldn't remove backup folder {}: {}", folder, err);
+            return false;
+        }
+    }
+    dal.delete_backup(bid).await.is_ok()
+}
+
+fn get_backup_folder(backup_dir: &str, backup: &Backup) -> String {
+    backup_dir.to_owned() + &(sep().to_string()) + &backup.pid.to_string() + &(sep().to_string()) + &backup.bid.to_string()
+}
+
+/// keeps the file's name so backups can be browsed from the file explorer
+fn get_backup_path(backup_dir: &str, backup: &Backup) -> String {
+    let name = backup.rel_path.rsplit('\\').next().unwrap_or(&backup.rel_path);
+    get_backup_folder(backup_dir, backup) + &(sep().to_string()) + name
+}
@@ -0,0 +1,190 @@
+use fastcdc::v2020::StreamCDC;
+use crate::network::GlassyClient;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+// fixed size chunks
+pub const FIXED_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+// content-defined chunk sizes; the average matches the fixed chunk size
+const CDC_MIN_SIZE: u32 = 1024 * 1024;
+const CDC_AVG_SIZE: u32 = 4 * 1024 * 1024;
+const CDC_MAX_SIZE: u32 = 16 * 1024 * 1024;
+
+/// how a file is split into chunks before uploading
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkingStrategy {
+    /// 4 mb chunks; inserting bytes shifts every chunk after the insertion
+    Fixed,
+    /// chunk boundaries depend on the content (FastCDC), so an edit only changes the chunks around it
+    Cdc,
+}
+
+impl ChunkingStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChunkingStrategy::Fixed => "fixed",
+            ChunkingStrategy::Cdc => "cdc",
+        }
+    }
+}
+
+/// where a chunk is in its file, and its blake3 hash
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkBoundary {
+    pub chunk_index: usize,
+    pub offset: u64,
+    pub length: u64,
+    pub block_hash: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChunkingServerOutput {
+    pub response: String,
+    pub body: Option<ChunkingInformation>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChunkingInformation {
+    pub strategies: Vec<String>,
+}
+
+/// asks the server which chunking strategies it accepts; servers that don't know about
+/// content-defined chunking only get fixed size chunks
+pub async fn negotiate_chunking_strategy(client: &GlassyClient) -> ChunkingStrategy {
+    match client.chunking_strategies().await {
+        Ok(strategies) if strategies.iter().any(|s| s == ChunkingStrategy::Cdc.as_str()) => ChunkingStrategy::Cdc,
+        Ok(_) => ChunkingStrategy::Fixed,
+        Err(err) => {
+            log::info!("server did not list chunking strategies, using fixed size chunks: {}", err);
+            ChunkingStrategy::Fixed
+        }
+    }
+}
+
+/// splits a file into chunks without keeping the chunk data in memory
+pub fn chunk_file(path: &String, strategy: ChunkingStrategy) -> std::io::Result<Vec<ChunkBoundary>> {
+    match strategy {
+        ChunkingStrategy::Fixed => chunk_file_fixed(path),
+        ChunkingStrategy::Cdc => chunk_file_cdc(path),
+    }
+}
+
+fn chunk_file_fixed(path: &String) -> std::io::Result<Vec<ChunkBoundary>> {
+    let mut file = File::open(path)?;
+    let mut output: Vec<ChunkBoundary> = Vec::new();
+    let mut offset: u64 = 0;
+    loop {
+        let mut data: Vec<u8> = Vec::with_capacity(FIXED_CHUNK_SIZE as usize);
+        let n = file.by_ref().take(FIXED_CHUNK_SIZE).read_to_end(&mut data)? as u64;
+        if n == 0 {
+            break;
+        }
+        output.push(ChunkBoundary {
+            chunk_index: output.len(),
+            offset,
+            length: n,
+            block_hash: blake3::hash(&data).to_hex().to_string(),
+        });
+        offset += n;
+        if n < FIXED_CHUNK_SIZE {
+            break;
+        }
+    }
+    Ok(output)
+}
+
+fn chunk_file_cdc(path: &String) -> std::io::Result<Vec<ChunkBoundary>> {
+    let file = File::open(path)?;
+    let chunker = StreamCDC::new(BufReader::new(file), CDC_MIN_SIZE, CDC_AVG_SIZE, CDC_MAX_SIZE);
+    let mut output: Vec<ChunkBoundary> = Vec::new();
+    for result in chunker {
+        let chunk = match result {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                return Err(std::io::Error::other(err.to_string()));
+            }
+        };
+        output.push(ChunkBoundary {
+            chunk_index: output.len(),
+            offset: chunk.offset,
+            length: chunk.length as u64,
+            block_hash: blake3::hash(&chunk.data).to_hex().to_string(),
+        });
+    }
+    Ok(output)
+}
+
+/// reads a single chunk of a file from disk
+pub fn read_chunk(path: &String, offset: u64, length: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data: Vec<u8> = Vec::with_capacity(length as usize);
+    file.take(length).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // deterministic pseudo-random bytes so chunk boundaries are interesting
+    fn test_data(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cdc_insert_only_changes_nearby_chunks() {
+        let dir = std::env::temp_dir().join("glassy_chunking_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let original = test_data(24 * 1024 * 1024, 42);
+        let mut edited = b"a few new bytes".to_vec();
+        edited.extend_from_slice(&original);
+        let original_path = dir.join("original").display().to_string();
+        let edited_path = dir.join("edited").display().to_string();
+        std::fs::write(&original_path, &original).unwrap();
+        std::fs::write(&edited_path, &edited).unwrap();
+
+        let before = chunk_file(&original_path, ChunkingStrategy::Cdc).unwrap();
+        let after = chunk_file(&edited_path, ChunkingStrategy::Cdc).unwrap();
+
+        // chunks cover the whole file
+        let total: u64 = after.iter().map(|c| c.length).sum();
+        assert_eq!(total, edited.len() as u64);
+        for pair in after.windows(2) {
+            assert_eq!(pair[0].offset + pair[0].length, pair[1].offset);
+        }
+
+        // only the first chunk is new
+        let changed = after
+            .iter()
+            .filter(|c| !before.iter().any(|b| b.block_hash == c.block_hash))
+            .count();
+        assert_eq!(changed, 1);
+
+        // whereas with fixed size chunks every chunk changes
+        let before = chunk_file(&original_path, ChunkingStrategy::Fixed).unwrap();
+        let after = chunk_file(&edited_path, ChunkingStrategy::Fixed).unwrap();
+        assert!(after.iter().all(|c| !before.iter().any(|b| b.block_hash == c.block_hash)));
+        assert!(after[..after.len() - 1].iter().all(|c| c.length == FIXED_CHUNK_SIZE));
+        assert_eq!(after.last().unwrap().length, 15);
+
+        // reading a chunk back gives the same bytes
+        let chunk = &after[1];
+        let data = read_chunk(&edited_path, chunk.offset, chunk.length).unwrap();
+        assert_eq!(blake3::hash(&data).to_hex().to_string(), chunk.block_hash);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,245 @@
+use crate::config::Config;
+use crate::dal::DataAccessLayer;
+use crate::job::JobControl;
+use crate::lock::check_unlocked;
+use crate::network::GlassyClient;
+use crate::progress::ProgressReporter;
+use crate::types::{ChangeType, CommitJob, CommitJobFile, GlassyError};
+use crate::upload::{mark_uploaded, upload_project_files, UploadChunkError, UploadChunkResponse, UploadedFile};
+use sqlx::{Pool, Sqlite};
+use std::collections::BTreeMap;
+
+// the server only accepts this many files per commit
+pub const COMMIT_LIMIT: usize = 200;
+
+/// uploads `filepaths` and commits them, COMMIT_LIMIT files per commit.
+/// the upload is saved as a commit job first, so if it is interrupted it can be finished with resume_commit
+pub async fn commit_files(
+    pid: i32,
+    filepaths: Vec<String>,
+    message: String,
+    config: &Config,
+    pool: &Pool<Sqlite>,
+    progress: &dyn ProgressReporter,
+    control: &JobControl,
+) -> Result<UploadChunkResponse, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    if let Some(job) = dal.get_commit_job(pid).await? {
+        log::warn!("project {} already has an unfinished commit job {}", pid, job.cjid);
+        return Ok(failed(UploadChunkError::ErrGeneric));
+    }
+
+    let files = get_commit_job_files(pid, &filepaths, pool).await?;
+    let paths: Vec<String> = files.iter().map(|file| file.path.clone()).collect();
+    check_unlocked(pid, &paths, &GlassyClient::new(config), pool).await?;
+    let cjid = dal.create_commit_job(pid, message.clone(), &files).await?;
+    let job = CommitJob { cjid, pid, message, created: String::new() };
+    run_commit_job(&job, config, pool, progress, control).await
+}
+
+/// finishes the project's interrupted upload, if it has one
+pub async fn resume_commit(
+    pid: i32,
+    config: &Config,
+    pool: &Pool<Sqlite>,
+    progress: &dyn ProgressReporter,
+    control: &JobControl,
+) -> Result<UploadChunkResponse, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    match dal.get_commit_job(pid).await? {
+        Some(job) => {
+            check_job_files_unchanged(&job, pool).await?;
+            run_commit_job(&job, config, pool, progress, control).await
+        }
+        None => {
+            log::warn!("project {} has no commit job to resume", pid);
+            Ok(failed(UploadChunkError::ErrGeneric))
+        }
+    }
+}
+
+/// fails with GlassyError::FileChanged if a file that isn't committed yet changed since the job was created.
+/// its current content would be uploaded, but the commit would name the hash it had back then
+async fn check_job_files_unchanged(job: &CommitJob, pool: &Pool<Sqlite>) -> Result<(), GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    for file in dal.get_commit_job_files(job.cjid).await? {
+        if file.commitid >= 0 || file.changetype == ChangeType::Delete as i32 {
+            continue;
+        }
+        let current = dal.get_file_info(job.pid, file.path.clone()).await.map(|info| info.hash).unwrap_or_default();
+        if current != file.hash {
+            log::warn!("{} in project {} changed since commit job {} was created", file.path, job.pid, job.cjid);
+            return Err(GlassyError::FileChanged(format!(
+                "{} changed since the upload started, discard the upload and upload it again",
+                file.path
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// forgets the project's interrupted upload. parts that were already committed stay committed
+pub async fn discard_commit(pid: i32, pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    match dal.get_commit_job(pid).await? {
+        Some(job) => {
+            dal.delete_commit_job(job.cjid).await?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// a cancelled job is thrown away if none of it was committed yet, otherwise it is kept to be resumed or discarded
+async fn run_commit_job(
+    job: &CommitJob,
+    config: &Config,
+    pool: &Pool<Sqlite>,
+    progress: &dyn ProgressReporter,
+    control: &JobControl,
+) -> Result<UploadChunkResponse, GlassyError> {
+    let res = commit_parts(job, config, pool, progress, control).await;
+    if let Err(GlassyError::Cancelled) = res {
+        let dal = DataAccessLayer::new(pool);
+        let files = dal.get_commit_job_files(job.cjid).await?;
+        if files.iter().all(|file| file.commitid < 0) {
+            log::info!("upload for project {} cancelled before anything was committed", job.pid);
+            dal.delete_commit_job(job.cjid).await?;
+        }
+    }
+    res
+}
+
+async fn commit_parts(
+    job: &CommitJob,
+    config: &Config,
+    pool: &Pool<Sqlite>,
+    progress: &dyn ProgressReporter,
+    control: &JobControl,
+) -> Result<UploadChunkResponse, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let files = dal.get_commit_job_files(job.cjid).await?;
+
+    // chunks the server already has are skipped, so resuming doesn't upload everything again
+    let mut to_upload: Vec<String> = Vec::new();
+    for file in files.iter().filter(|file| file.commitid < 0) {
+        if !to_upload.contains(&file.source) {
+            to_upload.push(file.source.clone());
+        }
+    }
+    if !to_upload.is_empty() {
+        let res = upload_project_files(job.pid, to_upload, config, pool, progress, control).await?;
+        if !res.success {
+            return Ok(res);
+        }
+    }
+
+    let mut parts: BTreeMap<i64, Vec<&CommitJobFile>> = BTreeMap::new();
+    for file in files.iter() {
+        parts.entry(file.part).or_default().push(file);
+    }
+    let split = files.len() >= COMMIT_LIMIT;
+    let client = GlassyClient::new(config);
+    for (part, part_files) in parts {
+        if part_files[0].done {
+            continue;
+        }
+        let uploaded: Vec<UploadedFile> = part_files
+            .iter()
+            .map(|file| UploadedFile { path: file.path.clone(), hash: file.hash.clone(), changetype: file.changetype })
+            .collect();
+        let mut commit_id = part_files[0].commitid;
+        if commit_id < 0 {
+            control.checkpoint().await?;
+            let mut message = job.message.clone();
+            if split {
+                message += &format!(" - Part {}", part + 1);
+            }
+            commit_id = client.commit(job.pid, message, &uploaded).await.map_err(|err| {
+                log::error!("couldn't commit part {} of commit job {}: {}", part + 1, job.cjid, err);
+                err
+            })?;
+            // recorded right away, so a resumed job never commits the same part twice
+            dal.set_commit_job_part(job.cjid, part, commit_id, false).await?;
+        }
+
+        mark_uploaded(job.pid, commit_id, uploaded, pool).await?;
+        dal.set_commit_job_part(job.cjid, part, commit_id, true).await?;
+        log::info!("created commit {} with {} files for project {}", commit_id, part_files.len(), job.pid);
+    }
+
+    dal.delete_commit_job(job.cjid).await?;
+    Ok(UploadChunkResponse { success: true, error: UploadChunkError::ErrOk, message: None })
+}
+
+/// lists what gets committed for `filepaths`, split into parts of COMMIT_LIMIT files
+async fn get_commit_job_files(pid: i32, filepaths: &[String], pool: &Pool<Sqlite>) -> Result<Vec<CommitJobFile>, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let uploads = dal.get_uploads(pid).await?;
+
+    let mut files: Vec<CommitJobFile> = Vec::new();
+    let mut add = |source: &String, path: &String, hash: &String, changetype: i32| {
+        let idx = files.len() as i64;
+        files.push(CommitJobFile {
+            idx,
+            part: idx / COMMIT_LIMIT as i64,
+            source: source.clone(),
+            path: path.clone(),
+            hash: hash.clone(),
+            changetype,
+            commitid: -1,
+            done: false,
+        });
+    };
+    for upload in uploads.iter().filter(|upload| filepaths.contains(&upload.filepath)) {
+        // renames are committed as a delete plus a create of content the server already has
+        if upload.change_type == ChangeType::Rename {
+            if let Some(old_filepath) = &upload.old_filepath {
+                add(&upload.filepath, old_filepath, &upload.hash, ChangeType::Delete as i32);
+                add(&upload.filepath, &upload.filepath, &upload.hash, ChangeType::Create as i32);
+                continue;
+            }
+        }
+        add(&upload.filepath, &upload.filepath, &upload.hash, upload.change_type as i32);
+    }
+    Ok(files)
+}
+
+fn failed(error: UploadChunkError) -> UploadChunkResponse {
+    UploadChunkResponse { success: false, error, message: None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Token;
+    use crate::test_util::setup_project;
+    use crate::progress::NoProgress;
+    use crate::sync::hash_dir;
+    use sqlx::SqlitePool;
+    use std::fs;
+
+    #[sqlx::test]
+    async fn test_resume_rejects_changed_files(pool: SqlitePool) {
+        let project = setup_project("commit", &pool).await;
+        let project_dir = &project.project_dir;
+        let dal = DataAccessLayer::new(&pool);
+        fs::write(project_dir.join("a.SLDPRT"), "a").unwrap();
+        hash_dir(3, project_dir.clone(), &pool, false).await;
+
+        let files = get_commit_job_files(3, &["a.SLDPRT".to_string()], &pool).await.unwrap();
+        let cjid = dal.create_commit_job(3, "message".to_string(), &files).await.unwrap();
+        let job = dal.get_commit_job(3).await.unwrap().unwrap();
+        assert_eq!(job.cjid, cjid);
+        assert!(check_job_files_unchanged(&job, &pool).await.is_ok());
+
+        // edited between the crash and the resume
+        fs::write(project_dir.join("a.SLDPRT"), "edited").unwrap();
+        hash_dir(3, project_dir.clone(), &pool, false).await;
+        let config = Config { server_url: "url".to_string(), user_id: "me".to_string(), token: Token::default() };
+        let res = resume_commit(3, &config, &pool, &NoProgress, &JobControl::default()).await;
+        assert!(matches!(res, Err(GlassyError::FileChanged(_))));
+        // kept so it can be discarded
+        assert!(dal.get_commit_job(3).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,67 @@
+use crate::dal::DataAccessLayer;
+use crate::types::GlassyError;
+use sqlx::{Pool, Row, Sqlite};
+use std::sync::{Arc, Mutex};
+
+/// the server an operation talks to and who it runs as
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub server_url: String,
+    pub user_id: String,
+    pub token: Token, // sent as a bearer token, see network.rs
+}
+
+/// the user's bearer token. clones share it, so a token that is refreshed while a job runs
+/// is used by the job's next request
+#[derive(Clone, Debug, Default)]
+pub struct Token(Arc<Mutex<Option<String>>>);
+
+impl Token {
+    pub fn get(&self) -> Option<String> {
+        self.0.lock().ok().and_then(|token| token.clone())
+    }
+
+    pub fn set(&self, token: Option<String>) {
+        if let Ok(mut current) = self.0.lock() {
+            *current = token;
+        }
+    }
+}
+
+impl From<Option<String>> for Token {
+    fn from(token: Option<String>) -> Token {
+        Token(Arc::new(Mutex::new(token)))
+    }
+}
+
+impl Config {
+    /// uses the active server's url
+    pub async fn load(user_id: String, pool: &Pool<Sqlite>) -> Result<Config, GlassyError> {
+        let dal = DataAccessLayer::new(pool);
+        let server_url = dal.get_current_server().await?;
+        if server_url.is_empty() {
+            return Err(GlassyError::Setup("no server is set up".to_string()));
+        }
+        Ok(Config { server_url, user_id, token: Token::default() })
+    }
+
+    pub fn with_token(self, token: impl Into<Token>) -> Config {
+        Config { token: token.into(), ..self }
+    }
+}
+
+/// empty if no server is set up yet
+pub async fn get_server_dir(pool: &Pool<Sqlite>) -> Result<String, GlassyError> {
+    let output = sqlx::query("SELECT local_dir FROM server WHERE active = 1")
+        .fetch_optional(pool)
+        .await?;
+    match output {
+        Some(row) => Ok(row.get::<String, &str>("local_dir")),
+        None => Ok("".to_string()),
+    }
+}
+
+pub async fn get_cache_setting(pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    dal.get_cache_setting().await
+}
@@ -0,0 +1,112 @@
+use crate::config::Config;
+use crate::dal::DataAccessLayer;
+use crate::download::{download_project_files, download_revision};
+use crate::file::sep;
+use crate::job::JobControl;
+use crate::progress::ProgressReporter;
+use crate::types::{DownloadRequestMessage, GlassyError};
+use sqlx::{Pool, Sqlite};
+
+// conflicts are files that changed both locally and remotely, see get_conflicts
+
+/// keeps the local file; it uploads as an update on top of the latest commit
+pub async fn keep_mine(pid: i32, path: String, pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    if !dal.is_conflict(pid, path.clone()).await? {
+        log::warn!("{} in project {} is not a conflict", path, pid);
+        return Ok(false);
+    }
+
+    log::info!("resolving conflict for {} in project {} by keeping the local file", path, pid);
+    dal.resolve_keep_mine(pid, path).await?;
+    Ok(true)
+}
+
+/// replaces the local file with the latest remote revision, after backing up the local file
+pub async fn take_theirs(
+    pid: i32,
+    path: String,
+    config: &Config,
+    pool: &Pool<Sqlite>,
+    progress: &dyn ProgressReporter,
+    job: &JobControl,
+) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    if !dal.is_conflict(pid, path.clone()).await? {
+        log::warn!("{} in project {} is not a conflict", path, pid);
+        return Ok(false);
+    }
+    let revision = dal.get_tracked_revision(pid, path.clone()).await?;
+
+    // download_project_files backs up the local file, and takes care of deleting it if it was deleted remotely
+    log::info!("resolving conflict for {} in project {} by taking commit {}", path, pid, revision.tracked_commitid);
+    let request = DownloadRequestMessage {
+        commit_id: revision.tracked_commitid,
+        rel_path: path,
+        hash: revision.tracked_hash,
+        download: revision.tracked_changetype != 3,
+    };
+    download_project_files(pid, vec![request], config, pool, progress, job).await
+}
+
+/// keeps the local file and saves the latest remote revision next to it as `name (commit N).ext`
+pub async fn keep_both(pid: i32, path: String, config: &Config, pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    if !dal.is_conflict(pid, path.clone()).await? {
+        log::warn!("{} in project {} is not a conflict", path, pid);
+        return Ok(false);
+    }
+    let revision = dal.get_tracked_revision(pid, path.clone()).await?;
+    // nothing to keep from a remote delete
+    if revision.tracked_changetype == 3 {
+        dal.resolve_keep_mine(pid, path).await?;
+        return Ok(true);
+    }
+    let project_dir = dal.get_project_dir(pid).await?;
+
+    let copy_path = conflict_copy_path(&path, &format!("commit {}", revision.tracked_commitid));
+    let download_path = project_dir + &(sep().to_string()) + &copy_path;
+    log::info!("resolving conflict for {} in project {} by saving the remote revision as {}", path, pid, copy_path);
+    let downloaded = download_revision(
+        pid.into(),
+        path.clone(),
+        revision.tracked_commitid,
+        download_path,
+        config,
+        pool,
+    )
+    .await?;
+    if !downloaded {
+        return Ok(false);
+    }
+
+    // the copy is a new file as far as the server is concerned
+    dal.insert_local_file(copy_path, pid, revision.tracked_hash, revision.tracked_size as u64).await?;
+    dal.resolve_keep_mine(pid, path).await?;
+    Ok(true)
+}
+
+/// inserts ` (label)` between a file's name and its extension
+fn conflict_copy_path(rel_path: &str, label: &str) -> String {
+    let (dir, name) = match rel_path.rfind('\\') {
+        Some(idx) => rel_path.split_at(idx + 1),
+        None => ("", rel_path),
+    };
+    match name.rfind('.') {
+        Some(idx) if idx > 0 => format!("{}{} ({}){}", dir, &name[..idx], label, &name[idx..]),
+        _ => format!("{}{} ({})", dir, name, label),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conflict_copy_path() {
+        assert_eq!(conflict_copy_path("Parts\\bracket.SLDPRT", "commit 9"), "Parts\\bracket (commit 9).SLDPRT");
+        assert_eq!(conflict_copy_path("top.v2.SLDASM", "commit 9"), "top.v2 (commit 9).SLDASM");
+        assert_eq!(conflict_copy_path("Docs\\README", "commit 9"), "Docs\\README (commit 9)");
+        assert_eq!(conflict_copy_path(".gitignore", "commit 9"), ".gitignore (commit 9)");
+    }
+}
@@ -0,0 +1,2293 @@
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::result::Result::Ok;
+use crate::{sparse::SparseSpec, sync::FileChange, types::{Backup, ChangeType, CommitJob, CommitJobFile, FileChunk, FileFingerprint, FileLock, GlassyError, JournalEntry, QueuedJob, TrackedRevision, TrashEntry, UpdatedFile}};
+
+pub struct DataAccessLayer<'a> {
+    pub pool: &'a Pool<Sqlite>
+}
+
+impl<'a> DataAccessLayer<'a> {
+    pub fn new(user_pool: &'a Pool<Sqlite>) -> Self {
+        DataAccessLayer { pool: user_pool }
+    }
+
+    /// gets the current server URL to use for network calls
+    pub async fn get_current_server(&self) -> Result<String, GlassyError> {
+        let output = sqlx::query("SELECT CASE WHEN debug_active = 1 THEN debug_url ELSE url END as url FROM server WHERE active = 1")
+            .fetch_one(self.pool).await;
+    
+        match output {
+            Ok(row) => Ok(row.get::<String, &str>("url")),
+            Err(err) => {
+                log::error!("couldn't get the current server url: {}", err);
+                Ok("".to_string())
+            }
+        }
+    }
+
+    /// gets the current server URL for db foreign key purposes
+    pub async fn get_active_server(&self) -> Result<String, GlassyError> {
+        let output = sqlx::query("SELECT url FROM server WHERE active = 1")
+            .fetch_one(self.pool)
+            .await;
+    
+        match output {
+            Ok(row) => Ok(row.get::<String, &str>("url")),
+            Err(err) => {
+                log::error!("couldn't get the active server url: {}", err);
+                Ok("".to_string())
+            }
+        }
+    }
+
+    /// deletes an entry from the file table
+    pub async fn delete_file_entry(&self, pid: i32, path: String) -> Result<bool, GlassyError> {
+        let _ = sqlx::query(
+            "DELETE FROM file
+            WHERE pid = $1 AND filepath = $2",
+        )
+        .bind(pid)
+        .bind(path)
+        .execute(self.pool)
+        .await;
+        Ok(true)
+    }
+
+    /// get the project directory for the specified project
+    pub async fn get_project_dir(&self, pid: i32) -> Result<String, GlassyError> {
+        //println!("current allocating {}B", get_allocated());
+        let server = self.get_active_server().await?;
+        let db_call = sqlx::query("SELECT server.local_dir, project.title, project.team_name FROM server, project WHERE server.active = 1 AND project.url = ? AND project.pid = ?")
+            .bind(server)
+            .bind(pid)
+            .fetch_one(self.pool)
+            .await;
+        match db_call {
+            Ok(row) => {
+                let output = Path::new(&row.get::<String, &str>("local_dir"))
+                    .join(row.get::<String, &str>("team_name"))
+                    .join(row.get::<String, &str>("title"));
+                Ok(output.display().to_string())
+            }
+            Err(err) => {
+                log::error!("couldn't get the project directory for pid {}: {}", pid, err);
+                Ok("".to_string())
+            }
+        }
+    }
+
+    pub async fn get_file_info(&self, pid: i32, path: String) -> Result<UpdatedFile, GlassyError> {
+        let output = sqlx::query(
+            "SELECT curr_hash, size, change_type, in_fs FROM file WHERE filepath = $1 AND pid = $2",
+        )
+        .bind(path.clone())
+        .bind(pid)
+        .fetch_one(self.pool)
+        .await;
+    
+        match output {
+            Ok(row) => {
+                let change = match row.get::<i32, &str>("change_type") {
+                    1 => ChangeType::Create,
+                    2 => ChangeType::Update,
+                    3 => ChangeType::Delete,
+                    4 => ChangeType::Rename,
+                    _ => ChangeType::NoChange,
+                };
+                let in_fs = row.get::<i32, &str>("in_fs") > 0;
+                let owo: UpdatedFile = UpdatedFile {
+                    path,
+                    hash: row.get::<String, &str>("curr_hash").to_string(),
+                    size: row.get::<i64, &str>("size"),
+                    change,
+                    in_fs
+                };
+    
+                Ok(owo)
+            }
+            Err(err) => {
+                log::error!("couldn't get the file information for {} in project {}: {}", path, pid, err);
+                Err(err.into())
+            }
+        }
+    }
+
+    pub async fn get_basehash(&self, pid: i32, path: String) -> Result<String, GlassyError> {
+        let result = sqlx::query(
+            "SELECT base_hash FROM file WHERE
+            pid = $1 AND filepath = $2 LIMIT 1
+            ",
+        )
+        .bind(pid)
+        .bind(path)
+        .fetch_one(self.pool)
+        .await;
+    
+        match result {
+            Ok(row) => Ok(row.get::<String, &str>("base_hash")),
+            Err(err) => {
+                log::error!("could not get base_hash: {}", err);
+                Err(err.into())
+            }
+        }
+    }
+    
+    // TODO necessary to have in dal?
+    pub async fn update_downloaded_file_entry(&self, pid: i32, path: String) -> Result<bool, GlassyError> {
+            let _ = sqlx::query(
+            "
+            UPDATE file SET
+            base_hash = tracked_hash,
+            curr_hash = tracked_hash,
+            base_commitid = tracked_commitid,
+            size = tracked_size,
+            in_fs = 1,
+            change_type = 0,
+            mtime = 0
+            WHERE pid = $1 AND filepath = $2
+            ",
+        )
+        .bind(pid)
+        .bind(path)
+        .execute(self.pool)
+        .await;
+        Ok(true)
+    }
+
+    pub async fn update_cache_setting(&self, new_cache: bool) -> Result<bool, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query("UPDATE server SET cache_setting = $1 WHERE url = $2")
+        .bind(if new_cache { 1 } else { 0 })
+        .bind(url)
+        .execute(self.pool)
+        .await {
+            Ok(_o) => {
+                Ok(true)
+            },
+            Err(err) => {
+                log::error!("could not set cache setting due to db error: {}", err);
+                Ok(false)
+            }
+        }
+    }
+
+    pub async fn get_cache_setting(&self) -> Result<bool, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query("SELECT cache_setting FROM server WHERE url = $1")
+            .bind(url)
+            .fetch_one(self.pool)
+            .await {
+                Ok(row) => {
+                    let setting = row.get::<u32, &str>("cache_setting");
+                    Ok(setting == 1)
+                },
+                Err(err) => {
+                    log::error!("could not retrieve cache setting due to db error: {}", err);
+                    Ok(false)
+                }
+        }
+    }
+
+    /// gets the maximum cache size in bytes, 0 if the cache size isn't limited
+    pub async fn get_cache_max_size(&self) -> Result<u64, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query("SELECT cache_max_size FROM server WHERE url = $1")
+            .bind(url)
+            .fetch_one(self.pool)
+            .await {
+                Ok(row) => Ok(row.get::<Option<i64>, &str>("cache_max_size").unwrap_or(0).max(0) as u64),
+                Err(err) => {
+                    log::error!("could not retrieve cache size limit due to db error: {}", err);
+                    Ok(0)
+                }
+        }
+    }
+
+    pub async fn set_cache_max_size(&self, max_size: u64) -> Result<bool, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query("UPDATE server SET cache_max_size = $1 WHERE url = $2")
+            .bind(max_size as i64)
+            .bind(url)
+            .execute(self.pool)
+            .await {
+                Ok(_o) => Ok(true),
+                Err(err) => {
+                    log::error!("could not set cache size limit due to db error: {}", err);
+                    Ok(false)
+                }
+        }
+    }
+
+    /// gets how many jobs the queue runs at once
+    pub async fn get_job_concurrency(&self) -> Result<u32, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query("SELECT job_concurrency FROM server WHERE url = $1")
+            .bind(url)
+            .fetch_one(self.pool)
+            .await {
+                Ok(row) => Ok(row.get::<Option<i64>, &str>("job_concurrency").unwrap_or(2).max(1) as u32),
+                Err(err) => {
+                    log::error!("could not retrieve job concurrency due to db error: {}", err);
+                    Ok(2)
+                }
+        }
+    }
+
+    pub async fn set_job_concurrency(&self, concurrency: u32) -> Result<bool, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query("UPDATE server SET job_concurrency = $1 WHERE url = $2")
+            .bind(concurrency.max(1) as i64)
+            .bind(url)
+            .execute(self.pool)
+            .await {
+                Ok(_o) => Ok(true),
+                Err(err) => {
+                    log::error!("could not set job concurrency due to db error: {}", err);
+                    Ok(false)
+                }
+        }
+    }
+
+    /// gets how many days backups are kept for, 0 if they are kept forever
+    pub async fn get_backup_retention_days(&self) -> Result<u32, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query("SELECT backup_retention_days FROM server WHERE url = $1")
+            .bind(url)
+            .fetch_one(self.pool)
+            .await {
+                Ok(row) => Ok(row.get::<Option<u32>, &str>("backup_retention_days").unwrap_or(0)),
+                Err(err) => {
+                    log::error!("could not retrieve backup retention due to db error: {}", err);
+                    Ok(0)
+                }
+        }
+    }
+
+    pub async fn set_backup_retention_days(&self, days: u32) -> Result<bool, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query("UPDATE server SET backup_retention_days = $1 WHERE url = $2")
+            .bind(days)
+            .bind(url)
+            .execute(self.pool)
+            .await {
+                Ok(_o) => Ok(true),
+                Err(err) => {
+                    log::error!("could not set backup retention due to db error: {}", err);
+                    Ok(false)
+                }
+        }
+    }
+
+    /// gets how many days trashed files are kept for, 0 if they are kept until purged
+    pub async fn get_trash_retention_days(&self) -> Result<u32, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query("SELECT trash_retention_days FROM server WHERE url = $1")
+            .bind(url)
+            .fetch_one(self.pool)
+            .await {
+                Ok(row) => Ok(row.get::<Option<u32>, &str>("trash_retention_days").unwrap_or(0)),
+                Err(err) => {
+                    log::error!("could not retrieve trash retention due to db error: {}", err);
+                    Ok(0)
+                }
+        }
+    }
+
+    pub async fn set_trash_retention_days(&self, days: u32) -> Result<bool, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query("UPDATE server SET trash_retention_days = $1 WHERE url = $2")
+            .bind(days)
+            .bind(url)
+            .execute(self.pool)
+            .await {
+                Ok(_o) => Ok(true),
+                Err(err) => {
+                    log::error!("could not set trash retention due to db error: {}", err);
+                    Ok(false)
+                }
+        }
+    }
+
+    pub async fn clear_project_table(&self, url: String) -> Result<(), GlassyError> {
+        let _ = sqlx::query("DELETE from project WHERE url = $1")
+            .bind(url.clone())
+            .execute(self.pool)
+            .await;
+        Ok(())
+    }
+
+    pub async fn clear_file_table(&self) -> Result<(), GlassyError> {
+        let _ = sqlx::query("DELETE from file")
+            .execute(self.pool)
+            .await;
+        Ok(())
+    }
+
+    pub async fn clear_file_table_for_project(&self, pid: i32) -> Result<(), GlassyError> {
+        let _ = sqlx::query("DELETE from file WHERE pid = $1")
+            .bind(pid)
+            .execute(self.pool)
+            .await;
+        Ok(())
+    }
+
+    pub async fn clear_file_table_for_project_after_commit(&self, pid: i32, commit_id: i32) -> Result<Vec<String>, GlassyError> {
+        let results: Vec<String> = sqlx::query_scalar("DELETE from file WHERE pid = $1 AND commit_id > $2 RETURNING filepath")
+            .bind(pid)
+            .bind(commit_id)
+            .fetch_all(self.pool)
+            .await?;
+        Ok(results)
+    }
+
+    pub async fn get_server_name(&self) -> Result<String, GlassyError> {
+        let output = sqlx::query("SELECT name FROM server WHERE active = 1")
+        .fetch_one(self.pool)
+        .await;
+
+        match output {
+            Ok(row) => Ok(row.get::<String, &str>("name")),
+            Err(err) => {
+                log::error!("couldn't get server name: {}", err);
+                Ok("glassyPDM".to_string())
+            }
+        }
+    }
+
+    pub async fn add_server(&self, url: String, clerk_pub_key: String, local_dir: String, name: String) -> Result<(), GlassyError> {
+        sqlx::query(
+            "INSERT INTO server (url, clerk_publickey, local_dir, name, active, debug_url, debug_active) VALUES (?, ?, ?, ?, ?, ?, ?);"
+        )
+        .bind(url)
+        .bind(clerk_pub_key)
+        .bind(local_dir)
+        .bind(name)
+        .bind(1)
+        .bind("http://localhost:5000")
+        .bind(0)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn add_project(&self, pid: i32, title: String, team_name: String, init_commit: i32) -> Result<(), GlassyError> {
+        let server = self.get_active_server().await?;
+
+        let _output = sqlx::query("INSERT INTO project(pid, url, title, team_name, base_commitid, remote_title) VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(pid, url) DO UPDATE SET remote_title = excluded.title")
+            .bind(pid)
+            .bind(server)
+            .bind(title.clone())
+            .bind(team_name)
+            .bind(init_commit)
+            .bind(title.clone())
+            .execute(self.pool)
+            .await;
+
+        Ok(())
+    }
+
+    pub async fn get_project_name(&self, pid: i32) -> Result<String, GlassyError> {
+        let server = self.get_active_server().await?;
+        let output = sqlx::query("SELECT title FROM project WHERE pid = $1 AND url = $2")
+            .bind(pid)
+            .bind(server)
+            .fetch_one(self.pool)
+            .await;
+
+        match output {
+            Ok(row) => Ok(row.get::<String, &str>("title")),
+            Err(err) => {
+                log::error!("couldn't get name of project {}: {}", pid, err);
+                Ok("".to_string())
+            }
+        }
+    }
+
+    pub async fn get_downloads(&self, pid: i32) -> Result<Vec<FileChange>, GlassyError> {
+        let output: Vec<FileChange> = match sqlx::query_as(
+            "SELECT filepath, tracked_size as size, tracked_changetype as change_type, tracked_hash as hash, tracked_commitid as commit_id FROM file WHERE pid = $1 AND
+            (
+                (in_fs = 1 AND tracked_changetype = 3) OR
+                (base_hash != tracked_hash AND tracked_changetype != 3)
+            )
+            "
+        )
+        .bind(pid).fetch_all(self.pool)
+        .await {
+            Ok(downloads) => downloads,
+            Err(err) => {
+                log::error!("encountered error while querying db: {}", err);
+                Vec::<FileChange>::new()
+            }
+        };
+        Ok(self.in_sparse_checkout(pid, output).await)
+    }
+
+    pub async fn get_uploads(&self, pid: i32) -> Result<Vec<FileChange>, GlassyError> {
+        // deleted files that were renamed are shown as part of the rename instead
+        let output = match sqlx::query_as("SELECT filepath, size, change_type, curr_hash as hash, base_commitid as commit_id, old_filepath FROM file WHERE pid = $1 AND change_type != 0
+            AND filepath NOT IN (SELECT old_filepath FROM file WHERE pid = $1 AND change_type = 4)")
+        .bind(pid).fetch_all(self.pool)
+        .await {
+            Ok(uploads) => uploads,
+            Err(err) => {
+                log::error!("encountered error while querying db: {}", err);
+                Vec::<FileChange>::new()
+            }
+        };
+        // files outside of the sparse checkout aren't on disk, but they aren't deleted
+        Ok(self.in_sparse_checkout(pid, output).await)
+    }
+
+    pub async fn get_conflicts(&self, pid: i32) -> Result<Vec<FileChange>, GlassyError> {
+        match sqlx::query_as(
+            "SELECT filepath, size, change_type, curr_hash as hash, base_commitid as commit_id FROM file WHERE pid = $1 AND
+            change_type != 0 AND
+            (
+                (in_fs = 1 AND tracked_changetype = 3) OR
+                (base_hash != tracked_hash AND tracked_changetype != 3)
+            )
+            "
+        )
+        .bind(pid).fetch_all(self.pool)
+        .await {
+            Ok(conflicts) => Ok(self.in_sparse_checkout(pid, conflicts).await),
+            Err(err) => {
+                log::error!("encountered error querying db: {}", err);
+                Ok(Vec::<FileChange>::new())
+            }
+        }
+    }
+
+    /// leaves out files outside of the project's sparse checkout
+    async fn in_sparse_checkout(&self, pid: i32, mut files: Vec<FileChange>) -> Vec<FileChange> {
+        let spec = SparseSpec::new(&self.get_sparse_rules(pid).await.unwrap_or_default());
+        files.retain(|file| spec.includes(&file.filepath));
+        files
+    }
+
+    /// checks whether a file is listed by get_conflicts
+    pub async fn is_conflict(&self, pid: i32, path: String) -> Result<bool, GlassyError> {
+        let conflicts = self.get_conflicts(pid).await?;
+        Ok(conflicts.iter().any(|conflict| conflict.filepath == path))
+    }
+
+    pub async fn get_tracked_revision(&self, pid: i32, path: String) -> Result<TrackedRevision, GlassyError> {
+        match sqlx::query_as("SELECT tracked_hash, tracked_commitid, tracked_changetype, tracked_size FROM file WHERE pid = $1 AND filepath = $2")
+            .bind(pid)
+            .bind(path.clone())
+            .fetch_one(self.pool)
+            .await {
+                Ok(revision) => Ok(revision),
+                Err(err) => {
+                    log::error!("couldn't get tracked revision of {} in project {}: {}", path, pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// resolves a conflict by keeping the local file and basing it on the latest commit,
+    /// so it uploads as a change on top of the remote revision instead of conflicting with it.
+    /// if the file was deleted remotely, the local file becomes a new file
+    pub async fn resolve_keep_mine(&self, pid: i32, path: String) -> Result<(), GlassyError> {
+        let res = sqlx::query(
+            "UPDATE file SET
+            base_hash = CASE WHEN tracked_changetype = 3 THEN '' ELSE tracked_hash END,
+            base_commitid = CASE WHEN tracked_changetype = 3 THEN -1 ELSE tracked_commitid END,
+            tracked_hash = CASE WHEN tracked_changetype = 3 THEN '' ELSE tracked_hash END,
+            tracked_changetype = CASE WHEN tracked_changetype = 3 THEN 1 ELSE tracked_changetype END
+            WHERE pid = $1 AND filepath = $2",
+        )
+        .bind(pid)
+        .bind(path.clone())
+        .execute(self.pool)
+        .await;
+        if let Err(err) = res {
+            log::error!("couldn't keep local version of {} in project {}: {}", path, pid, err);
+            return Err(err.into());
+        }
+        self.update_change_types(pid).await
+    }
+
+    /// gets (curr_hash, size, base_commitid) of a file that has local edits that were never uploaded,
+    /// None if the file is unchanged or not on disk
+    pub async fn get_local_edit(&self, pid: i32, path: String) -> Result<Option<(String, i64, i64)>, GlassyError> {
+        match sqlx::query_as("SELECT curr_hash, size, base_commitid FROM file
+            WHERE pid = $1 AND filepath = $2 AND in_fs = 1 AND curr_hash != base_hash")
+            .bind(pid)
+            .bind(path.clone())
+            .fetch_optional(self.pool)
+            .await {
+                Ok(edit) => Ok(edit),
+                Err(err) => {
+                    log::error!("couldn't check {} in project {} for local edits: {}", path, pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// records `backup` and returns its bid. its bid and created are ignored
+    pub async fn add_backup(&self, url: String, backup: &Backup) -> Result<i64, GlassyError> {
+        match sqlx::query("INSERT INTO backup(url, pid, rel_path, hash, size, base_commitid, reason) VALUES ($1, $2, $3, $4, $5, $6, $7)")
+            .bind(url)
+            .bind(backup.pid)
+            .bind(&backup.rel_path)
+            .bind(&backup.hash)
+            .bind(backup.size)
+            .bind(backup.base_commitid)
+            .bind(&backup.reason)
+            .execute(self.pool)
+            .await {
+                Ok(res) => Ok(res.last_insert_rowid()),
+                Err(err) => {
+                    log::error!("couldn't record backup of {} in project {}: {}", backup.rel_path, backup.pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// gets the backups of a server, or of one of its projects, newest first
+    pub async fn get_backups(&self, url: String, pid: Option<i32>) -> Result<Vec<Backup>, GlassyError> {
+        match sqlx::query_as("SELECT bid, pid, rel_path, hash, size, base_commitid, reason, created FROM backup
+            WHERE url = $1 AND ($2 IS NULL OR pid = $2) ORDER BY created DESC, bid DESC")
+            .bind(url)
+            .bind(pid)
+            .fetch_all(self.pool)
+            .await {
+                Ok(backups) => Ok(backups),
+                Err(err) => {
+                    log::error!("couldn't query backups: {}", err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn get_backup(&self, bid: i64) -> Result<Backup, GlassyError> {
+        match sqlx::query_as("SELECT bid, pid, rel_path, hash, size, base_commitid, reason, created FROM backup WHERE bid = $1")
+            .bind(bid)
+            .fetch_one(self.pool)
+            .await {
+                Ok(backup) => Ok(backup),
+                Err(err) => {
+                    log::error!("couldn't get backup {}: {}", bid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn delete_backup(&self, bid: i64) -> Result<(), GlassyError> {
+        match sqlx::query("DELETE FROM backup WHERE bid = $1")
+            .bind(bid)
+            .execute(self.pool)
+            .await {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    log::error!("couldn't delete backup {}: {}", bid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// gets the backups of a server that are older than the retention period
+    pub async fn get_expired_backups(&self, url: String, retention_days: u32) -> Result<Vec<Backup>, GlassyError> {
+        match sqlx::query_as("SELECT bid, pid, rel_path, hash, size, base_commitid, reason, created FROM backup
+            WHERE url = $1 AND created < datetime('now', '-' || $2 || ' days')")
+            .bind(url)
+            .bind(retention_days)
+            .fetch_all(self.pool)
+            .await {
+                Ok(backups) => Ok(backups),
+                Err(err) => {
+                    log::error!("couldn't query expired backups: {}", err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// gets (filepath, curr_hash) of every file of a project that is on disk
+    pub async fn get_local_files(&self, pid: i32) -> Result<Vec<(String, String)>, GlassyError> {
+        match sqlx::query_as("SELECT filepath, curr_hash FROM file WHERE pid = $1 AND in_fs = 1 AND curr_hash != ''")
+            .bind(pid)
+            .fetch_all(self.pool)
+            .await {
+                Ok(files) => Ok(files),
+                Err(err) => {
+                    log::error!("couldn't query local files of project {}: {}", pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn add_trash_entry(&self, url: String, pid: i32, rel_path: String, hash: String, reason: &str) -> Result<i64, GlassyError> {
+        match sqlx::query("INSERT INTO trash(url, pid, rel_path, hash, reason) VALUES ($1, $2, $3, $4, $5)")
+            .bind(url)
+            .bind(pid)
+            .bind(rel_path.clone())
+            .bind(hash)
+            .bind(reason)
+            .execute(self.pool)
+            .await {
+                Ok(res) => Ok(res.last_insert_rowid()),
+                Err(err) => {
+                    log::error!("couldn't record trashed file {} in project {}: {}", rel_path, pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// gets the trashed files of a server, or of one of its projects, newest first
+    pub async fn get_trash_entries(&self, url: String, pid: Option<i32>) -> Result<Vec<TrashEntry>, GlassyError> {
+        match sqlx::query_as("SELECT tid, pid, rel_path, hash, reason, created FROM trash
+            WHERE url = $1 AND ($2 IS NULL OR pid = $2) ORDER BY created DESC, tid DESC")
+            .bind(url)
+            .bind(pid)
+            .fetch_all(self.pool)
+            .await {
+                Ok(entries) => Ok(entries),
+                Err(err) => {
+                    log::error!("couldn't query trash: {}", err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn get_trash_entry(&self, tid: i64) -> Result<TrashEntry, GlassyError> {
+        match sqlx::query_as("SELECT tid, pid, rel_path, hash, reason, created FROM trash WHERE tid = $1")
+            .bind(tid)
+            .fetch_one(self.pool)
+            .await {
+                Ok(entry) => Ok(entry),
+                Err(err) => {
+                    log::error!("couldn't get trash entry {}: {}", tid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn delete_trash_entry(&self, tid: i64) -> Result<(), GlassyError> {
+        match sqlx::query("DELETE FROM trash WHERE tid = $1")
+            .bind(tid)
+            .execute(self.pool)
+            .await {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    log::error!("couldn't delete trash entry {}: {}", tid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// gets the trashed files of a server that are older than the retention period
+    pub async fn get_expired_trash_entries(&self, url: String, retention_days: u32) -> Result<Vec<TrashEntry>, GlassyError> {
+        match sqlx::query_as("SELECT tid, pid, rel_path, hash, reason, created FROM trash
+            WHERE url = $1 AND created < datetime('now', '-' || $2 || ' days')")
+            .bind(url)
+            .bind(retention_days)
+            .fetch_all(self.pool)
+            .await {
+                Ok(entries) => Ok(entries),
+                Err(err) => {
+                    log::error!("couldn't query expired trash entries: {}", err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn insert_remote_file(&self, file_path: String, pid: i32, commit_id: i32, file_hash: String, changetype: i32, tracked_size: i32) ->Result<(), GlassyError> {
+        let hehe = sqlx::query("INSERT INTO file(filepath, pid, tracked_commitid, tracked_hash, tracked_changetype, in_fs, change_type, tracked_size)
+            VALUES($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT(filepath, pid) DO UPDATE SET
+            tracked_commitid = excluded.tracked_commitid,
+            tracked_hash = excluded.tracked_hash,
+            tracked_changetype = CASE WHEN in_fs = 1 OR excluded.tracked_changetype = 3 THEN excluded.tracked_changetype ELSE 1 END,
+            tracked_size = excluded.tracked_size")
+        .bind(file_path)
+        .bind(pid)
+        .bind(commit_id)
+        .bind(file_hash)
+        .bind(changetype)
+        .bind(0) // in_fs
+        .bind(0) // changetype
+        .bind(tracked_size)
+        .execute(self.pool).await;
+        match hehe {
+            Ok(_owo) => {
+                Ok(())
+            }
+            Err(err) => {
+                log::error!("couldn't insert remote file in project {}: {}", pid, err);
+                Err(err.into())
+            }
+        }
+    }
+
+    pub async fn insert_local_file(&self, rel_path: String, pid: i32, hash: String, filesize: u64) -> Result<(), GlassyError> {
+        let hehe = sqlx::query("INSERT INTO file(filepath, pid, curr_hash, size) VALUES($1, $2, $3, $4)
+        ON CONFLICT(filepath, pid) DO UPDATE SET curr_hash = excluded.curr_hash, size = excluded.size, in_fs = 1")
+        .bind(rel_path)
+        .bind(pid)
+        .bind(hash)
+        .bind(filesize as i64)
+        .execute(self.pool).await;
+        match hehe {
+            Ok(_owo) => { Ok(())}
+            Err(err) => {
+                log::error!("encountered error while saving file to db: {}", err);
+                Err(err.into())
+            }
+        }
+    }
+
+    /// gets the stored fingerprint and current hash of every file in a project that has been hashed before
+    pub async fn get_local_fingerprints(&self, pid: i32) -> Result<HashMap<String, (String, FileFingerprint)>, GlassyError> {
+        let rows = match sqlx::query("SELECT filepath, curr_hash, size, mtime, file_id FROM file WHERE pid = $1 AND curr_hash != '' AND mtime != 0")
+            .bind(pid)
+            .fetch_all(self.pool)
+            .await {
+                Ok(rows) => rows,
+                Err(err) => {
+                    log::error!("couldn't get file fingerprints for project {}: {}", pid, err);
+                    return Err(err.into());
+                }
+        };
+
+        let mut output = HashMap::new();
+        for row in rows {
+            let fingerprint = FileFingerprint {
+                size: row.get::<i64, &str>("size"),
+                mtime: row.get::<i64, &str>("mtime"),
+                file_id: row.get::<i64, &str>("file_id"),
+            };
+            output.insert(row.get::<String, &str>("filepath"), (row.get::<String, &str>("curr_hash"), fingerprint));
+        }
+        Ok(output)
+    }
+
+    /// paths of the project's files that are on the server
+    pub async fn get_tracked_paths(&self, pid: i32) -> Result<HashSet<String>, GlassyError> {
+        match sqlx::query_scalar("SELECT filepath FROM file WHERE pid = $1 AND base_hash != ''")
+            .bind(pid)
+            .fetch_all(self.pool)
+            .await {
+                Ok(paths) => Ok(paths.into_iter().collect()),
+                Err(err) => {
+                    log::error!("couldn't get tracked files for project {}: {}", pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// stores the fingerprint of a local file; must be called after insert_local_file
+    pub async fn set_file_fingerprint(&self, rel_path: String, pid: i32, fingerprint: &FileFingerprint) -> Result<(), GlassyError> {
+        match sqlx::query("UPDATE file SET mtime = $1, file_id = $2 WHERE pid = $3 AND filepath = $4")
+            .bind(fingerprint.mtime)
+            .bind(fingerprint.file_id)
+            .bind(pid)
+            .bind(rel_path)
+            .execute(self.pool)
+            .await {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    log::error!("encountered error while saving file fingerprint to db: {}", err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// forgets every stored fingerprint for a project so the next scan re-hashes all files
+    pub async fn clear_file_fingerprints(&self, pid: i32) -> Result<(), GlassyError> {
+        let _ = sqlx::query("UPDATE file SET mtime = 0, file_id = 0 WHERE pid = $1")
+            .bind(pid)
+            .execute(self.pool)
+            .await;
+        Ok(())
+    }
+
+    // TODO handle errors
+    pub async fn update_change_types(&self, pid: i32) -> Result<(), GlassyError> {
+            // no change - file was un-deleted (e.g., recovered from user's recycle bin)
+        let _ = sqlx::query(
+            "UPDATE file SET change_type = 0 WHERE in_fs = 1 AND change_type = 3 AND pid = $1",
+        )
+        .bind(pid)
+        .execute(self.pool)
+        .await;
+
+        // no change - file was reset
+        let _ = sqlx::query(
+            "UPDATE file SET change_type = 0 WHERE in_fs = 1 AND base_hash == curr_hash AND pid = $1",
+        )
+        .bind(pid)
+        .execute(self.pool)
+        .await;
+
+        // updated file - base hash is different from current hash, and file is tracked, i.e. base hash not empty
+        let _ = sqlx::query(
+            "UPDATE file SET change_type = 2 WHERE in_fs = 1 AND base_hash != curr_hash AND pid = $1 AND base_hash != ''"
+        ).bind(pid).execute(self.pool).await;
+
+        // new file - base hash is different from current hash and file is untracked, i.e. base hash is empty
+        let _ = sqlx::query(
+            "UPDATE file SET change_type = 1 WHERE in_fs = 1 AND base_hash != curr_hash AND pid = $1 AND base_hash == ''"
+        ).bind(pid).execute(self.pool).await;
+
+        // deleted file - file is tracked, i.e. base hash not empty and file wasn't found in folder
+        let _ = sqlx::query(
+            "UPDATE file SET change_type = 3 WHERE in_fs = 0 AND pid = $1 AND base_hash != ''",
+        )
+        .bind(pid)
+        .execute(self.pool)
+        .await;
+
+        // renamed/moved file - pair new files with deleted files that have the same content
+        let _ = self.detect_renames(pid).await;
+
+        // delete entries of files that are untracked and deleted
+        match sqlx::query("DELETE FROM file WHERE in_fs = 0 AND pid = $1 AND base_hash = ''")
+            .bind(pid)
+            .execute(self.pool)
+            .await {
+                Ok(a) => {
+                    log::info!("rows affected: {}",a.rows_affected());
+                },
+                Err(e) => {
+                    log::error!("error deleting entries of untracked and deleted: {}", e)
+                }
+            };
+        Ok(())
+    }
+
+    /// turns (deleted file, new file) pairs with identical content into a single rename.
+    /// the new file gets change_type 4 and remembers where it came from in old_filepath;
+    /// the deleted file keeps change_type 3 so the tracked state for the old path is kept
+    async fn detect_renames(&self, pid: i32) -> Result<(), GlassyError> {
+        let _ = sqlx::query("UPDATE file SET old_filepath = NULL, change_type = CASE WHEN change_type = 4 THEN 1 ELSE change_type END WHERE pid = $1")
+            .bind(pid)
+            .execute(self.pool)
+            .await;
+
+        let deleted: Vec<(String, String)> = match sqlx::query_as(
+            "SELECT filepath, base_hash FROM file WHERE pid = $1 AND in_fs = 0 AND change_type = 3 ORDER BY filepath"
+        )
+        .bind(pid)
+        .fetch_all(self.pool)
+        .await {
+            Ok(rows) => rows,
+            Err(err) => {
+                log::error!("couldn't query deleted files for rename detection: {}", err);
+                return Err(err.into());
+            }
+        };
+        // files outside of the sparse checkout only look deleted, see get_uploads
+        let spec = SparseSpec::new(&self.get_sparse_rules(pid).await.unwrap_or_default());
+        let mut deleted_by_hash: HashMap<String, Vec<String>> = HashMap::new();
+        for (filepath, hash) in deleted.into_iter().filter(|(filepath, _)| spec.includes(filepath)) {
+            deleted_by_hash.entry(hash).or_default().push(filepath);
+        }
+        if deleted_by_hash.is_empty() {
+            return Ok(());
+        }
+
+        let created: Vec<(String, String)> = match sqlx::query_as(
+            "SELECT filepath, curr_hash FROM file WHERE pid = $1 AND in_fs = 1 AND change_type = 1 ORDER BY filepath"
+        )
+        .bind(pid)
+        .fetch_all(self.pool)
+        .await {
+            Ok(rows) => rows,
+            Err(err) => {
+                log::error!("couldn't query new files for rename detection: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        // each deleted file can only be the source of one rename
+        for (filepath, hash) in created {
+            let old_filepath = match deleted_by_hash.get_mut(&hash).and_then(|paths| paths.pop()) {
+                Some(p) => p,
+                None => continue,
+            };
+            let _ = sqlx::query("UPDATE file SET change_type = 4, old_filepath = $1 WHERE pid = $2 AND filepath = $3")
+                .bind(old_filepath)
+                .bind(pid)
+                .bind(filepath)
+                .execute(self.pool)
+                .await;
+        }
+        Ok(())
+    }
+
+    /// marks a file, or every file in a folder, as no longer being on disk
+    pub async fn mark_missing(&self, pid: i32, rel_path: String) -> Result<(), GlassyError> {
+        let folder = rel_path.clone() + "\\";
+        match sqlx::query("UPDATE file SET in_fs = 0 WHERE pid = $1 AND (filepath = $2 OR substr(filepath, 1, length($3)) = $3)")
+            .bind(pid)
+            .bind(rel_path)
+            .bind(folder)
+            .execute(self.pool)
+            .await {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    log::error!("couldn't mark files as missing in project {}: {}", pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// marks a file as still being on disk without touching its stored hash
+    pub async fn mark_present(&self, pid: i32, rel_path: String) -> Result<(), GlassyError> {
+        match sqlx::query("UPDATE file SET in_fs = 1 WHERE pid = $1 AND filepath = $2")
+            .bind(pid)
+            .bind(rel_path)
+            .execute(self.pool)
+            .await {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    log::error!("couldn't mark file as present in project {}: {}", pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn reset_fs_state(&self, pid: i32) -> Result<(), GlassyError> {
+        let _ = sqlx::query("UPDATE file SET in_fs = 0 WHERE pid = $1")
+        .bind(pid)
+        .execute(self.pool)
+        .await;
+        Ok(())
+    }
+
+    /// `reason` is what the journal's files are being put in the project for, see transfer.rs
+    pub async fn create_download_journal(&self, pid: i32, reason: &str) -> Result<i64, GlassyError> {
+        match sqlx::query("INSERT INTO download_journal(pid, reason) VALUES ($1, $2)")
+            .bind(pid)
+            .bind(reason)
+            .execute(self.pool)
+            .await {
+                Ok(res) => Ok(res.last_insert_rowid()),
+                Err(err) => {
+                    log::error!("couldn't create download journal for project {}: {}", pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// records every pending change of a download and marks the journal as committing
+    pub async fn write_journal_entries(&self, jid: i64, entries: &Vec<JournalEntry>) -> Result<(), GlassyError> {
+        let mut tx = match self.pool.begin().await {
+            Ok(tx) => tx,
+            Err(err) => {
+                log::error!("couldn't start transaction for download journal {}: {}", jid, err);
+                return Err(err.into());
+            }
+        };
+        for entry in entries {
+            let res = sqlx::query("INSERT INTO download_journal_entry(jid, idx, rel_path, target, source, backup, download, done)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)")
+                .bind(jid)
+                .bind(entry.idx)
+                .bind(entry.rel_path.clone())
+                .bind(entry.target.clone())
+                .bind(entry.source.clone())
+                .bind(entry.backup.clone())
+                .bind(entry.download)
+                .bind(entry.done)
+                .execute(&mut *tx)
+                .await;
+            if let Err(err) = res {
+                log::error!("couldn't write download journal entry for {}: {}", entry.rel_path, err);
+                return Err(err.into());
+            }
+        }
+        let _ = sqlx::query("UPDATE download_journal SET state = 1 WHERE jid = $1")
+            .bind(jid)
+            .execute(&mut *tx)
+            .await;
+        match tx.commit().await {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                log::error!("couldn't commit download journal {}: {}", jid, err);
+                Err(err.into())
+            }
+        }
+    }
+
+    pub async fn set_journal_entry_done(&self, jid: i64, idx: i64, done: bool) -> Result<(), GlassyError> {
+        match sqlx::query("UPDATE download_journal_entry SET done = $1 WHERE jid = $2 AND idx = $3")
+            .bind(done)
+            .bind(jid)
+            .bind(idx)
+            .execute(self.pool)
+            .await {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    log::error!("couldn't update download journal entry {} of journal {}: {}", idx, jid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// gets every unfinished download journal as (jid, pid, state)
+    pub async fn get_download_journals(&self) -> Result<Vec<(i64, i32, i32)>, GlassyError> {
+        match sqlx::query_as("SELECT jid, pid, state FROM download_journal ORDER BY jid")
+            .fetch_all(self.pool)
+            .await {
+                Ok(journals) => Ok(journals),
+                Err(err) => {
+                    log::error!("couldn't query download journals: {}", err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn get_journal_entries(&self, jid: i64) -> Result<Vec<JournalEntry>, GlassyError> {
+        match sqlx::query_as("SELECT idx, rel_path, target, source, backup, download, done FROM download_journal_entry WHERE jid = $1 ORDER BY idx")
+            .bind(jid)
+            .fetch_all(self.pool)
+            .await {
+                Ok(entries) => Ok(entries),
+                Err(err) => {
+                    log::error!("couldn't query entries of download journal {}: {}", jid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn delete_download_journal(&self, jid: i64) -> Result<(), GlassyError> {
+        let _ = sqlx::query("DELETE FROM download_journal_entry WHERE jid = $1")
+            .bind(jid)
+            .execute(self.pool)
+            .await;
+        let _ = sqlx::query("DELETE FROM download_journal WHERE jid = $1")
+            .bind(jid)
+            .execute(self.pool)
+            .await;
+        Ok(())
+    }
+
+    /// updates the file table for a download whose files are all in place and removes its journal,
+    /// in one transaction so the file table never disagrees with the project folder
+    pub async fn commit_download_journal(&self, jid: i64, pid: i32) -> Result<(), GlassyError> {
+        let entries = self.get_journal_entries(jid).await?;
+        let reason: String = sqlx::query_scalar("SELECT reason FROM download_journal WHERE jid = $1")
+            .bind(jid)
+            .fetch_optional(self.pool)
+            .await?
+            .unwrap_or_default();
+        let mut tx = match self.pool.begin().await {
+            Ok(tx) => tx,
+            Err(err) => {
+                log::error!("couldn't start transaction for download journal {}: {}", jid, err);
+                return Err(err.into());
+            }
+        };
+        for entry in entries {
+            let res = if entry.download && reason == "reset" {
+                // reset files are back to the revision their local edits were made on
+                sqlx::query(
+                    "
+                    UPDATE file SET
+                    curr_hash = base_hash,
+                    in_fs = 1,
+                    change_type = 0,
+                    mtime = 0
+                    WHERE pid = $1 AND filepath = $2
+                    ",
+                )
+                .bind(pid)
+                .bind(entry.rel_path.clone())
+                .execute(&mut *tx)
+                .await
+            } else if entry.download {
+                // TODO instead of using the tracked values
+                // should we compute them instead?
+                sqlx::query(
+                    "
+                    UPDATE file SET
+                    base_hash = tracked_hash,
+                    curr_hash = tracked_hash,
+                    base_commitid = tracked_commitid,
+                    size = tracked_size,
+                    in_fs = 1,
+                    change_type = 0,
+                    mtime = 0
+                    WHERE pid = $1 AND filepath = $2
+                    ",
+                )
+                .bind(pid)
+                .bind(entry.rel_path.clone())
+                .execute(&mut *tx)
+                .await
+            } else {
+                sqlx::query("DELETE FROM file WHERE pid = $1 AND filepath = $2")
+                    .bind(pid)
+                    .bind(entry.rel_path.clone())
+                    .execute(&mut *tx)
+                    .await
+            };
+            if let Err(err) = res {
+                log::error!("couldn't update file table for {}: {}", entry.rel_path, err);
+                return Err(err.into());
+            }
+        }
+        let _ = sqlx::query("DELETE FROM download_journal_entry WHERE jid = $1")
+            .bind(jid)
+            .execute(&mut *tx)
+            .await;
+        let _ = sqlx::query("DELETE FROM download_journal WHERE jid = $1")
+            .bind(jid)
+            .execute(&mut *tx)
+            .await;
+        match tx.commit().await {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                log::error!("couldn't commit file table changes for download journal {}: {}", jid, err);
+                Err(err.into())
+            }
+        }
+    }
+
+    /// stores the list of blocks a file is made of and adds a reference to each block.
+    /// an identical manifest is left alone and Ok(false) is returned; a different one, e.g. after
+    /// the server switched chunking strategies, replaces it and drops its references
+    pub async fn save_cache_manifest(&self, url: String, file_hash: String, chunks: &Vec<FileChunk>) -> Result<bool, GlassyError> {
+        let mut tx = match self.pool.begin().await {
+            Ok(tx) => tx,
+            Err(err) => {
+                log::error!("couldn't start transaction for manifest of {}: {}", file_hash, err);
+                return Err(err.into());
+            }
+        };
+        let existing: Vec<(String,)> = match sqlx::query_as("SELECT block_hash FROM cache_manifest
+            WHERE url = $1 AND file_hash = $2 ORDER BY chunk_index")
+            .bind(url.clone())
+            .bind(file_hash.clone())
+            .fetch_all(&mut *tx)
+            .await {
+                Ok(rows) => rows,
+                Err(err) => {
+                    log::error!("couldn't query manifest of {}: {}", file_hash, err);
+                    return Err(err.into());
+                }
+        };
+        let mut sorted: Vec<&FileChunk> = chunks.iter().collect();
+        sorted.sort_by_key(|chunk| chunk.chunk_index);
+        if existing.len() == sorted.len() && existing.iter().zip(sorted.iter()).all(|((old,), new)| *old == new.block_hash) {
+            return Ok(false);
+        }
+
+        let old_blocks: HashSet<String> = existing.into_iter().map(|(block_hash,)| block_hash).collect();
+        for block_hash in old_blocks {
+            let _ = sqlx::query("UPDATE cache_block SET refcount = refcount - 1 WHERE url = $1 AND block_hash = $2")
+                .bind(url.clone())
+                .bind(block_hash)
+                .execute(&mut *tx)
+                .await;
+        }
+        let _ = sqlx::query("DELETE FROM cache_manifest WHERE url = $1 AND file_hash = $2")
+            .bind(url.clone())
+            .bind(file_hash.clone())
+            .execute(&mut *tx)
+            .await;
+
+        let mut referenced: HashSet<String> = HashSet::new();
+        for chunk in chunks {
+            let res = sqlx::query("INSERT INTO cache_manifest(url, file_hash, chunk_index, block_hash, offset, size)
+                VALUES ($1, $2, $3, $4, $5, $6)")
+                .bind(url.clone())
+                .bind(file_hash.clone())
+                .bind(chunk.chunk_index)
+                .bind(chunk.block_hash.clone())
+                .bind(chunk.offset.map(|offset| offset as i64))
+                .bind(chunk.size.map(|size| size as i64))
+                .execute(&mut *tx)
+                .await;
+            if let Err(err) = res {
+                log::error!("couldn't save chunk {} of {} to the manifest: {}", chunk.chunk_index, file_hash, err);
+                return Err(err.into());
+            }
+
+            // a block that appears twice in one file is still one reference
+            if !referenced.insert(chunk.block_hash.clone()) {
+                continue;
+            }
+            let res = sqlx::query("INSERT INTO cache_block(url, block_hash, size, refcount) VALUES ($1, $2, $3, 1)
+                ON CONFLICT(url, block_hash) DO UPDATE SET refcount = refcount + 1, size = COALESCE(excluded.size, size)")
+                .bind(url.clone())
+                .bind(chunk.block_hash.clone())
+                .bind(chunk.size.map(|size| size as i64))
+                .execute(&mut *tx)
+                .await;
+            if let Err(err) = res {
+                log::error!("couldn't add reference to block {}: {}", chunk.block_hash, err);
+                return Err(err.into());
+            }
+        }
+
+        match tx.commit().await {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                log::error!("couldn't commit manifest of {}: {}", file_hash, err);
+                Err(err.into())
+            }
+        }
+    }
+
+    /// gets the blocks a file is made of, empty if the file isn't in the cache
+    pub async fn get_cache_manifest(&self, url: String, file_hash: String) -> Result<Vec<FileChunk>, GlassyError> {
+        let output = sqlx::query("SELECT chunk_index, block_hash, offset, size FROM cache_manifest
+            WHERE url = $1 AND file_hash = $2 ORDER BY chunk_index")
+            .bind(url)
+            .bind(file_hash.clone())
+            .fetch_all(self.pool)
+            .await;
+        match output {
+            Ok(rows) => Ok(rows
+                .iter()
+                .map(|row| FileChunk {
+                    s3_url: "".to_string(),
+                    block_hash: row.get::<String, &str>("block_hash"),
+                    chunk_index: row.get::<i64, &str>("chunk_index"),
+                    file_hash: file_hash.clone(),
+                    offset: row.get::<Option<i64>, &str>("offset").map(|offset| offset as u64),
+                    size: row.get::<Option<i64>, &str>("size").map(|size| size as u64),
+                })
+                .collect()),
+            Err(err) => {
+                log::error!("couldn't query manifest of {}: {}", file_hash, err);
+                Err(err.into())
+            }
+        }
+    }
+
+    /// records blocks that are now in the block store, as (block_hash, size in bytes)
+    pub async fn set_blocks_present(&self, url: String, blocks: &Vec<(String, u64)>) -> Result<(), GlassyError> {
+        let mut tx = match self.pool.begin().await {
+            Ok(tx) => tx,
+            Err(err) => {
+                log::error!("couldn't start transaction to record cached blocks: {}", err);
+                return Err(err.into());
+            }
+        };
+        for (block_hash, size) in blocks {
+            let res = sqlx::query("INSERT INTO cache_block(url, block_hash, size, refcount, present) VALUES ($1, $2, $3, 0, 1)
+                ON CONFLICT(url, block_hash) DO UPDATE SET size = excluded.size, present = 1")
+                .bind(url.clone())
+                .bind(block_hash.clone())
+                .bind(*size as i64)
+                .execute(&mut *tx)
+                .await;
+            if let Err(err) = res {
+                log::error!("couldn't record cached block {}: {}", block_hash, err);
+                return Err(err.into());
+            }
+        }
+        match tx.commit().await {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                log::error!("couldn't commit cached blocks: {}", err);
+                Err(err.into())
+            }
+        }
+    }
+
+    /// gets the size in bytes of every block in the block store
+    pub async fn get_cached_size(&self, url: String) -> Result<u64, GlassyError> {
+        match sqlx::query("SELECT COALESCE(SUM(size), 0) AS total FROM cache_block WHERE url = $1 AND present = 1")
+            .bind(url)
+            .fetch_one(self.pool)
+            .await {
+                Ok(row) => Ok(row.get::<i64, &str>("total") as u64),
+                Err(err) => {
+                    log::error!("couldn't query cache size: {}", err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// marks a cached file as used, last_access is unix time in ms
+    pub async fn touch_cache_file(&self, url: String, file_hash: String, last_access: i64) -> Result<(), GlassyError> {
+        match sqlx::query("INSERT INTO cache_file(url, file_hash, last_access) VALUES ($1, $2, $3)
+            ON CONFLICT(url, file_hash) DO UPDATE SET last_access = excluded.last_access")
+            .bind(url)
+            .bind(file_hash.clone())
+            .bind(last_access)
+            .execute(self.pool)
+            .await {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    log::error!("couldn't update last access of cached file {}: {}", file_hash, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// gets the cached file that was used least recently
+    pub async fn get_lru_cache_file(&self, url: String) -> Result<Option<String>, GlassyError> {
+        match sqlx::query("SELECT file_hash FROM cache_file WHERE url = $1 ORDER BY last_access ASC LIMIT 1")
+            .bind(url)
+            .fetch_optional(self.pool)
+            .await {
+                Ok(row) => Ok(row.map(|row| row.get::<String, &str>("file_hash"))),
+                Err(err) => {
+                    log::error!("couldn't query least recently used cached file: {}", err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// removes a file from the cache and drops its references to blocks.
+    /// returns every block that is no longer used by any file, which can be deleted from the block store
+    pub async fn drop_cache_manifest(&self, url: String, file_hash: String) -> Result<Vec<String>, GlassyError> {
+        let mut tx = match self.pool.begin().await {
+            Ok(tx) => tx,
+            Err(err) => {
+                log::error!("couldn't start transaction to evict {}: {}", file_hash, err);
+                return Err(err.into());
+            }
+        };
+        let _ = sqlx::query("UPDATE cache_block SET refcount = refcount - 1 WHERE url = $1 AND block_hash IN
+            (SELECT DISTINCT block_hash FROM cache_manifest WHERE url = $1 AND file_hash = $2)")
+            .bind(url.clone())
+            .bind(file_hash.clone())
+            .execute(&mut *tx)
+            .await;
+        let _ = sqlx::query("DELETE FROM cache_manifest WHERE url = $1 AND file_hash = $2")
+            .bind(url.clone())
+            .bind(file_hash.clone())
+            .execute(&mut *tx)
+            .await;
+        let _ = sqlx::query("DELETE FROM cache_file WHERE url = $1 AND file_hash = $2")
+            .bind(url.clone())
+            .bind(file_hash.clone())
+            .execute(&mut *tx)
+            .await;
+        let orphans: Vec<(String,)> = match sqlx::query_as("DELETE FROM cache_block WHERE url = $1 AND refcount <= 0 RETURNING block_hash")
+            .bind(url)
+            .fetch_all(&mut *tx)
+            .await {
+                Ok(rows) => rows,
+                Err(err) => {
+                    log::error!("couldn't remove unused blocks: {}", err);
+                    return Err(err.into());
+                }
+        };
+        match tx.commit().await {
+            Ok(_) => Ok(orphans.into_iter().map(|(block_hash,)| block_hash).collect()),
+            Err(err) => {
+                log::error!("couldn't commit eviction of {}: {}", file_hash, err);
+                Err(err.into())
+            }
+        }
+    }
+
+    /// forgets every manifest and block of a server's cache, e.g. when the cache folder is deleted
+    pub async fn clear_cache_tables(&self, url: String) -> Result<(), GlassyError> {
+        let _ = sqlx::query("DELETE FROM cache_file WHERE url = $1")
+            .bind(url.clone())
+            .execute(self.pool)
+            .await;
+        let _ = sqlx::query("DELETE FROM cache_manifest WHERE url = $1")
+            .bind(url.clone())
+            .execute(self.pool)
+            .await;
+        let _ = sqlx::query("DELETE FROM cache_block WHERE url = $1")
+            .bind(url)
+            .execute(self.pool)
+            .await;
+        Ok(())
+    }
+
+    /// records an upload and its files before anything is committed
+    pub async fn create_commit_job(&self, pid: i32, message: String, files: &Vec<CommitJobFile>) -> Result<i64, GlassyError> {
+        let mut tx = match self.pool.begin().await {
+            Ok(tx) => tx,
+            Err(err) => {
+                log::error!("couldn't start transaction for commit job of project {}: {}", pid, err);
+                return Err(err.into());
+            }
+        };
+        let cjid = match sqlx::query("INSERT INTO commit_job(pid, message) VALUES ($1, $2)")
+            .bind(pid)
+            .bind(message)
+            .execute(&mut *tx)
+            .await {
+                Ok(res) => res.last_insert_rowid(),
+                Err(err) => {
+                    log::error!("couldn't create commit job for project {}: {}", pid, err);
+                    return Err(err.into());
+                }
+        };
+        for file in files {
+            let res = sqlx::query("INSERT INTO commit_job_file(cjid, idx, part, source, path, hash, changetype, commitid, done)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)")
+                .bind(cjid)
+                .bind(file.idx)
+                .bind(file.part)
+                .bind(file.source.clone())
+                .bind(file.path.clone())
+                .bind(file.hash.clone())
+                .bind(file.changetype)
+                .bind(file.commitid)
+                .bind(file.done)
+                .execute(&mut *tx)
+                .await;
+            if let Err(err) = res {
+                log::error!("couldn't write commit job file {}: {}", file.path, err);
+                return Err(err.into());
+            }
+        }
+        match tx.commit().await {
+            Ok(_) => Ok(cjid),
+            Err(err) => {
+                log::error!("couldn't commit commit job for project {}: {}", pid, err);
+                Err(err.into())
+            }
+        }
+    }
+
+    /// gets the project's unfinished upload, if there is one
+    pub async fn get_commit_job(&self, pid: i32) -> Result<Option<CommitJob>, GlassyError> {
+        match sqlx::query_as("SELECT cjid, pid, message, created FROM commit_job WHERE pid = $1 ORDER BY cjid LIMIT 1")
+            .bind(pid)
+            .fetch_optional(self.pool)
+            .await {
+                Ok(job) => Ok(job),
+                Err(err) => {
+                    log::error!("couldn't query commit job for project {}: {}", pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn get_commit_job_files(&self, cjid: i64) -> Result<Vec<CommitJobFile>, GlassyError> {
+        match sqlx::query_as("SELECT idx, part, source, path, hash, changetype, commitid, done FROM commit_job_file WHERE cjid = $1 ORDER BY idx")
+            .bind(cjid)
+            .fetch_all(self.pool)
+            .await {
+                Ok(files) => Ok(files),
+                Err(err) => {
+                    log::error!("couldn't query files of commit job {}: {}", cjid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn set_commit_job_part(&self, cjid: i64, part: i64, commit_id: i32, done: bool) -> Result<(), GlassyError> {
+        match sqlx::query("UPDATE commit_job_file SET commitid = $1, done = $2 WHERE cjid = $3 AND part = $4")
+            .bind(commit_id)
+            .bind(done)
+            .bind(cjid)
+            .bind(part)
+            .execute(self.pool)
+            .await {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    log::error!("couldn't update part {} of commit job {}: {}", part, cjid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn delete_commit_job(&self, cjid: i64) -> Result<(), GlassyError> {
+        let _ = sqlx::query("DELETE FROM commit_job_file WHERE cjid = $1")
+            .bind(cjid)
+            .execute(self.pool)
+            .await;
+        let _ = sqlx::query("DELETE FROM commit_job WHERE cjid = $1")
+            .bind(cjid)
+            .execute(self.pool)
+            .await;
+        Ok(())
+    }
+
+    /// the patterns of the project's sparse checkout in the order they were added, empty if it isn't sparse
+    pub async fn get_sparse_rules(&self, pid: i32) -> Result<Vec<String>, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query_scalar("SELECT pattern FROM sparse_rule WHERE pid = $1 AND url = $2 ORDER BY srid")
+            .bind(pid)
+            .bind(url)
+            .fetch_all(self.pool)
+            .await {
+                Ok(patterns) => Ok(patterns),
+                Err(err) => {
+                    log::error!("couldn't get sparse checkout of project {}: {}", pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn add_sparse_rule(&self, pid: i32, pattern: String) -> Result<bool, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query("INSERT INTO sparse_rule(pid, url, pattern) VALUES ($1, $2, $3)")
+            .bind(pid)
+            .bind(url)
+            .bind(pattern)
+            .execute(self.pool)
+            .await {
+                Ok(_) => Ok(true),
+                Err(err) => {
+                    log::error!("couldn't add to sparse checkout of project {}: {}", pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn remove_sparse_rule(&self, pid: i32, pattern: String) -> Result<bool, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query("DELETE FROM sparse_rule WHERE pid = $1 AND url = $2 AND pattern = $3")
+            .bind(pid)
+            .bind(url)
+            .bind(pattern)
+            .execute(self.pool)
+            .await {
+                Ok(res) => Ok(res.rows_affected() > 0),
+                Err(err) => {
+                    log::error!("couldn't remove from sparse checkout of project {}: {}", pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// local files whose references haven't been read since they last changed, with their current hash
+    pub async fn get_unread_references(&self, pid: i32) -> Result<Vec<(String, String)>, GlassyError> {
+        match sqlx::query_as("SELECT filepath, curr_hash FROM file WHERE pid = $1 AND in_fs = 1 AND curr_hash != '' AND curr_hash != refs_hash")
+            .bind(pid)
+            .fetch_all(self.pool)
+            .await {
+                Ok(files) => Ok(files),
+                Err(err) => {
+                    log::error!("couldn't get files to read references of in project {}: {}", pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// replaces the references of `filepath`, as written in its revision with `hash`
+    pub async fn set_references(&self, pid: i32, filepath: &String, hash: &String, references: &[String]) -> Result<(), GlassyError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM file_reference WHERE pid = $1 AND filepath = $2")
+            .bind(pid)
+            .bind(filepath)
+            .execute(&mut *tx)
+            .await?;
+        for reference in references {
+            sqlx::query("INSERT OR IGNORE INTO file_reference(pid, filepath, reference) VALUES ($1, $2, $3)")
+                .bind(pid)
+                .bind(filepath)
+                .bind(reference)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("UPDATE file SET refs_hash = $1 WHERE pid = $2 AND filepath = $3")
+            .bind(hash)
+            .bind(pid)
+            .bind(filepath)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// the references of each local file in the project, as written in the file. see references.rs
+    pub async fn get_references(&self, pid: i32) -> Result<HashMap<String, Vec<String>>, GlassyError> {
+        let rows: Vec<(String, String)> = match sqlx::query_as(
+            "SELECT r.filepath, r.reference FROM file_reference r JOIN file f ON f.pid = r.pid AND f.filepath = r.filepath
+            WHERE r.pid = $1 AND f.in_fs = 1 ORDER BY r.filepath, r.reference")
+            .bind(pid)
+            .fetch_all(self.pool)
+            .await {
+                Ok(rows) => rows,
+                Err(err) => {
+                    log::error!("couldn't get file references of project {}: {}", pid, err);
+                    return Err(err.into());
+                }
+        };
+        let mut output: HashMap<String, Vec<String>> = HashMap::new();
+        for (filepath, reference) in rows {
+            output.entry(filepath).or_default().push(reference);
+        }
+        Ok(output)
+    }
+
+    /// paths of every file in the project, local or on the server
+    pub async fn get_project_paths(&self, pid: i32) -> Result<Vec<String>, GlassyError> {
+        match sqlx::query_scalar("SELECT filepath FROM file WHERE pid = $1 ORDER BY filepath")
+            .bind(pid)
+            .fetch_all(self.pool)
+            .await {
+                Ok(paths) => Ok(paths),
+                Err(err) => {
+                    log::error!("couldn't get files of project {}: {}", pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// replaces the project's cached locks. locks on files that aren't in the file table aren't cached
+    pub async fn save_locks(&self, pid: i32, locks: &[FileLock]) -> Result<(), GlassyError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE file SET lock_user = NULL, locked_at = NULL WHERE pid = $1 AND lock_user IS NOT NULL")
+            .bind(pid)
+            .execute(&mut *tx)
+            .await?;
+        for lock in locks {
+            sqlx::query("UPDATE file SET lock_user = $1, locked_at = $2 WHERE pid = $3 AND filepath = $4")
+                .bind(lock.user_id.clone())
+                .bind(lock.created.clone())
+                .bind(pid)
+                .bind(lock.path.clone())
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// the project's locks as of the last time they were fetched
+    pub async fn get_locks(&self, pid: i32) -> Result<Vec<FileLock>, GlassyError> {
+        match sqlx::query_as("SELECT filepath AS path, lock_user AS user_id, COALESCE(locked_at, '') AS created FROM file
+            WHERE pid = $1 AND lock_user IS NOT NULL ORDER BY filepath")
+            .bind(pid)
+            .fetch_all(self.pool)
+            .await {
+                Ok(locks) => Ok(locks),
+                Err(err) => {
+                    log::error!("couldn't get locks of project {}: {}", pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// whether the project's files are kept read-only until they are checked out
+    pub async fn get_readonly_checkout(&self, pid: i32) -> Result<bool, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query("SELECT readonly_checkout FROM project WHERE pid = $1 AND url = $2")
+            .bind(pid)
+            .bind(url)
+            .fetch_optional(self.pool)
+            .await {
+                Ok(row) => Ok(row.is_some_and(|row| row.get::<i64, &str>("readonly_checkout") == 1)),
+                Err(err) => {
+                    log::error!("could not retrieve read-only checkout setting due to db error: {}", err);
+                    Ok(false)
+                }
+        }
+    }
+
+    pub async fn set_readonly_checkout(&self, pid: i32, readonly: bool) -> Result<bool, GlassyError> {
+        let url = self.get_active_server().await?;
+        match sqlx::query("UPDATE project SET readonly_checkout = $1 WHERE pid = $2 AND url = $3")
+            .bind(if readonly { 1 } else { 0 })
+            .bind(pid)
+            .bind(url)
+            .execute(self.pool)
+            .await {
+                Ok(res) => Ok(res.rows_affected() > 0),
+                Err(err) => {
+                    log::error!("could not set read-only checkout setting due to db error: {}", err);
+                    Ok(false)
+                }
+        }
+    }
+
+    pub async fn add_job(&self, url: String, pid: i32, kind: &str, task: String, user_id: String) -> Result<i64, GlassyError> {
+        match sqlx::query("INSERT INTO job(url, pid, kind, task, user_id) VALUES ($1, $2, $3, $4, $5)")
+            .bind(url)
+            .bind(pid)
+            .bind(kind)
+            .bind(task)
+            .bind(user_id)
+            .execute(self.pool)
+            .await {
+                Ok(res) => Ok(res.last_insert_rowid()),
+                Err(err) => {
+                    log::error!("couldn't queue {} job for project {}: {}", kind, pid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    pub async fn get_job(&self, jid: i64) -> Result<Option<QueuedJob>, GlassyError> {
+        match sqlx::query_as("SELECT jid, pid, kind, task, user_id, state, attempts, error, created FROM job WHERE jid = $1")
+            .bind(jid)
+            .fetch_optional(self.pool)
+            .await {
+                Ok(job) => Ok(job),
+                Err(err) => {
+                    log::error!("couldn't query job {}: {}", jid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// gets a server's jobs that are waiting to run, oldest first
+    pub async fn get_queued_jobs(&self, url: String) -> Result<Vec<QueuedJob>, GlassyError> {
+        match sqlx::query_as("SELECT jid, pid, kind, task, user_id, state, attempts, error, created FROM job WHERE url = $1 AND state = 'queued' ORDER BY jid")
+            .bind(url)
+            .fetch_all(self.pool)
+            .await {
+                Ok(jobs) => Ok(jobs),
+                Err(err) => {
+                    log::error!("couldn't query queued jobs: {}", err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// gets a server's latest jobs, newest first
+    pub async fn get_jobs(&self, url: String, limit: u32) -> Result<Vec<QueuedJob>, GlassyError> {
+        match sqlx::query_as("SELECT jid, pid, kind, task, user_id, state, attempts, error, created FROM job WHERE url = $1 ORDER BY jid DESC LIMIT $2")
+            .bind(url)
+            .bind(limit)
+            .fetch_all(self.pool)
+            .await {
+                Ok(jobs) => Ok(jobs),
+                Err(err) => {
+                    log::error!("couldn't query jobs: {}", err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// marks a job as running and counts the attempt
+    pub async fn start_job(&self, jid: i64) -> Result<(), GlassyError> {
+        match sqlx::query("UPDATE job SET state = 'running', attempts = attempts + 1 WHERE jid = $1")
+            .bind(jid)
+            .execute(self.pool)
+            .await {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    log::error!("couldn't start job {}: {}", jid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// `state` is done, failed or cancelled
+    pub async fn finish_job(&self, jid: i64, state: &str, result: Option<String>, error: Option<String>) -> Result<(), GlassyError> {
+        match sqlx::query("UPDATE job SET state = $1, result = $2, error = $3 WHERE jid = $4")
+            .bind(state)
+            .bind(result)
+            .bind(error)
+            .bind(jid)
+            .execute(self.pool)
+            .await {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    log::error!("couldn't finish job {}: {}", jid, err);
+                    Err(err.into())
+                }
+        }
+    }
+
+    /// cancels a job that hasn't started yet. returns false if it already started
+    pub async fn cancel_queued_job(&self, jid: i64) -> Result<bool, GlassyError> {
+        let res = sqlx::query("UPDATE job SET state = 'cancelled' WHERE jid = $1 AND state = 'queued'")
+            .bind(jid)
+            .execute(self.pool)
+            .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    /// puts jobs that were running when the app closed back in the queue,
+    /// unless they were started `max_attempts` times already, then they are failed with `error`
+    pub async fn requeue_interrupted_jobs(&self, max_attempts: i64, error: String) -> Result<(), GlassyError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE job SET state = 'failed', error = $1 WHERE state = 'running' AND attempts >= $2")
+            .bind(error)
+            .bind(max_attempts)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE job SET state = 'queued' WHERE state = 'running'")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// forgets finished jobs older than `days`
+    pub async fn prune_jobs(&self, days: u32) -> Result<(), GlassyError> {
+        let _ = sqlx::query("DELETE FROM job WHERE state IN ('done', 'failed', 'cancelled') AND created < datetime('now', $1)")
+            .bind(format!("-{} days", days))
+            .execute(self.pool)
+            .await;
+        Ok(())
+    }
+} // end impl DataAcessLayer<'_>
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::SqlitePool;
+
+    #[sqlx::test]
+    async fn test_cache_setting(pool: SqlitePool) {
+        let dal = DataAccessLayer::new(&pool);
+        init_db(&pool).await;
+
+        // verify initial settings
+        let res = dal.get_cache_setting().await.unwrap();
+        assert!(!res);
+
+        // update settings
+        let _ = dal.update_cache_setting(true).await.unwrap();
+
+        // verify cache setting was updated
+        let res = dal.get_cache_setting().await.unwrap();
+        assert!(res);
+    }
+
+    #[sqlx::test]
+    async fn test_upload_untracked_files(pool: SqlitePool) {
+        let dal = DataAccessLayer::new(&pool);
+
+        init_db(&pool).await;
+        let _ = dal.insert_local_file("path/to/file".to_string(), 0, "abcd".to_string(), 43).await;
+        let _ = dal.insert_local_file("path/to/file2".to_string(), 0, "sf".to_string(), 43).await;
+        let _ = dal.insert_local_file("abc/def/ghi".to_string(), 0, "xyz".to_string(), 43).await;
+
+        // call function under test
+        let _ = dal.update_change_types(0).await;
+        let uploads = dal.get_uploads(0).await.unwrap();
+        let mut err_flag = false;
+        for owo in uploads.clone() {
+            if owo.change_type != ChangeType::Create {
+                err_flag = true;
+            }
+        }
+
+        assert!(!err_flag); // all of the inserted files are new, so they should all be changetype create
+        assert_eq!(uploads.len(), 3); // 3 files were added in add_local_files
+        assert_eq!(dal.get_downloads(0).await.unwrap().len(), 0); // no downloads
+        //assert_eq!(dal.get_conflicts(0).await.unwrap().len(), 0); // TODO
+
+    }
+
+    #[sqlx::test]
+    async fn test_download_for_fresh_install(pool: SqlitePool) {
+        let dal = DataAccessLayer::new(&pool);
+        init_db(&pool).await;
+        let _ = dal.clear_file_table().await;
+        let _ = dal.reset_fs_state(0).await;
+        let _ = dal.update_change_types(0).await;
+        let _ = dal.insert_remote_file("path/to/file".to_string(), 0, 13, "abcd".to_string(), ChangeType::Create as i32, 132).await;
+        let _ = dal.insert_remote_file("path/to/file2".to_string(), 0, 6, "sss".to_string(), ChangeType::Create as i32, 132).await;
+        let _ = dal.insert_remote_file("abc/def/ghi".to_string(), 0, 14, "xyz".to_string(), ChangeType::Create as i32, 132).await;
+        let _ = dal.insert_remote_file("abc/def/hello".to_string(), 0, 12, "hh".to_string(), ChangeType::Delete as i32, 132).await;
+
+        // FIXME investigate this
+        // this fails - update_change_types gets called before remote files are added to table
+        // so currently (241216) this is 'unrealistic'
+        // it works IRL, but this feels like it shouldnt fail
+        //let _ = dal.update_change_types(0).await;
+
+        let downloads = dal.get_downloads(0).await.unwrap();
+        assert_eq!(downloads.len(), 3); // we don't need to download abc/def/hello
+        assert_eq!(dal.get_uploads(0).await.unwrap().len(), 0); // no uploads
+        // TODO conflicts
+    }
+
+    #[sqlx::test]
+    async fn test_local_fingerprints(pool: SqlitePool) {
+        let dal = DataAccessLayer::new(&pool);
+        init_db(&pool).await;
+        let fingerprint = FileFingerprint { size: 43, mtime: 1734307200000000000, file_id: 12 };
+        let _ = dal.insert_local_file("path/to/file".to_string(), 0, "abcd".to_string(), 43).await;
+        let _ = dal.insert_local_file("path/to/file2".to_string(), 0, "sf".to_string(), 43).await;
+        let _ = dal.set_file_fingerprint("path/to/file".to_string(), 0, &fingerprint).await;
+
+        // only files with a stored fingerprint are returned
+        let fingerprints = dal.get_local_fingerprints(0).await.unwrap();
+        assert_eq!(fingerprints.len(), 1);
+        assert_eq!(fingerprints.get("path/to/file"), Some(&("abcd".to_string(), fingerprint)));
+
+        // downloading over a file invalidates its fingerprint
+        let _ = dal.update_downloaded_file_entry(0, "path/to/file".to_string()).await;
+        assert_eq!(dal.get_local_fingerprints(0).await.unwrap().len(), 0);
+    }
+
+    #[sqlx::test]
+    async fn test_mark_missing(pool: SqlitePool) {
+        let dal = DataAccessLayer::new(&pool);
+        init_db(&pool).await;
+        let _ = dal.insert_local_file("Parts\\bracket.SLDPRT".to_string(), 0, "abcd".to_string(), 43).await;
+        let _ = dal.insert_local_file("Parts\\Fasteners\\bolt.SLDPRT".to_string(), 0, "sf".to_string(), 43).await;
+        let _ = dal.insert_local_file("Parts_old\\bracket.SLDPRT".to_string(), 0, "xyz".to_string(), 43).await;
+
+        // removing a folder marks everything inside it, but not folders that share its prefix
+        let _ = dal.mark_missing(0, "Parts".to_string()).await;
+        let _ = dal.update_change_types(0).await;
+        let uploads = dal.get_uploads(0).await.unwrap();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].filepath, "Parts_old\\bracket.SLDPRT");
+    }
+
+    #[sqlx::test]
+    async fn test_detect_renames(pool: SqlitePool) {
+        let dal = DataAccessLayer::new(&pool);
+        init_db(&pool).await;
+
+        // two tracked files, both already uploaded at commit 3
+        let _ = dal.insert_local_file("Parts\\bracket.SLDPRT".to_string(), 0, "abcd".to_string(), 43).await;
+        let _ = dal.insert_local_file("Parts\\bolt.SLDPRT".to_string(), 0, "sf".to_string(), 43).await;
+        let _ = sqlx::query("UPDATE file SET base_hash = curr_hash, tracked_hash = curr_hash, base_commitid = 3, tracked_commitid = 3")
+            .execute(&pool).await;
+
+        // bracket gets moved, bolt gets deleted, and an unrelated file is added
+        let _ = dal.reset_fs_state(0).await;
+        let _ = dal.insert_local_file("Suspension\\bracket.SLDPRT".to_string(), 0, "abcd".to_string(), 43).await;
+        let _ = dal.insert_local_file("Suspension\\upright.SLDPRT".to_string(), 0, "xyz".to_string(), 43).await;
+        let _ = dal.update_change_types(0).await;
+
+        let uploads = dal.get_uploads(0).await.unwrap();
+        assert_eq!(uploads.len(), 3);
+        let renamed = uploads.iter().find(|f| f.filepath == "Suspension\\bracket.SLDPRT").unwrap();
+        assert!(renamed.change_type == ChangeType::Rename);
+        assert_eq!(renamed.old_filepath, Some("Parts\\bracket.SLDPRT".to_string()));
+        let deleted = uploads.iter().find(|f| f.filepath == "Parts\\bolt.SLDPRT").unwrap();
+        assert!(deleted.change_type == ChangeType::Delete);
+        let created = uploads.iter().find(|f| f.filepath == "Suspension\\upright.SLDPRT").unwrap();
+        assert!(created.change_type == ChangeType::Create);
+
+        // moving the file back undoes the rename
+        let _ = dal.reset_fs_state(0).await;
+        let _ = dal.insert_local_file("Parts\\bracket.SLDPRT".to_string(), 0, "abcd".to_string(), 43).await;
+        let _ = dal.insert_local_file("Parts\\bolt.SLDPRT".to_string(), 0, "sf".to_string(), 43).await;
+        let _ = dal.update_change_types(0).await;
+        assert_eq!(dal.get_uploads(0).await.unwrap().len(), 0);
+    }
+
+    #[sqlx::test]
+    async fn test_commit_download_journal(pool: SqlitePool) {
+        let dal = DataAccessLayer::new(&pool);
+        init_db(&pool).await;
+        let _ = dal.insert_remote_file("path\\to\\file".to_string(), 0, 13, "abcd".to_string(), ChangeType::Create as i32, 132).await;
+        let _ = dal.insert_remote_file("path\\to\\old".to_string(), 0, 13, "sss".to_string(), ChangeType::Delete as i32, 132).await;
+
+        let jid = dal.create_download_journal(0, "download").await.unwrap();
+        let entries = vec![
+            JournalEntry { idx: 0, rel_path: "path\\to\\file".to_string(), target: "t".to_string(), source: "s".to_string(), backup: "b".to_string(), download: true, done: false },
+            JournalEntry { idx: 1, rel_path: "path\\to\\old".to_string(), target: "t2".to_string(), source: "".to_string(), backup: "b2".to_string(), download: false, done: false },
+        ];
+        dal.write_journal_entries(jid, &entries).await.unwrap();
+        let _ = dal.set_journal_entry_done(jid, 0, true).await;
+
+        // journal survives until it is committed
+        let journals = dal.get_download_journals().await.unwrap();
+        assert_eq!(journals, vec![(jid, 0, 1)]);
+        let stored = dal.get_journal_entries(jid).await.unwrap();
+        assert_eq!(stored.len(), 2);
+        assert!(stored[0].done && !stored[1].done);
+
+        dal.commit_download_journal(jid, 0).await.unwrap();
+        assert_eq!(dal.get_download_journals().await.unwrap().len(), 0);
+        let file = dal.get_file_info(0, "path\\to\\file".to_string()).await.unwrap();
+        assert_eq!(file.hash, "abcd");
+        assert!(file.in_fs);
+        assert!(dal.get_file_info(0, "path\\to\\old".to_string()).await.is_err());
+    }
+
+    #[sqlx::test]
+    async fn test_cache_manifest(pool: SqlitePool) {
+        let dal = DataAccessLayer::new(&pool);
+        init_db(&pool).await;
+        let chunk = |file_hash: &str, chunk_index: i64, block_hash: &str| FileChunk {
+            s3_url: "s3".to_string(),
+            block_hash: block_hash.to_string(),
+            chunk_index,
+            file_hash: file_hash.to_string(),
+            offset: Some(chunk_index as u64 * 10),
+            size: Some(10),
+        };
+        let refcount = |block_hash: &'static str| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query("SELECT refcount FROM cache_block WHERE url = 'url' AND block_hash = $1")
+                    .bind(block_hash)
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap()
+                    .get::<i64, &str>("refcount")
+            }
+        };
+
+        // two revisions of a file that share their first block
+        let rev1 = vec![chunk("rev1", 0, "aaaa"), chunk("rev1", 1, "bbbb")];
+        let rev2 = vec![chunk("rev2", 0, "aaaa"), chunk("rev2", 1, "cccc"), chunk("rev2", 2, "cccc")];
+        assert!(dal.save_cache_manifest("url".to_string(), "rev1".to_string(), &rev1).await.unwrap());
+        assert!(dal.save_cache_manifest("url".to_string(), "rev2".to_string(), &rev2).await.unwrap());
+        assert_eq!(refcount("aaaa").await, 2);
+        assert_eq!(refcount("bbbb").await, 1);
+        assert_eq!(refcount("cccc").await, 1);
+
+        // saving a manifest again doesn't add references
+        assert!(!dal.save_cache_manifest("url".to_string(), "rev1".to_string(), &rev1).await.unwrap());
+        assert_eq!(refcount("aaaa").await, 2);
+
+        // a different manifest for the same file replaces the old one
+        let rechunked = vec![chunk("rev1", 0, "dddd")];
+        assert!(dal.save_cache_manifest("url".to_string(), "rev1".to_string(), &rechunked).await.unwrap());
+        assert_eq!(refcount("aaaa").await, 1);
+        assert_eq!(refcount("bbbb").await, 0);
+        assert_eq!(refcount("dddd").await, 1);
+
+        let manifest = dal.get_cache_manifest("url".to_string(), "rev2".to_string()).await.unwrap();
+        assert_eq!(manifest.len(), 3);
+        assert_eq!(manifest[1].block_hash, "cccc");
+        assert_eq!(manifest[2].offset, Some(20));
+        assert!(dal.get_cache_manifest("url".to_string(), "rev3".to_string()).await.unwrap().is_empty());
+
+        dal.clear_cache_tables("url".to_string()).await.unwrap();
+        assert!(dal.get_cache_manifest("url".to_string(), "rev1".to_string()).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_cache_eviction(pool: SqlitePool) {
+        let dal = DataAccessLayer::new(&pool);
+        init_db(&pool).await;
+        let url = "url".to_string();
+        let chunk = |file_hash: &str, chunk_index: i64, block_hash: &str| FileChunk {
+            s3_url: "s3".to_string(),
+            block_hash: block_hash.to_string(),
+            chunk_index,
+            file_hash: file_hash.to_string(),
+            offset: None,
+            size: None,
+        };
+
+        // no limit by default
+        assert_eq!(dal.get_cache_max_size().await.unwrap(), 0);
+        assert!(dal.set_cache_max_size(1000).await.unwrap());
+        assert_eq!(dal.get_cache_max_size().await.unwrap(), 1000);
+
+        let rev1 = vec![chunk("rev1", 0, "aaaa"), chunk("rev1", 1, "bbbb")];
+        let rev2 = vec![chunk("rev2", 0, "aaaa"), chunk("rev2", 1, "cccc")];
+        let _ = dal.save_cache_manifest(url.clone(), "rev1".to_string(), &rev1).await;
+        let _ = dal.save_cache_manifest(url.clone(), "rev2".to_string(), &rev2).await;
+        let blocks = vec![("aaaa".to_string(), 100), ("bbbb".to_string(), 20), ("cccc".to_string(), 3)];
+        dal.set_blocks_present(url.clone(), &blocks).await.unwrap();
+        assert_eq!(dal.get_cached_size(url.clone()).await.unwrap(), 123);
+
+        // rev1 was used before rev2, until it is used again
+        assert_eq!(dal.get_lru_cache_file(url.clone()).await.unwrap(), None);
+        let _ = dal.touch_cache_file(url.clone(), "rev1".to_string(), 10).await;
+        let _ = dal.touch_cache_file(url.clone(), "rev2".to_string(), 20).await;
+        assert_eq!(dal.get_lru_cache_file(url.clone()).await.unwrap(), Some("rev1".to_string()));
+        let _ = dal.touch_cache_file(url.clone(), "rev1".to_string(), 30).await;
+        assert_eq!(dal.get_lru_cache_file(url.clone()).await.unwrap(), Some("rev2".to_string()));
+
+        // evicting rev2 only frees the block rev1 doesn't share
+        let orphans = dal.drop_cache_manifest(url.clone(), "rev2".to_string()).await.unwrap();
+        assert_eq!(orphans, vec!["cccc".to_string()]);
+        assert_eq!(dal.get_cached_size(url.clone()).await.unwrap(), 120);
+        assert!(dal.get_cache_manifest(url.clone(), "rev2".to_string()).await.unwrap().is_empty());
+        assert_eq!(dal.get_lru_cache_file(url.clone()).await.unwrap(), Some("rev1".to_string()));
+
+        let mut orphans = dal.drop_cache_manifest(url.clone(), "rev1".to_string()).await.unwrap();
+        orphans.sort();
+        assert_eq!(orphans, vec!["aaaa".to_string(), "bbbb".to_string()]);
+        assert_eq!(dal.get_cached_size(url.clone()).await.unwrap(), 0);
+    }
+
+    #[sqlx::test]
+    async fn test_resolve_conflicts(pool: SqlitePool) {
+        let dal = DataAccessLayer::new(&pool);
+        init_db(&pool).await;
+        let is_download = |downloads: &Vec<FileChange>, path: &str| downloads.iter().any(|f| f.filepath == path);
+
+        // both sides edited a file that was at commit 5
+        for path in ["mine", "theirs", "both", "deleted"] {
+            let _ = dal.insert_remote_file(path.to_string(), 0, 5, "base".to_string(), ChangeType::Create as i32, 10).await;
+            let _ = dal.insert_local_file(path.to_string(), 0, "base".to_string(), 10).await;
+            let _ = dal.update_downloaded_file_entry(0, path.to_string()).await;
+        }
+        let _ = dal.reset_fs_state(0).await;
+        for path in ["mine", "theirs", "both", "deleted"] {
+            let _ = dal.insert_local_file(path.to_string(), 0, "local".to_string(), 11).await;
+            let _ = dal.insert_remote_file(path.to_string(), 0, 9, "remote".to_string(), ChangeType::Update as i32, 12).await;
+        }
+        let _ = dal.insert_remote_file("deleted".to_string(), 0, 9, "".to_string(), ChangeType::Delete as i32, 0).await;
+        let _ = dal.update_change_types(0).await;
+        assert_eq!(dal.get_conflicts(0).await.unwrap().len(), 4);
+        assert!(dal.is_conflict(0, "mine".to_string()).await.unwrap());
+
+        // keep mine: the local file uploads as an update on top of commit 9
+        dal.resolve_keep_mine(0, "mine".to_string()).await.unwrap();
+        assert!(!dal.is_conflict(0, "mine".to_string()).await.unwrap());
+        assert!(!is_download(&dal.get_downloads(0).await.unwrap(), "mine"));
+        let mine = dal.get_uploads(0).await.unwrap().into_iter().find(|f| f.filepath == "mine").unwrap();
+        assert!(mine.change_type == ChangeType::Update);
+        assert_eq!(mine.commit_id, 9);
+
+        // keep mine on a file deleted remotely: the local file is uploaded again as a new file
+        dal.resolve_keep_mine(0, "deleted".to_string()).await.unwrap();
+        assert!(!is_download(&dal.get_downloads(0).await.unwrap(), "deleted"));
+        let deleted = dal.get_uploads(0).await.unwrap().into_iter().find(|f| f.filepath == "deleted").unwrap();
+        assert!(deleted.change_type == ChangeType::Create);
+
+        // keep both: the remote revision is saved next to the local file, which is kept
+        let revision = dal.get_tracked_revision(0, "both".to_string()).await.unwrap();
+        assert_eq!(revision.tracked_commitid, 9);
+        let _ = dal.insert_local_file("both (commit 9)".to_string(), 0, revision.tracked_hash, revision.tracked_size as u64).await;
+        dal.resolve_keep_mine(0, "both".to_string()).await.unwrap();
+        let uploads = dal.get_uploads(0).await.unwrap();
+        assert!(uploads.iter().find(|f| f.filepath == "both").unwrap().change_type == ChangeType::Update);
+        assert!(uploads.iter().find(|f| f.filepath == "both (commit 9)").unwrap().change_type == ChangeType::Create);
+
+        // take theirs: committing the download of the remote revision clears the conflict
+        let jid = dal.create_download_journal(0, "download").await.unwrap();
+        let entries = vec![JournalEntry { idx: 0, rel_path: "theirs".to_string(), target: "t".to_string(), source: "s".to_string(), backup: "b".to_string(), download: true, done: true }];
+        dal.write_journal_entries(jid, &entries).await.unwrap();
+        dal.commit_download_journal(jid, 0).await.unwrap();
+        assert!(!dal.is_conflict(0, "theirs".to_string()).await.unwrap());
+        let theirs = dal.get_file_info(0, "theirs".to_string()).await.unwrap();
+        assert_eq!(theirs.hash, "remote");
+        assert!(theirs.change == ChangeType::NoChange);
+        assert_eq!(dal.get_conflicts(0).await.unwrap().len(), 0);
+    }
+
+    #[sqlx::test]
+    async fn test_backups(pool: SqlitePool) {
+        let dal = DataAccessLayer::new(&pool);
+        init_db(&pool).await;
+        let url = "url".to_string();
+
+        // only files with local edits need a backup
+        let _ = dal.insert_remote_file("edited".to_string(), 0, 5, "base".to_string(), ChangeType::Create as i32, 10).await;
+        let _ = dal.insert_remote_file("clean".to_string(), 0, 5, "base".to_string(), ChangeType::Create as i32, 10).await;
+        let _ = dal.update_downloaded_file_entry(0, "edited".to_string()).await;
+        let _ = dal.update_downloaded_file_entry(0, "clean".to_string()).await;
+        let _ = dal.insert_local_file("edited".to_string(), 0, "local".to_string(), 11).await;
+        let _ = dal.insert_local_file("new".to_string(), 0, "new".to_string(), 3).await;
+        assert_eq!(dal.get_local_edit(0, "edited".to_string()).await.unwrap(), Some(("local".to_string(), 11, 5)));
+        assert_eq!(dal.get_local_edit(0, "new".to_string()).await.unwrap(), Some(("new".to_string(), 3, -1)));
+        assert_eq!(dal.get_local_edit(0, "clean".to_string()).await.unwrap(), None);
+        assert_eq!(dal.get_local_edit(0, "missing".to_string()).await.unwrap(), None);
+
+        let backup = |pid: i32, rel_path: &str, hash: &str, size: i64, base_commitid: i64, reason: &str| Backup {
+            bid: 0,
+            pid,
+            rel_path: rel_path.to_string(),
+            hash: hash.to_string(),
+            size,
+            base_commitid,
+            reason: reason.to_string(),
+            created: String::new(),
+        };
+        let first = dal.add_backup(url.clone(), &backup(0, "edited", "local", 11, 5, "download")).await.unwrap();
+        let second = dal.add_backup(url.clone(), &backup(1, "other", "h", 1, 41, "reset")).await.unwrap();
+        let backups = dal.get_backups(url.clone(), None).await.unwrap();
+        assert_eq!(backups.iter().map(|b| b.bid).collect::<Vec<i64>>(), vec![second, first]);
+        assert_eq!(dal.get_backups(url.clone(), Some(0)).await.unwrap().len(), 1);
+        assert_eq!(dal.get_backup(first).await.unwrap().reason, "download");
+
+        // backups from today aren't expired
+        assert_eq!(dal.get_backup_retention_days().await.unwrap(), 30);
+        assert_eq!(dal.get_expired_backups(url.clone(), 30).await.unwrap().len(), 0);
+        let _ = sqlx::query("UPDATE backup SET created = datetime('now', '-40 days') WHERE bid = $1").bind(first).execute(&pool).await;
+        let expired = dal.get_expired_backups(url.clone(), 30).await.unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].bid, first);
+
+        dal.delete_backup(first).await.unwrap();
+        assert!(dal.get_backup(first).await.is_err());
+    }
+
+    #[sqlx::test]
+    async fn test_trash(pool: SqlitePool) {
+        let dal = DataAccessLayer::new(&pool);
+        init_db(&pool).await;
+        let url = "url".to_string();
+
+        // two trashed files with the same content are separate entries
+        let first = dal.add_trash_entry(url.clone(), 0, "a\\part".to_string(), "same".to_string(), "download").await.unwrap();
+        let second = dal.add_trash_entry(url.clone(), 0, "b\\part".to_string(), "same".to_string(), "reset").await.unwrap();
+        let third = dal.add_trash_entry(url.clone(), 1, "other".to_string(), "h".to_string(), "project").await.unwrap();
+        assert!(first != second);
+        let entries = dal.get_trash_entries(url.clone(), None).await.unwrap();
+        assert_eq!(entries.iter().map(|e| e.tid).collect::<Vec<i64>>(), vec![third, second, first]);
+        assert_eq!(dal.get_trash_entries(url.clone(), Some(0)).await.unwrap().len(), 2);
+        let entry = dal.get_trash_entry(second).await.unwrap();
+        assert_eq!(entry.rel_path, "b\\part");
+        assert_eq!(entry.reason, "reset");
+
+        assert_eq!(dal.get_trash_retention_days().await.unwrap(), 30);
+        assert_eq!(dal.get_expired_trash_entries(url.clone(), 30).await.unwrap().len(), 0);
+        let _ = sqlx::query("UPDATE trash SET created = datetime('now', '-40 days') WHERE tid = $1").bind(first).execute(&pool).await;
+        let expired = dal.get_expired_trash_entries(url.clone(), 30).await.unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].tid, first);
+
+        dal.delete_trash_entry(first).await.unwrap();
+        assert!(dal.get_trash_entry(first).await.is_err());
+
+        // only files on disk can be trashed
+        let _ = dal.insert_local_file("local".to_string(), 0, "hash".to_string(), 1).await;
+        let _ = dal.insert_remote_file("remote".to_string(), 0, 5, "base".to_string(), ChangeType::Create as i32, 10).await;
+        assert_eq!(dal.get_local_files(0).await.unwrap(), vec![("local".to_string(), "hash".to_string())]);
+    }
+
+    #[sqlx::test]
+    async fn test_commit_job(pool: SqlitePool) {
+        let dal = DataAccessLayer::new(&pool);
+        init_db(&pool).await;
+        assert!(dal.get_commit_job(0).await.unwrap().is_none());
+
+        let file = |idx: i64, part: i64, path: &str| CommitJobFile {
+            idx,
+            part,
+            source: path.to_string(),
+            path: path.to_string(),
+            hash: "hash".to_string(),
+            changetype: ChangeType::Create as i32,
+            commitid: -1,
+            done: false,
+        };
+        let files = vec![file(0, 0, "a"), file(1, 0, "b"), file(2, 1, "c")];
+        let cjid = dal.create_commit_job(0, "message".to_string(), &files).await.unwrap();
+        let job = dal.get_commit_job(0).await.unwrap().unwrap();
+        assert_eq!(job.cjid, cjid);
+        assert_eq!(job.message, "message");
+        assert!(dal.get_commit_job(1).await.unwrap().is_none());
+        assert_eq!(dal.get_commit_job_files(cjid).await.unwrap(), files);
+
+        // parts are committed one at a time
+        dal.set_commit_job_part(cjid, 0, 12, true).await.unwrap();
+        let files = dal.get_commit_job_files(cjid).await.unwrap();
+        assert_eq!(files.iter().map(|f| (f.commitid, f.done)).collect::<Vec<(i32, bool)>>(), vec![(12, true), (12, true), (-1, false)]);
+
+        dal.delete_commit_job(cjid).await.unwrap();
+        assert!(dal.get_commit_job(0).await.unwrap().is_none());
+        assert!(dal.get_commit_job_files(cjid).await.unwrap().is_empty());
+    }
+
+    /* test 3: sync with server with some local files, test different changetypes and such */
+
+    /* test 4: something with conflicts */
+
+
+    //////////////////////
+    // helper functions //
+    //////////////////////
+
+    /// initialize a db with a server and some projects
+    async fn init_db(pool: &SqlitePool) {
+        let dal = DataAccessLayer::new(pool);
+
+        // create server entry
+        let _ = dal.add_server("url".to_string(), "key".to_string(), "owo/location".to_string(), "test server".to_string()).await;
+
+        let _ = dal.clear_file_table().await;
+        let _ = dal.clear_project_table("url".to_string()).await;
+        // create project entries
+        let _ = dal.add_project(0, "project name".to_string(), "team name".to_string(), 0).await;
+        let _ = dal.add_project(1, "project 2".to_string(), "team name".to_string(), 41).await;
+        let _ = dal.add_project(14, "another project".to_string(), "team 2".to_string(), 2).await;
+    }
+
+}
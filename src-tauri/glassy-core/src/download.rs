@@ -0,0 +1,412 @@
+use crate::config::Config;
+use crate::dal::DataAccessLayer;
+use crate::file::{sep, translate_filepath};
+use crate::job::JobControl;
+use crate::network::{backoff, CHUNK_TIMEOUT};
+use crate::progress::{NoProgress, ProgressReporter};
+use crate::sparse::SparseSpec;
+use crate::transfer::{run_transfer, transfer_revision, RevisionRequest, TransferPlan, TransferReason};
+use crate::types::{DownloadInformation, DownloadRequestMessage, FileChunk, GlassyError};
+use log::trace;
+use reqwest::header::RANGE;
+use reqwest::{Client, StatusCode};
+use sqlx::{Pool, Sqlite};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DOWNLOAD_RETRIES: u32 = 5;
+
+/// downloads files at the commits in `files` into the project, deleting the ones that aren't downloaded
+pub async fn download_project_files(
+    pid: i32,
+    files: Vec<DownloadRequestMessage>,
+    config: &Config,
+    pool: &Pool<Sqlite>,
+    progress: &dyn ProgressReporter,
+    job: &JobControl,
+) -> Result<bool, GlassyError> {
+    // files outside of the sparse checkout are never downloaded or deleted
+    let spec = SparseSpec::load(pid, pool).await;
+    let files = files.into_iter().filter(|file| spec.includes(&file.rel_path)).collect();
+    let plan = TransferPlan::new(pid, TransferReason::Download, files);
+    run_transfer(plan, config, pool, progress, job).await
+}
+
+/// downloads `path` at `commit_id` to `download_path`, which can be outside of the project
+pub async fn download_revision(
+    pid: i64,
+    path: String,
+    commit_id: i64,
+    download_path: String,
+    config: &Config,
+    pool: &Pool<Sqlite>,
+) -> Result<bool, GlassyError> {
+    let request = RevisionRequest { pid: pid as i32, path, commit_id, download_path };
+    transfer_revision(request, config, pool, &NoProgress, &JobControl::default()).await
+}
+
+/// downloads a chunk into the cache, resuming a partial download if there is one.
+/// returns Ok(false) if the chunk couldn't be downloaded intact after retrying
+pub async fn download_with_client(
+    dir: &String,
+    chunk_download: FileChunk,
+    client: &Client,
+) -> Result<bool, GlassyError> {
+    let path = dir.to_owned() + &(sep().to_string()) + &chunk_download.block_hash;
+    let part_path = path.clone() + ".part";
+
+    // chunk might already be in the cache from an earlier download
+    if verify_chunk(&path, &chunk_download.block_hash) {
+        return Ok(true);
+    }
+
+    // create cache folder if it doesnt exist
+    if let Err(err) = fs::create_dir_all(dir) {
+        log::error!("couldn't create cache folder {}: {}", dir, err);
+        return Ok(false);
+    }
+
+    let mut attempt = 0;
+    loop {
+        match download_to_part(&part_path, &chunk_download.s3_url, client).await {
+            Ok(true) => {
+                if verify_chunk(&part_path, &chunk_download.block_hash) {
+                    return match fs::rename(&part_path, &path) {
+                        Ok(_) => Ok(true),
+                        Err(err) => {
+                            log::error!("couldn't move chunk {} into the cache: {}", path, err);
+                            Ok(false)
+                        }
+                    };
+                }
+                // a corrupt partial download can't be resumed
+                log::warn!("chunk {} does not match its hash, starting over", chunk_download.block_hash);
+                let _ = fs::remove_file(&part_path);
+            }
+            Ok(false) => {}
+            Err(err) => {
+                log::warn!("error downloading chunk {} (attempt {}): {}", chunk_download.block_hash, attempt + 1, err);
+                if attempt + 1 >= DOWNLOAD_RETRIES {
+                    return Err(err.into());
+                }
+            }
+        }
+
+        attempt += 1;
+        if attempt >= DOWNLOAD_RETRIES {
+            log::error!("giving up on chunk {} after {} attempts", chunk_download.block_hash, attempt);
+            return Ok(false);
+        }
+        tokio::time::sleep(backoff(attempt + 1)).await;
+    }
+}
+
+/// streams a chunk to its .part file, continuing from where an earlier attempt stopped.
+/// returns Ok(false) if the .part file couldn't be written
+async fn download_to_part(part_path: &String, url: &String, client: &Client) -> Result<bool, reqwest::Error> {
+    let existing = match fs::metadata(part_path) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    };
+    let mut request = client.get(url).timeout(CHUNK_TIMEOUT);
+    if existing > 0 {
+        request = request.header(RANGE, format!("bytes={}-", existing));
+    }
+    let mut response = request.send().await?;
+
+    // the .part file already has everything
+    if existing > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        return Ok(true);
+    }
+    response = response.error_for_status()?;
+
+    // if the server ignored the range request we get the whole chunk again
+    let resume = existing > 0 && response.status() == StatusCode::PARTIAL_CONTENT;
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resume)
+        .truncate(!resume)
+        .open(part_path);
+    let mut writer = match file {
+        Ok(f) => BufWriter::new(f),
+        Err(err) => {
+            log::error!("couldn't open {}: {}", part_path, err);
+            return Ok(false);
+        }
+    };
+
+    while let Some(bytes) = response.chunk().await? {
+        if let Err(err) = writer.write_all(&bytes) {
+            log::error!("couldn't write to {}: {}", part_path, err);
+            return Ok(false);
+        }
+    }
+    if let Err(err) = writer.flush() {
+        log::error!("couldn't write to {}: {}", part_path, err);
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// checks that a chunk exists and its blake3 hash matches block_hash
+pub fn verify_chunk(path: &String, block_hash: &String) -> bool {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => return false,
+    };
+    let mut hasher = blake3::Hasher::new();
+    if let Err(err) = hasher.update_reader(BufReader::new(file)) {
+        log::warn!("couldn't read chunk {}: {}", path, err);
+        return false;
+    }
+    hasher.finalize().to_hex().as_str() == block_hash
+}
+
+/// records which blocks make up a file, so its blocks can be found in the block store
+pub async fn save_manifest(pool: &Pool<Sqlite>, download: &DownloadInformation) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let url = dal.get_active_server().await?;
+    let saved = dal.save_cache_manifest(url.clone(), download.file_hash.clone(), &download.file_chunks).await;
+    let _ = dal.touch_cache_file(url, download.file_hash.clone(), now_ms()).await;
+    saved
+}
+
+/// gets the blocks a cached file is made of, empty if the file was never cached
+pub async fn load_manifest(pool: &Pool<Sqlite>, file_hash: &str) -> Vec<FileChunk> {
+    let dal = DataAccessLayer::new(pool);
+    let url = dal.get_active_server().await.unwrap_or_default();
+    let manifest = dal.get_cache_manifest(url.clone(), file_hash.to_string()).await.unwrap_or_default();
+    if !manifest.is_empty() {
+        let _ = dal.touch_cache_file(url, file_hash.to_string(), now_ms()).await;
+    }
+    manifest
+}
+
+/// records the size of every block that is in the block store, so the cache size doesn't need to be
+/// recomputed from disk
+pub async fn record_blocks<'a>(pool: &Pool<Sqlite>, block_dir: &String, chunks: impl Iterator<Item = &'a FileChunk>) {
+    let mut blocks: Vec<(String, u64)> = Vec::new();
+    for chunk in chunks {
+        let path = block_dir.to_owned() + &(sep().to_string()) + &chunk.block_hash;
+        if let Ok(metadata) = fs::metadata(&path) {
+            blocks.push((chunk.block_hash.clone(), metadata.len()));
+        }
+    }
+    let dal = DataAccessLayer::new(pool);
+    let url = dal.get_active_server().await.unwrap_or_default();
+    let _ = dal.set_blocks_present(url, &blocks).await;
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// several files, or several places in one file, can share a block; it only needs downloading once
+pub fn unique_blocks(chunks: Vec<FileChunk>) -> Vec<FileChunk> {
+    let mut seen: HashSet<String> = HashSet::new();
+    chunks.into_iter().filter(|chunk| seen.insert(chunk.block_hash.clone())).collect()
+}
+
+// block dir is the block store, mapping is the file's manifest
+// proj dir should be the complete path to the desired file and must exist
+pub fn assemble_file(block_dir: &String, mapping: &[FileChunk], proj_path: &String) -> bool {
+    let mut mapping = mapping.to_vec();
+    if !sort_mapping(&mut mapping) {
+        log::error!("assemble file: chunks of {} leave gaps or overlap", proj_path);
+        return false;
+    }
+    let file_path;
+    #[cfg(target_os = "windows")]
+    {
+        file_path = proj_path.to_string();
+    }
+    #[cfg(target_os = "linux")]
+    {
+        file_path = translate_filepath(proj_path, true);
+    }
+
+    if mapping.len() == 1 {
+        // nothing to do, just copy the file
+        let cache_path = block_dir.to_owned() + &(sep().to_string()) + &mapping[0].block_hash;
+        return match fs::copy(&cache_path, file_path) {
+            Ok(_) => true,
+            Err(err) => {
+                log::error!("error copying {} out of the cache: {}", cache_path, err);
+                false
+            }
+        };
+    } else if mapping.is_empty() {
+        log::error!("assemble file: empty mapping for {}", proj_path);
+        return false;
+    }
+
+    // otherwise we need to assemble the file
+    let proj_file = match File::create(file_path) {
+        Ok(file) => file,
+        Err(err) => {
+            log::error!("error creating project file {}", err);
+            return false;
+        }
+    };
+    let mut writer = BufWriter::new(proj_file);
+    for chunk in mapping {
+        let cache_path = block_dir.to_owned() + &(sep().to_string()) + &chunk.block_hash;
+        let chunk_data = match fs::read(cache_path.clone()) {
+            Ok(data) => data,
+            Err(err) => {
+                log::error!("error reading chunk data from {}: {}", cache_path, err);
+                return false;
+            }
+        };
+        if let Err(err) = writer.write_all(&chunk_data) {
+            log::error!("error writing chunk data to {}: {}", proj_path, err);
+            return false;
+        }
+    }
+
+    if let Err(err) = writer.flush() {
+        log::error!("error writing {}: {}", proj_path, err);
+        return false;
+    }
+    true
+}
+
+/// puts chunks in the order they appear in the file.
+/// returns false if the chunk offsets don't line up end to end
+fn sort_mapping(mapping: &mut [FileChunk]) -> bool {
+    if !mapping.iter().all(|chunk| chunk.offset.is_some() && chunk.size.is_some()) {
+        mapping.sort_by_key(|chunk| chunk.chunk_index);
+        return true;
+    }
+    mapping.sort_by_key(|chunk| chunk.offset);
+    let mut end: u64 = 0;
+    for chunk in mapping.iter() {
+        if chunk.offset != Some(end) {
+            return false;
+        }
+        end += chunk.size.unwrap_or(0);
+    }
+    true
+}
+
+// checks every block of a file's manifest is in the block store and intact
+pub fn verify_cache(block_dir: &String, mapping: &[FileChunk]) -> bool {
+    if mapping.is_empty() {
+        return false;
+    }
+
+    for chunk in mapping {
+        let cache_path = block_dir.to_owned() + &(sep().to_string()) + &chunk.block_hash;
+        match Path::new(&cache_path).try_exists() {
+            Ok(result) => {
+                if !result {
+                    return false;
+                }
+            }
+            Err(err) => {
+                log::error!("couldn't verify cached chunk {}: {}", cache_path, err);
+                return false;
+            }
+        };
+        if !verify_chunk(&cache_path, &chunk.block_hash) {
+            // remove it so it gets downloaded again
+            log::warn!("cached chunk {} is corrupt", cache_path);
+            let _ = fs::remove_file(&cache_path);
+            return false;
+        }
+    }
+
+    true
+}
+
+/// sorts deeper paths first
+pub fn compare_directory_deep(path_a: &str, path_b: &str) -> Ordering {
+    count_separators(path_b).cmp(&count_separators(path_a))
+}
+
+fn count_separators(path: &str) -> usize {
+    path.chars().filter(|c| *c == sep()).count()
+}
+
+pub fn get_directories(deleted: &Vec<DownloadRequestMessage>) -> HashSet<String> {
+    let mut output: HashSet<String> = HashSet::new();
+    for file in deleted {
+        let path = PathBuf::from(file.rel_path.clone());
+        let parent = match path.parent() {
+            Some(a) => a,
+            None => continue
+        };
+        // TODO lol ???
+        let components: Vec<String> = parent.components().map(|comp| comp.as_os_str().to_str().unwrap().to_string()).collect();
+        let mut working: String = "".to_string();
+        for component in components {
+            working = working + &(sep().to_string()) + &component;
+            trace!("adding {} to list of directories to try to delete", working);
+            output.insert(working.clone());
+        }
+    }
+    output
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(chunk_index: i64, offset: Option<u64>, size: Option<u64>) -> FileChunk {
+        FileChunk {
+            s3_url: String::new(),
+            block_hash: chunk_index.to_string(),
+            chunk_index,
+            file_hash: String::new(),
+            offset,
+            size,
+        }
+    }
+
+    #[test]
+    fn test_sort_mapping() {
+        // fixed size chunks are ordered by index
+        let mut mapping = vec![chunk(1, None, None), chunk(0, None, None)];
+        assert!(sort_mapping(&mut mapping));
+        assert_eq!(mapping[0].chunk_index, 0);
+
+        // variable size chunks are ordered by offset
+        let mut mapping = vec![chunk(2, Some(30), Some(5)), chunk(0, Some(0), Some(10)), chunk(1, Some(10), Some(20))];
+        assert!(sort_mapping(&mut mapping));
+        let order: Vec<i64> = mapping.iter().map(|c| c.chunk_index).collect();
+        assert_eq!(order, vec![0, 1, 2]);
+
+        // a gap between chunks means the mapping is broken
+        let mut mapping = vec![chunk(0, Some(0), Some(10)), chunk(1, Some(12), Some(10))];
+        assert!(!sort_mapping(&mut mapping));
+    }
+
+    #[test]
+    fn test_verify_chunk() {
+        let dir = std::env::temp_dir().join("glassy_verify_chunk_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let data = b"some chunk of a solidworks part";
+        let block_hash = blake3::hash(data).to_hex().to_string();
+        let path = dir.join(&block_hash).display().to_string();
+
+        // missing chunk
+        assert!(!verify_chunk(&path, &block_hash));
+
+        fs::write(&path, data).unwrap();
+        assert!(verify_chunk(&path, &block_hash));
+
+        // truncated chunk, e.g. from an interrupted download
+        fs::write(&path, &data[..10]).unwrap();
+        assert!(!verify_chunk(&path, &block_hash));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
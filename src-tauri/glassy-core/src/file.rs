@@ -0,0 +1,364 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use ignore::gitignore::{Glob, Gitignore, GitignoreBuilder};
+use ignore::Match;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use crate::dal::DataAccessLayer;
+use crate::sync::{get_relative_path, should_skip_path, walk_dir};
+use crate::types::GlassyError;
+
+#[derive(sqlx::FromRow, Clone, Serialize, Deserialize)]
+
+pub struct FileSummary {
+    pub filepath: String,
+    pub change_type: i32,
+    pub in_fs: i32,
+    pub lock_user: Option<String>, // who has the file checked out, see lock.rs
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DirectorySummary {
+    pub files: Vec<FileSummary>,
+    pub folders: HashSet<String>
+}
+
+/// lists the files and folders directly inside `directory` of a project
+pub async fn list_directory(project_id: i32, directory: String, pool: &Pool<Sqlite>) -> Result<DirectorySummary, GlassyError> {
+    // get files that have the path in the project
+    // only return files and folders that are in the directory
+    let all_files: Vec<FileSummary> = sqlx::query_as("SELECT filepath, tracked_changetype as change_type, in_fs, lock_user FROM file WHERE pid = $1 ORDER BY filepath")
+        .bind(project_id).fetch_all(pool)
+        .await
+        .map_err(|err| {
+            log::error!("encountered error while querying db: {}", err);
+            err
+        })?;
+    let mut files = Vec::<FileSummary>::new();
+    let mut folders = HashSet::<String>::new();
+    for file in all_files {
+        // skip if file doesnt include path
+        let rel_path = match file.filepath.strip_prefix(&directory) {
+            Some(path) => path,
+            None => {
+                continue;
+            }
+        };
+
+        if rel_path.contains('\\') {
+            let folder_name = rel_path.split_at(rel_path.find('\\').unwrap()).0;
+            folders.insert(folder_name.to_string() + "\\");
+        } else {
+            files.push(file);
+        }
+    }
+    Ok(DirectorySummary{ folders, files})
+}
+
+// ignore rules use .gitignore syntax: globs, `folder/` for folders and `!` to stop ignoring something.
+// a project's rules come from its .glassyignore, which is synced like any other file, and from projectignorelist,
+// which only applies on this computer and wins over the file. rules only apply to untracked files,
+// so a file that is already on the server keeps syncing
+
+pub const IGNORE_FILE: &str = ".glassyignore";
+
+/// a project's ignore rules, see IgnoreRules::load
+pub struct IgnoreRules {
+    rules: Gitignore,
+    tracked: HashSet<String>,
+}
+
+impl IgnoreRules {
+    /// later rules win over earlier ones. rules that can't be parsed are skipped
+    pub fn new(lines: &[String], tracked: HashSet<String>) -> IgnoreRules {
+        IgnoreRules { rules: build_rules(lines), tracked }
+    }
+
+    /// loads the rules in the project's .glassyignore, then the ones in projectignorelist
+    pub async fn load(pid: i32, project_dir: &Path, pool: &Pool<Sqlite>) -> IgnoreRules {
+        let dal = DataAccessLayer::new(pool);
+        let mut lines = read_ignore_file(project_dir);
+        if let Ok(url) = dal.get_active_server().await {
+            lines.extend(get_ignore_list(pid, url, pool).await.unwrap_or_default());
+        }
+        let tracked = dal.get_tracked_paths(pid).await.unwrap_or_default();
+        IgnoreRules::new(&lines, tracked)
+    }
+
+    /// `rel_path` is a path in the project with backslashes, like in the file table
+    pub fn is_ignored(&self, rel_path: &str) -> bool {
+        !self.tracked.contains(rel_path) && self.matched(rel_path).is_ignore()
+    }
+
+    fn matched(&self, rel_path: &str) -> Match<&Glob> {
+        match_rules(&self.rules, rel_path)
+    }
+}
+
+/// builds .gitignore style rules, also used for sparse checkouts
+pub(crate) fn build_rules(lines: &[String]) -> Gitignore {
+    let mut builder = GitignoreBuilder::new("");
+    // windows paths aren't case sensitive, and neither are the extensions cad programs use
+    let _ = builder.case_insensitive(true);
+    for line in lines {
+        if let Err(err) = builder.add_line(None, line) {
+            log::warn!("skipping rule {}: {}", line, err);
+        }
+    }
+    builder.build().unwrap_or_else(|err| {
+        log::warn!("couldn't build rules: {}", err);
+        Gitignore::empty()
+    })
+}
+
+/// matches `rel_path`, a path in the project with backslashes, or one of its folders against `rules`
+pub(crate) fn match_rules<'a>(rules: &'a Gitignore, rel_path: &str) -> Match<&'a Glob> {
+    let path = translate_filepath(rel_path, true);
+    rules.matched_path_or_any_parents(Path::new(&path), false)
+}
+
+/// the rules in the project's .glassyignore, if it has one
+fn read_ignore_file(project_dir: &Path) -> Vec<String> {
+    match fs::read_to_string(project_dir.join(IGNORE_FILE)) {
+        Ok(contents) => contents.lines().map(|line| line.to_string()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// returns false if `rule` can't be parsed
+pub fn is_valid_ignore_rule(rule: &str) -> bool {
+    let rule = rule.trim();
+    !rule.is_empty() && !rule.starts_with('#') && GitignoreBuilder::new("").add_line(None, rule).is_ok()
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IgnoreRule {
+    pub rule: String,
+    pub from_file: bool, // from .glassyignore instead of projectignorelist
+}
+
+/// the project's rules in the order they apply
+pub async fn list_ignore_rules(project_id: i32, pool: &Pool<Sqlite>) -> Result<Vec<IgnoreRule>, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let project_dir = dal.get_project_dir(project_id).await?;
+    let url = dal.get_active_server().await?;
+
+    let mut output: Vec<IgnoreRule> = read_ignore_file(Path::new(&project_dir))
+        .into_iter()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|rule| IgnoreRule { rule, from_file: true })
+        .collect();
+    for rule in get_ignore_list(project_id, url, pool).await? {
+        output.push(IgnoreRule { rule, from_file: false });
+    }
+    Ok(output)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IgnoreMatch {
+    pub filepath: String,
+    pub tracked: bool, // tracked files keep syncing even if a rule matches them
+}
+
+/// the files in the project that `rule` matches
+pub async fn preview_ignore_rule(project_id: i32, rule: String, pool: &Pool<Sqlite>) -> Result<Vec<IgnoreMatch>, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let project_dir = PathBuf::from(dal.get_project_dir(project_id).await?);
+    let tracked = dal.get_tracked_paths(project_id).await?;
+    let rules = IgnoreRules::new(&[rule], HashSet::new());
+
+    let mut paths: Vec<PathBuf> = vec![];
+    walk_dir(&project_dir, &mut paths);
+    let mut output = Vec::new();
+    for path in paths {
+        let rel_path = match get_relative_path(&project_dir, &path) {
+            Some(p) => p,
+            None => continue,
+        };
+        // a negated rule matches the files it stops ignoring
+        if should_skip_path(&rel_path) || rules.matched(&rel_path).is_none() {
+            continue;
+        }
+        output.push(IgnoreMatch { tracked: tracked.contains(&rel_path), filepath: rel_path });
+    }
+    output.sort_by(|a, b| a.filepath.cmp(&b.filepath));
+    Ok(output)
+}
+
+/// returns false if the rule can't be parsed or the project already has it
+pub async fn add_ignore_list_entry(project_id: i32, server_url: String, path: String, pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    let path = path.trim().to_string();
+    if !is_valid_ignore_rule(&path) || get_ignore_list(project_id, server_url.clone(), pool).await?.contains(&path) {
+        return Ok(false);
+    }
+    sqlx::query("INSERT INTO projectignorelist(pid, url, path) VALUES ($1, $2, $3);")
+        .bind(project_id)
+        .bind(server_url)
+        .bind(path)
+        .execute(pool)
+        .await?;
+    Ok(true)
+}
+
+pub async fn remove_ignore_list_entry(project_id: i32, server_url: String, path: String, pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    let res = sqlx::query("DELETE FROM projectignorelist WHERE pid = $1 AND url = $2 AND path = $3;")
+        .bind(project_id)
+        .bind(server_url)
+        .bind(path)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// the project's rules from projectignorelist, in the order they were added
+pub async fn get_ignore_list(project_id: i32, server_url: String, pool: &Pool<Sqlite>) -> Result<Vec<String>, GlassyError> {
+    let output: Vec<String> = sqlx::query_scalar("SELECT path FROM projectignorelist WHERE pid = $1 AND url = $2 ORDER BY pil_id;")
+        .bind(project_id)
+        .bind(server_url)
+        .fetch_all(pool)
+        .await?;
+    Ok(output)
+}
+
+// check if file is ignored by the project's rules
+pub async fn should_file_be_ignored(project_id: i32, file: String, pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let project_dir = dal.get_project_dir(project_id).await?;
+    let rules = IgnoreRules::load(project_id, Path::new(&project_dir), pool).await;
+    Ok(rules.is_ignored(&file))
+}
+
+// convert forward slashes to backward slashes or vice versa
+pub fn translate_filepath(path: &str, to_unix: bool) -> String {
+    if to_unix { // backward slashes to forwards
+        path.replace('\\', "/")
+    } else { // forward slashes to backwards
+        path.replace('/', "\\")
+    }
+}
+
+// paths are stored with backward slashes, this converts them to what the os expects
+pub fn native_path(path: &str) -> String {
+    #[cfg(target_os = "windows")]
+    {
+        path.to_owned()
+    }
+    #[cfg(target_os = "linux")]
+    {
+        translate_filepath(path, true)
+    }
+}
+
+pub fn sep() -> char {
+    #[cfg(target_os = "windows")]
+    {
+        '\\'
+    }
+    #[cfg(target_os = "linux")]
+    {
+        '/'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::setup_project;
+    use crate::sync::hash_dir;
+    use sqlx::SqlitePool;
+    
+    struct TestCaseTranslateFilePath {
+        set_path: String,
+        set_to_unix: bool,
+        exp_path: String
+    }
+
+    #[test]
+    fn test_translate_filepath() {
+        // initialize test cases
+        let test_cases: Vec<TestCaseTranslateFilePath> = vec![
+            TestCaseTranslateFilePath { set_path: "path/to/file".to_string(), set_to_unix: false, exp_path: "path\\to\\file".to_string()},
+            TestCaseTranslateFilePath { set_path: "path/to/file".to_string(), set_to_unix: true, exp_path: "path/to/file".to_string()},
+            TestCaseTranslateFilePath { set_path: "path\\to\\file".to_string(), set_to_unix: true, exp_path: "path/to/file".to_string()},
+            TestCaseTranslateFilePath { set_path: "path\\to\\file".to_string(), set_to_unix: false, exp_path: "path\\to\\file".to_string()}
+        ];
+        
+        // run function under test and validate results
+        for case in test_cases {
+            let ret = translate_filepath(&case.set_path, case.set_to_unix);
+            assert_eq!(ret, case.exp_path);
+        }
+    }
+
+    #[test]
+    fn test_ignore_rules() {
+        let lines: Vec<String> = ["# cad backups", "*.bak", "build/", "!build/keep.txt", "*.log", "!debug.log"]
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        let tracked = HashSet::from(["tracked.bak".to_string()]);
+        let rules = IgnoreRules::new(&lines, tracked);
+
+        assert!(rules.is_ignored("part.SLDPRT.bak"));
+        assert!(rules.is_ignored("Parts\\old.BAK"));
+        assert!(rules.is_ignored("build\\out.step"));
+        assert!(rules.is_ignored("Parts\\build\\out.step"));
+        assert!(!rules.is_ignored("build"));
+        assert!(!rules.is_ignored("build\\keep.txt"));
+        assert!(rules.is_ignored("other.log"));
+        assert!(!rules.is_ignored("debug.log"));
+        assert!(!rules.is_ignored("part.SLDPRT"));
+        // files on the server keep syncing
+        assert!(!rules.is_ignored("tracked.bak"));
+
+        assert!(is_valid_ignore_rule("*.SLDPRT"));
+        assert!(!is_valid_ignore_rule("# comment"));
+        assert!(!is_valid_ignore_rule("{a,b"));
+    }
+
+    #[sqlx::test]
+    async fn test_ignored_files_arent_uploaded(pool: SqlitePool) {
+        let project = setup_project("ignore", &pool).await;
+        let project_dir = &project.project_dir;
+        let dal = DataAccessLayer::new(&pool);
+        fs::create_dir_all(project_dir.join("exports")).unwrap();
+        fs::write(project_dir.join("part.SLDPRT"), "part").unwrap();
+        fs::write(project_dir.join("part.SLDPRT.bak"), "backup").unwrap();
+        fs::write(project_dir.join("exports").join("part.step"), "export").unwrap();
+        fs::write(project_dir.join(IGNORE_FILE), "exports/\n").unwrap();
+
+        assert!(add_ignore_list_entry(3, "url".to_string(), "*.bak".to_string(), &pool).await.unwrap());
+        assert!(!add_ignore_list_entry(3, "url".to_string(), "*.bak".to_string(), &pool).await.unwrap());
+        let rules: Vec<String> = list_ignore_rules(3, &pool).await.unwrap().into_iter().map(|rule| rule.rule).collect();
+        assert_eq!(rules, vec!["exports/", "*.bak"]);
+        let preview = preview_ignore_rule(3, "*.step".to_string(), &pool).await.unwrap();
+        assert_eq!(preview.len(), 1);
+        assert_eq!(preview[0].filepath, "exports\\part.step");
+
+        hash_dir(3, project_dir.clone(), &pool, false).await;
+        let mut uploads: Vec<String> = dal.get_uploads(3).await.unwrap().into_iter().map(|file| file.filepath).collect();
+        uploads.sort();
+        assert_eq!(uploads, vec![IGNORE_FILE, "part.SLDPRT"]);
+
+        // removing the rule brings the file back
+        assert!(remove_ignore_list_entry(3, "url".to_string(), "*.bak".to_string(), &pool).await.unwrap());
+        hash_dir(3, project_dir.clone(), &pool, false).await;
+        assert_eq!(dal.get_uploads(3).await.unwrap().len(), 3);
+
+    }
+
+    #[test]
+    fn test_sep() {
+        #[cfg(target_os = "windows")]
+        {
+            assert_eq!('\\', sep());
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            assert_eq!('/', sep());
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,135 @@
+use crate::types::GlassyError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+// long running transfers run as jobs, so they can be paused and cancelled while they run.
+// a job only stops at points where it can leave the project and file table consistent, see JobControl::checkpoint
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum JobState {
+    Running,
+    Paused,
+    Cancelled,
+}
+
+/// pauses and cancels a job from outside of it
+#[derive(Clone)]
+pub struct JobControl {
+    state: Arc<watch::Sender<JobState>>,
+}
+
+impl Default for JobControl {
+    fn default() -> Self {
+        JobControl { state: Arc::new(watch::Sender::new(JobState::Running)) }
+    }
+}
+
+impl JobControl {
+    pub fn state(&self) -> JobState {
+        *self.state.borrow()
+    }
+
+    /// returns false if the job wasn't running
+    pub fn pause(&self) -> bool {
+        self.state.send_if_modified(|state| match state {
+            JobState::Running => {
+                *state = JobState::Paused;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    /// returns false if the job wasn't paused
+    pub fn resume(&self) -> bool {
+        self.state.send_if_modified(|state| match state {
+            JobState::Paused => {
+                *state = JobState::Running;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    /// the job stops at its next checkpoint. a cancelled job can't be resumed
+    pub fn cancel(&self) {
+        self.state.send_replace(JobState::Cancelled);
+    }
+
+    /// called by the job where it is safe to stop. waits while the job is paused,
+    /// and returns GlassyError::Cancelled once it has been cancelled
+    pub async fn checkpoint(&self) -> Result<(), GlassyError> {
+        let mut receiver = self.state.subscribe();
+        let state = match receiver.wait_for(|state| *state != JobState::Paused).await {
+            Ok(state) => *state,
+            Err(_) => JobState::Cancelled, // can't happen, we hold the sender
+        };
+        match state {
+            JobState::Cancelled => Err(GlassyError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// the controls of every job that is running, by job id. see queue.rs
+#[derive(Default)]
+pub struct Jobs {
+    jobs: Mutex<HashMap<i64, JobControl>>,
+}
+
+impl Jobs {
+    pub fn register(&self, id: i64) -> JobControl {
+        let control = JobControl::default();
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.insert(id, control.clone());
+        }
+        control
+    }
+
+    pub fn get(&self, id: i64) -> Option<JobControl> {
+        let jobs = self.jobs.lock().ok()?;
+        jobs.get(&id).cloned()
+    }
+
+    pub fn finish(&self, id: i64) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_job_control() {
+        let jobs = Jobs::default();
+        let control = jobs.register(7);
+        assert!(control.checkpoint().await.is_ok());
+
+        // a paused job waits at its checkpoint until it is resumed
+        assert!(jobs.get(7).unwrap().pause());
+        assert_eq!(control.state(), JobState::Paused);
+        let waiting = control.clone();
+        let checkpoint = tokio::spawn(async move { waiting.checkpoint().await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!checkpoint.is_finished());
+        assert!(control.resume());
+        assert!(checkpoint.await.unwrap().is_ok());
+
+        // cancelling wakes a paused job up too
+        control.pause();
+        let waiting = control.clone();
+        let checkpoint = tokio::spawn(async move { waiting.checkpoint().await });
+        control.cancel();
+        assert!(matches!(checkpoint.await.unwrap(), Err(GlassyError::Cancelled)));
+        assert!(!control.resume());
+
+        jobs.finish(7);
+        assert!(jobs.get(7).is_none());
+    }
+}
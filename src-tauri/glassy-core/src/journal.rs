@@ -0,0 +1,217 @@
+use crate::dal::DataAccessLayer;
+use crate::file::{native_path, sep};
+use crate::types::JournalEntry;
+use crate::util::{get_staging_dir, set_readonly};
+use sqlx::{Pool, Sqlite};
+use std::fs::{self, remove_dir_all};
+use std::path::Path;
+
+// journal states
+pub const JOURNAL_STAGING: i32 = 0;
+pub const JOURNAL_COMMITTING: i32 = 1;
+
+/// folder that holds the staged files and backups of a single download
+pub fn get_journal_dir(staging_dir: &String, jid: i64) -> String {
+    staging_dir.to_owned() + &(sep().to_string()) + &jid.to_string()
+}
+
+/// applies every entry of a journal to the project folder.
+/// if any entry fails, everything that was applied is undone and false is returned
+pub async fn apply_journal(jid: i64, pool: &Pool<Sqlite>) -> bool {
+    let dal = DataAccessLayer::new(pool);
+    let entries = match dal.get_journal_entries(jid).await {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+
+    let mut applied: Vec<JournalEntry> = vec![];
+    for entry in entries {
+        if !apply_entry(&entry) {
+            log::error!("couldn't apply download of {}, rolling back", entry.rel_path);
+            for entry in applied.iter().rev() {
+                if undo_entry(entry) {
+                    let _ = dal.set_journal_entry_done(jid, entry.idx, false).await;
+                }
+            }
+            return false;
+        }
+        let _ = dal.set_journal_entry_done(jid, entry.idx, true).await;
+        applied.push(entry);
+    }
+    true
+}
+
+/// updates the file table and throws away the staged files and backups
+pub async fn commit_journal(jid: i64, pid: i32, pool: &Pool<Sqlite>) -> bool {
+    let dal = DataAccessLayer::new(pool);
+    if dal.commit_download_journal(jid, pid).await.is_err() {
+        return false;
+    }
+    remove_journal_dir(jid, pool).await;
+    true
+}
+
+/// throws away a journal without touching the project, e.g. when staging fails
+pub async fn discard_journal(jid: i64, pool: &Pool<Sqlite>) {
+    let dal = DataAccessLayer::new(pool);
+    let _ = dal.delete_download_journal(jid).await;
+    remove_journal_dir(jid, pool).await;
+}
+
+/// finishes or undoes downloads that were interrupted, e.g. by the app closing.
+/// journals that were still staging never touched the project and are thrown away;
+/// journals that were committing are rolled forward, or rolled back if that isn't possible
+pub async fn recover_journals(pool: &Pool<Sqlite>) {
+    let dal = DataAccessLayer::new(pool);
+    let journals = match dal.get_download_journals().await {
+        Ok(journals) => journals,
+        Err(_) => return,
+    };
+
+    for (jid, pid, state) in journals {
+        if state == JOURNAL_STAGING {
+            log::info!("discarding download journal {} that never started committing", jid);
+            discard_journal(jid, pool).await;
+            continue;
+        }
+
+        log::info!("recovering interrupted download journal {} for project {}", jid, pid);
+        let entries = match dal.get_journal_entries(jid).await {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        let mut rolled_forward = true;
+        for entry in entries.iter().filter(|entry| !entry.done) {
+            if !apply_entry(entry) {
+                rolled_forward = false;
+                break;
+            }
+            let _ = dal.set_journal_entry_done(jid, entry.idx, true).await;
+        }
+
+        if rolled_forward {
+            if commit_journal(jid, pid, pool).await {
+                log::info!("download journal {} rolled forward", jid);
+            }
+        } else {
+            log::warn!("couldn't roll download journal {} forward, rolling back", jid);
+            for entry in entries.iter().rev() {
+                let _ = undo_entry(entry);
+            }
+            discard_journal(jid, pool).await;
+        }
+    }
+}
+
+async fn remove_journal_dir(jid: i64, pool: &Pool<Sqlite>) {
+    let staging_dir = get_staging_dir(pool).await.unwrap_or_default();
+    if staging_dir.is_empty() {
+        return;
+    }
+    let journal_dir = get_journal_dir(&staging_dir, jid);
+    if Path::new(&journal_dir).exists() {
+        if let Err(err) = remove_dir_all(&journal_dir) {
+            log::warn!("couldn't remove staging folder {}: {}", journal_dir, err);
+        }
+    }
+}
+
+// both apply_entry and undo_entry can be re-run safely after a crash part way through
+
+/// moves the file at target to backup, then moves source to target
+fn apply_entry(entry: &JournalEntry) -> bool {
+    if entry.target.is_empty() {
+        return true;
+    }
+    let target = native_path(&entry.target);
+    let source = native_path(&entry.source);
+    let backup = native_path(&entry.backup);
+
+    // source was already moved into place
+    if !entry.source.is_empty() && !Path::new(&source).exists() && Path::new(&target).exists() {
+        return true;
+    }
+
+    if Path::new(&target).exists() && !Path::new(&backup).exists() {
+        if !rename(&target, &backup) {
+            return false;
+        }
+        // so the journal folder can be removed, see lock.rs
+        set_readonly(Path::new(&backup), false);
+    }
+    if entry.source.is_empty() {
+        return true;
+    }
+    rename(&source, &target)
+}
+
+/// puts source and the backed up file back where they were
+fn undo_entry(entry: &JournalEntry) -> bool {
+    if entry.target.is_empty() {
+        return true;
+    }
+    let target = native_path(&entry.target);
+    let source = native_path(&entry.source);
+    let backup = native_path(&entry.backup);
+
+    let mut ok = true;
+    if !entry.source.is_empty() && Path::new(&target).exists() && !Path::new(&source).exists() {
+        ok = rename(&target, &source);
+    }
+    if Path::new(&backup).exists() {
+        ok = ok && rename(&backup, &target);
+    }
+    ok
+}
+
+fn rename(from: &String, to: &String) -> bool {
+    if let Some(prefix) = Path::new(to).parent() {
+        let _ = fs::create_dir_all(prefix);
+    }
+    match fs::rename(from, to) {
+        Ok(_) => true,
+        Err(err) => {
+            log::error!("couldn't move {} to {}: {}", from, to, err);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_and_undo_entry() {
+        let dir = std::env::temp_dir().join("glassy_journal_test");
+        let _ = remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).display().to_string();
+        fs::write(path("target"), "old").unwrap();
+        fs::write(path("staged"), "new").unwrap();
+
+        let entry = JournalEntry {
+            idx: 0,
+            rel_path: "target".to_string(),
+            target: path("target"),
+            source: path("staged"),
+            backup: path("backup/0"),
+            download: true,
+            done: false,
+        };
+
+        // applying twice is the same as applying once
+        assert!(apply_entry(&entry));
+        assert!(apply_entry(&entry));
+        assert_eq!(fs::read_to_string(path("target")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(path("backup/0")).unwrap(), "old");
+
+        // undoing puts everything back
+        assert!(undo_entry(&entry));
+        assert_eq!(fs::read_to_string(path("target")).unwrap(), "old");
+        assert_eq!(fs::read_to_string(path("staged")).unwrap(), "new");
+        assert!(!Path::new(&path("backup/0")).exists());
+
+        let _ = remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,31 @@
+pub mod backup;
+pub mod chunking;
+pub mod commit;
+pub mod config;
+pub mod conflict;
+pub mod dal;
+pub mod download;
+pub mod file;
+pub mod job;
+pub mod journal;
+pub mod lock;
+pub mod network;
+pub mod open_files;
+pub mod progress;
+pub mod queue;
+pub mod references;
+pub mod reset;
+pub mod sparse;
+pub mod sync;
+#[cfg(test)]
+mod test_util;
+pub mod transfer;
+pub mod trash;
+pub mod types;
+pub mod upload;
+pub mod util;
+
+use sqlx::migrate::Migrator;
+
+/// the database schema, embedded so frontends don't need to ship the migrations folder
+pub static MIGRATOR: Migrator = sqlx::migrate!();
@@ -0,0 +1,276 @@
+use crate::dal::DataAccessLayer;
+use crate::file::{native_path, sep};
+use crate::sync::sync_project;
+use crate::types::{FileLock, GlassyError, RemoteFile};
+use crate::util::set_readonly;
+use sqlx::{Pool, Sqlite};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// parts and assemblies can't be merged, so a file is checked out (locked) before it is edited.
+// the server keeps the locks, the file table caches them as of the last time they were fetched.
+// files someone else has locked can't be uploaded, and a project can keep files read-only until they are checked out
+
+/// where locks are kept. GlassyClient talks to the server, LocalLocks stands in for it in tests
+pub trait LockServer {
+    /// the user the locks are taken for
+    fn user_id(&self) -> &str;
+
+    /// locks whichever of `paths` nobody else has locked, and returns all of the project's locks
+    fn lock(&self, pid: i32, paths: &[String]) -> impl Future<Output = Result<Vec<FileLock>, GlassyError>> + Send;
+
+    /// unlocks `paths` and returns all of the project's locks. only the user's own locks are released unless `force` is set
+    fn unlock(&self, pid: i32, paths: &[String], force: bool) -> impl Future<Output = Result<Vec<FileLock>, GlassyError>> + Send;
+
+    /// all of the project's locks
+    fn locks(&self, pid: i32) -> impl Future<Output = Result<Vec<FileLock>, GlassyError>> + Send;
+}
+
+/// keeps locks in memory. clones share their locks, so as_user can act as someone else on the same "server"
+#[derive(Clone, Default)]
+pub struct LocalLocks {
+    user_id: String,
+    locks: Arc<Mutex<HashMap<i32, Vec<FileLock>>>>,
+}
+
+impl LocalLocks {
+    pub fn new(user_id: &str) -> LocalLocks {
+        LocalLocks { user_id: user_id.to_string(), locks: Arc::default() }
+    }
+
+    pub fn as_user(&self, user_id: &str) -> LocalLocks {
+        LocalLocks { user_id: user_id.to_string(), locks: self.locks.clone() }
+    }
+
+    fn project_locks(&self, pid: i32) -> Vec<FileLock> {
+        self.locks.lock().map(|locks| locks.get(&pid).cloned().unwrap_or_default()).unwrap_or_default()
+    }
+}
+
+impl LockServer for LocalLocks {
+    fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    async fn lock(&self, pid: i32, paths: &[String]) -> Result<Vec<FileLock>, GlassyError> {
+        if let Ok(mut locks) = self.locks.lock() {
+            let project = locks.entry(pid).or_default();
+            for path in paths {
+                if !project.iter().any(|lock| &lock.path == path) {
+                    project.push(FileLock { path: path.clone(), user_id: self.user_id.clone(), created: String::new() });
+                }
+            }
+        }
+        Ok(self.project_locks(pid))
+    }
+
+    async fn unlock(&self, pid: i32, paths: &[String], force: bool) -> Result<Vec<FileLock>, GlassyError> {
+        if let Ok(mut locks) = self.locks.lock() {
+            let project = locks.entry(pid).or_default();
+            project.retain(|lock| !paths.contains(&lock.path) || (!force && lock.user_id != self.user_id));
+        }
+        Ok(self.project_locks(pid))
+    }
+
+    async fn locks(&self, pid: i32) -> Result<Vec<FileLock>, GlassyError> {
+        Ok(self.project_locks(pid))
+    }
+}
+
+/// checks out `paths`. returns the locks someone else already has on some of them, empty if all of them were locked
+pub async fn acquire_locks(pid: i32, paths: &[String], server: &impl LockServer, pool: &Pool<Sqlite>) -> Result<Vec<FileLock>, GlassyError> {
+    let locks = server.lock(pid, paths).await?;
+    let taken = locked_by_others(&locks, paths, server.user_id());
+    save_locks(pid, &locks, server.user_id(), pool).await?;
+    if !taken.is_empty() {
+        log::warn!("{} of {} files in project {} are checked out by someone else", taken.len(), paths.len(), pid);
+    }
+    Ok(taken)
+}
+
+/// checks in `paths`. `force` breaks other people's locks, if the server allows it
+pub async fn release_locks(pid: i32, paths: &[String], force: bool, server: &impl LockServer, pool: &Pool<Sqlite>) -> Result<(), GlassyError> {
+    let locks = server.unlock(pid, paths, force).await?;
+    save_locks(pid, &locks, server.user_id(), pool).await
+}
+
+/// fetches the project's locks and caches them
+pub async fn refresh_locks(pid: i32, server: &impl LockServer, pool: &Pool<Sqlite>) -> Result<Vec<FileLock>, GlassyError> {
+    let locks = server.locks(pid).await?;
+    save_locks(pid, &locks, server.user_id(), pool).await?;
+    Ok(locks)
+}
+
+/// syncs the project, then fetches its locks from `server` so the file list shows who has what checked out.
+/// without a user every lock would look like someone else's, and read-only checkout would lock the user out of their own files
+pub async fn sync_with_locks(
+    pid: i32,
+    remote: Vec<RemoteFile>,
+    force_rehash: bool,
+    server: Option<&impl LockServer>,
+    pool: &Pool<Sqlite>,
+) -> Result<bool, GlassyError> {
+    let res = sync_project(pid, remote, force_rehash, pool).await?;
+    if let Some(server) = server.filter(|server| !server.user_id().is_empty()) {
+        if let Err(err) = refresh_locks(pid, server, pool).await {
+            log::warn!("couldn't fetch locks of project {}: {}", pid, err);
+        }
+    }
+    Ok(res)
+}
+
+/// fails with GlassyError::Locked if someone else has any of `paths` checked out.
+/// if the locks can't be fetched, e.g. because the server doesn't support them, the cached ones are used
+pub async fn check_unlocked(pid: i32, paths: &[String], server: &impl LockServer, pool: &Pool<Sqlite>) -> Result<(), GlassyError> {
+    let locks = match refresh_locks(pid, server, pool).await {
+        Ok(locks) => locks,
+        Err(err) => {
+            log::warn!("couldn't fetch locks of project {}, using the cached ones: {}", pid, err);
+            DataAccessLayer::new(pool).get_locks(pid).await?
+        }
+    };
+    let taken = locked_by_others(&locks, paths, server.user_id());
+    if taken.is_empty() {
+        return Ok(());
+    }
+    let listed: Vec<String> = taken.iter().map(|lock| format!("{} ({})", lock.path, lock.user_id)).collect();
+    Err(GlassyError::Locked(format!("checked out by someone else: {}", listed.join(", "))))
+}
+
+/// if the project keeps files read-only until they are checked out, makes its tracked files read-only
+/// except for the ones `user_id` has locked
+pub async fn apply_lock_permissions(pid: i32, user_id: &str, pool: &Pool<Sqlite>) {
+    let dal = DataAccessLayer::new(pool);
+    if !dal.get_readonly_checkout(pid).await.unwrap_or(false) {
+        return;
+    }
+    let held: HashSet<String> = match dal.get_locks(pid).await {
+        Ok(locks) => locks.into_iter().filter(|lock| lock.user_id == user_id).map(|lock| lock.path).collect(),
+        Err(_) => return,
+    };
+    set_tracked_readonly(pid, |path| !held.contains(path), pool).await;
+}
+
+/// makes every tracked file of the project writable again, for when read-only checkout is turned off
+pub async fn clear_lock_permissions(pid: i32, pool: &Pool<Sqlite>) {
+    set_tracked_readonly(pid, |_| false, pool).await;
+}
+
+async fn set_tracked_readonly(pid: i32, readonly: impl Fn(&String) -> bool, pool: &Pool<Sqlite>) {
+    let dal = DataAccessLayer::new(pool);
+    let project_dir = match dal.get_project_dir(pid).await {
+        Ok(dir) if !dir.is_empty() => dir,
+        _ => return,
+    };
+    for path in dal.get_tracked_paths(pid).await.unwrap_or_default() {
+        let file = native_path(&(project_dir.clone() + &sep().to_string() + &path));
+        if Path::new(&file).exists() {
+            set_readonly(Path::new(&file), readonly(&path));
+        }
+    }
+}
+
+async fn save_locks(pid: i32, locks: &[FileLock], user_id: &str, pool: &Pool<Sqlite>) -> Result<(), GlassyError> {
+    DataAccessLayer::new(pool).save_locks(pid, locks).await?;
+    apply_lock_permissions(pid, user_id, pool).await;
+    Ok(())
+}
+
+fn locked_by_others(locks: &[FileLock], paths: &[String], user_id: &str) -> Vec<FileLock> {
+    locks
+        .iter()
+        .filter(|lock| lock.user_id != user_id && paths.contains(&lock.path))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::setup_project;
+    use crate::sync::hash_dir;
+    use sqlx::SqlitePool;
+    use std::fs;
+
+    #[sqlx::test]
+    async fn test_file_locks(pool: SqlitePool) {
+        let project = setup_project("lock", &pool).await;
+        let project_dir = &project.project_dir;
+        let dal = DataAccessLayer::new(&pool);
+        fs::write(project_dir.join("a.SLDPRT"), "a").unwrap();
+        fs::write(project_dir.join("b.SLDPRT"), "b").unwrap();
+        hash_dir(3, project_dir.clone(), &pool, false).await;
+
+        let mine = LocalLocks::new("me");
+        let theirs = mine.as_user("them");
+        let paths = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<String>>();
+
+        assert!(acquire_locks(3, &paths(&["a.SLDPRT"]), &theirs, &pool).await.unwrap().is_empty());
+        let taken = acquire_locks(3, &paths(&["a.SLDPRT", "b.SLDPRT"]), &mine, &pool).await.unwrap();
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].user_id, "them");
+        assert_eq!(dal.get_locks(3).await.unwrap().len(), 2);
+
+        // their file can't be uploaded, mine can
+        assert!(check_unlocked(3, &paths(&["b.SLDPRT"]), &mine, &pool).await.is_ok());
+        let res = check_unlocked(3, &paths(&["a.SLDPRT", "b.SLDPRT"]), &mine, &pool).await;
+        assert!(matches!(res, Err(GlassyError::Locked(_))));
+
+        // i can only release their lock by force
+        release_locks(3, &paths(&["a.SLDPRT"]), false, &mine, &pool).await.unwrap();
+        assert!(check_unlocked(3, &paths(&["a.SLDPRT"]), &mine, &pool).await.is_err());
+        release_locks(3, &paths(&["a.SLDPRT", "b.SLDPRT"]), true, &mine, &pool).await.unwrap();
+        assert!(dal.get_locks(3).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_readonly_checkout(pool: SqlitePool) {
+        let project = setup_project("readonly", &pool).await;
+        let project_dir = &project.project_dir;
+        let dal = DataAccessLayer::new(&pool);
+        fs::write(project_dir.join("a.SLDPRT"), "a").unwrap();
+        fs::write(project_dir.join("b.SLDPRT"), "b").unwrap();
+        hash_dir(3, project_dir.clone(), &pool, false).await;
+        sqlx::query("UPDATE file SET base_hash = curr_hash WHERE pid = 3").execute(&pool).await.unwrap();
+        let readonly = |name: &str| fs::metadata(project_dir.join(name)).unwrap().permissions().readonly();
+
+        let server = LocalLocks::new("me");
+        assert!(dal.set_readonly_checkout(3, true).await.unwrap());
+        refresh_locks(3, &server, &pool).await.unwrap();
+        assert!(readonly("a.SLDPRT") && readonly("b.SLDPRT"));
+
+        // checked out files can be edited until they are checked in
+        acquire_locks(3, &["a.SLDPRT".to_string()], &server, &pool).await.unwrap();
+        assert!(!readonly("a.SLDPRT") && readonly("b.SLDPRT"));
+        release_locks(3, &["a.SLDPRT".to_string()], false, &server, &pool).await.unwrap();
+        assert!(readonly("a.SLDPRT"));
+
+        clear_lock_permissions(3, &pool).await;
+        assert!(!readonly("a.SLDPRT") && !readonly("b.SLDPRT"));
+    }
+
+    #[sqlx::test]
+    async fn test_sync_keeps_own_checkouts_writable(pool: SqlitePool) {
+        let project = setup_project("sync-lock", &pool).await;
+        let project_dir = &project.project_dir;
+        let dal = DataAccessLayer::new(&pool);
+        fs::write(project_dir.join("a.SLDPRT"), "a").unwrap();
+        fs::write(project_dir.join("b.SLDPRT"), "b").unwrap();
+        hash_dir(3, project_dir.clone(), &pool, false).await;
+        sqlx::query("UPDATE file SET base_hash = curr_hash WHERE pid = 3").execute(&pool).await.unwrap();
+        let readonly = |name: &str| fs::metadata(project_dir.join(name)).unwrap().permissions().readonly();
+
+        let server = LocalLocks::new("me");
+        assert!(dal.set_readonly_checkout(3, true).await.unwrap());
+        acquire_locks(3, &["a.SLDPRT".to_string()], &server, &pool).await.unwrap();
+        sync_with_locks(3, Vec::new(), false, Some(&server), &pool).await.unwrap();
+        assert!(!readonly("a.SLDPRT") && readonly("b.SLDPRT"));
+
+        // a sync that doesn't know the user leaves the locks alone
+        sync_with_locks(3, Vec::new(), false, Some(&server.as_user("")), &pool).await.unwrap();
+        assert!(!readonly("a.SLDPRT") && readonly("b.SLDPRT"));
+        clear_lock_permissions(3, &pool).await;
+    }
+}
@@ -0,0 +1,315 @@
+use crate::chunking::{ChunkingServerOutput, ChunkingStrategy};
+use crate::config::{Config, Token};
+use crate::lock::LockServer;
+use crate::types::{DownloadInformation, DownloadRequest, DownloadServerOutput, FileLock, GlassyError, RemoteFile};
+use crate::upload::{ChunkDescriptor, MissingChunksRequest, MissingChunksResponse, UploadResponse, UploadedFile};
+use reqwest::multipart::{Form, Part};
+use reqwest::{Client, Proxy, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const USER_AGENT: &str = concat!("glassyPDM/", env!("CARGO_PKG_VERSION"));
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// api calls should be quick, chunks can take a while on a slow connection
+const API_TIMEOUT: Duration = Duration::from_secs(30);
+pub(crate) const CHUNK_TIMEOUT: Duration = Duration::from_secs(300);
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+const POOL_MAX_IDLE_PER_HOST: usize = 8;
+const MAX_RETRIES: u32 = 4;
+const RETRY_BASE_DELAY_MS: u64 = 500;
+
+// every GlassyClient shares one connection pool
+static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// the http client every request goes through. requests to the glassy server should use GlassyClient instead,
+/// this is for requests that don't need auth, e.g. presigned S3 urls.
+/// GLASSY_PROXY sets a proxy for everything, otherwise the system proxy is used
+pub fn http_client() -> &'static Client {
+    HTTP_CLIENT.get_or_init(|| {
+        let mut builder = Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+            .pool_max_idle_per_host(POOL_MAX_IDLE_PER_HOST);
+        if let Ok(proxy) = std::env::var("GLASSY_PROXY") {
+            match Proxy::all(&proxy) {
+                Ok(proxy) => builder = builder.proxy(proxy),
+                Err(err) => log::warn!("ignoring invalid GLASSY_PROXY {}: {}", proxy, err),
+            }
+        }
+        builder.build().unwrap_or_else(|err| {
+            log::error!("couldn't configure the http client, using the defaults: {}", err);
+            Client::new()
+        })
+    })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitRequest<'a> {
+    project_id: i32,
+    message: String,
+    files: &'a [UploadedFile],
+}
+
+#[derive(Deserialize)]
+struct CommitOutput {
+    response: String,
+    body: Option<CommitBody>,
+}
+
+#[derive(Deserialize)]
+struct CommitBody {
+    commit_id: i32,
+}
+
+#[derive(Deserialize)]
+struct ProjectStateOutput {
+    response: String,
+    body: Option<Vec<RemoteFile>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LockRequest<'a> {
+    project_id: i32,
+    paths: &'a [String],
+    force: bool,
+}
+
+#[derive(Deserialize)]
+struct LockOutput {
+    response: String,
+    body: Option<Vec<FileLock>>,
+    error: Option<String>,
+}
+
+/// talks to the glassy server as the user in a Config
+#[derive(Clone)]
+pub struct GlassyClient {
+    client: &'static Client,
+    server_url: String,
+    user_id: String,
+    token: Token,
+}
+
+impl GlassyClient {
+    pub fn new(config: &Config) -> GlassyClient {
+        GlassyClient {
+            client: http_client(),
+            server_url: config.server_url.clone(),
+            user_id: config.user_id.clone(),
+            token: config.token.clone(),
+        }
+    }
+
+    /// for requests that aren't to the glassy server
+    pub fn http(&self) -> &Client {
+        self.client
+    }
+
+    /// gets `path` from the server and parses the response, retrying if that fails
+    pub async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, GlassyError> {
+        let response = self
+            .send_idempotent(|| self.authed(self.client.get(self.server_url.clone() + path)).timeout(API_TIMEOUT))
+            .await?;
+        Ok(response.json::<T>().await?)
+    }
+
+    /// gets presigned urls for the chunks of `path` at `commit_id`
+    pub async fn request_download(&self, pid: i64, path: &str, commit_id: i64) -> Result<DownloadInformation, GlassyError> {
+        let body = DownloadRequest {
+            project_id: pid,
+            path: path.to_string(),
+            commit_id,
+            user_id: self.user_id.clone(),
+        };
+        let endpoint = self.server_url.clone() + "/store/download";
+        let response = self
+            .send_idempotent(|| self.authed(self.client.post(&endpoint)).json(&body).timeout(API_TIMEOUT))
+            .await?;
+        let output = response.json::<DownloadServerOutput>().await?;
+        match output.body {
+            Some(info) if output.response == "success" => Ok(info),
+            _ => Err(GlassyError::Server { response: output.response }),
+        }
+    }
+
+    /// uploads one chunk of a file. chunks are stored by their hash, so uploading one twice is harmless
+    pub async fn upload_chunk(
+        &self,
+        chunk: &ChunkDescriptor,
+        num_chunks: usize,
+        strategy: ChunkingStrategy,
+        data: Vec<u8>,
+    ) -> Result<(), GlassyError> {
+        let endpoint = self.server_url.clone() + "/store/request";
+        let response = self
+            .send_idempotent(|| {
+                let form = Form::new()
+                    .part("chunk", Part::bytes(data.clone()).file_name(chunk.block_hash.clone()))
+                    .text("file_hash", chunk.file_hash.clone())
+                    .text("block_hash", chunk.block_hash.clone())
+                    .text("num_chunks", num_chunks.to_string())
+                    .text("chunk_index", chunk.chunk_index.to_string())
+                    .text("offset", chunk.offset.to_string())
+                    .text("size", chunk.size.to_string())
+                    .text("chunking", strategy.as_str())
+                    .text("user_id", self.user_id.clone());
+                self.authed(self.client.post(&endpoint)).multipart(form).timeout(CHUNK_TIMEOUT)
+            })
+            .await?;
+        let output = response.json::<UploadResponse>().await?;
+        if output.response == "error" {
+            return Err(GlassyError::Server { response: output.error.unwrap_or(output.response) });
+        }
+        Ok(())
+    }
+
+    /// which of `chunks` the server doesn't have yet
+    pub async fn missing_chunks(&self, chunks: &[ChunkDescriptor]) -> Result<Vec<ChunkDescriptor>, GlassyError> {
+        let body = MissingChunksRequest {
+            user_id: self.user_id.clone(),
+            chunks: chunks.to_vec(),
+        };
+        let endpoint = self.server_url.clone() + "/store/missing";
+        let response = self
+            .send_idempotent(|| self.authed(self.client.post(&endpoint)).json(&body).timeout(API_TIMEOUT))
+            .await?;
+        match response.json::<MissingChunksResponse>().await? {
+            MissingChunksResponse { response, body: Some(body), .. } if response == "success" => Ok(body.missing),
+            res => Err(GlassyError::Server { response: res.error.unwrap_or(res.response) }),
+        }
+    }
+
+    /// the chunking strategies the server accepts
+    pub async fn chunking_strategies(&self) -> Result<Vec<String>, GlassyError> {
+        let output: ChunkingServerOutput = self.get_json("/store/chunking").await?;
+        match output.body {
+            Some(body) if output.response == "success" => Ok(body.strategies),
+            _ => Err(GlassyError::Server { response: output.response }),
+        }
+    }
+
+    /// commits files whose chunks were already uploaded. returns the new commit's id.
+    /// not retried, since the commit might have gone through
+    pub async fn commit(&self, pid: i32, message: String, files: &[UploadedFile]) -> Result<i32, GlassyError> {
+        let body = CommitRequest { project_id: pid, message, files };
+        let response = self
+            .authed(self.client.post(self.server_url.clone() + "/commit"))
+            .json(&body)
+            .timeout(API_TIMEOUT)
+            .send()
+            .await?;
+        let output = check_status(response)?.json::<CommitOutput>().await?;
+        match output.body {
+            Some(body) if output.response == "success" => Ok(body.commit_id),
+            _ => Err(GlassyError::Server { response: output.response }),
+        }
+    }
+
+    /// the project's files at `commit`, which can be "latest"
+    pub async fn project_status(&self, pid: i32, commit: &str) -> Result<Vec<RemoteFile>, GlassyError> {
+        let output: ProjectStateOutput = self.get_json(&format!("/project/status/by-id/{}/{}", pid, commit)).await?;
+        if output.response != "success" {
+            return Err(GlassyError::Server { response: output.response });
+        }
+        Ok(output.body.unwrap_or_default())
+    }
+
+    /// sends a lock request, which is safe to repeat, and returns the project's locks
+    async fn send_lock_request(&self, endpoint: &str, pid: i32, paths: &[String], force: bool) -> Result<Vec<FileLock>, GlassyError> {
+        let body = LockRequest { project_id: pid, paths, force };
+        let endpoint = self.server_url.clone() + endpoint;
+        let response = self
+            .send_idempotent(|| self.authed(self.client.post(&endpoint)).json(&body).timeout(API_TIMEOUT))
+            .await?;
+        lock_output(response.json::<LockOutput>().await?)
+    }
+
+    // read for every request, the token can be refreshed while a job runs
+    fn authed(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.token.get() {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// sends a request that is safe to repeat, retrying with exponential backoff if the connection fails,
+    /// times out or the server has a problem
+    async fn send_idempotent<F>(&self, make_request: F) -> Result<Response, GlassyError>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let mut attempt = 0;
+        loop {
+            let err = match make_request().send().await {
+                Ok(response) if is_transient(response.status()) => GlassyError::Server { response: response.status().to_string() },
+                Ok(response) => return check_status(response),
+                Err(err) if err.is_connect() || err.is_timeout() || err.is_request() => GlassyError::Network(err),
+                Err(err) => return Err(err.into()),
+            };
+            attempt += 1;
+            if attempt > MAX_RETRIES {
+                return Err(err);
+            }
+            log::warn!("request to the server failed (attempt {}), retrying: {}", attempt, err);
+            tokio::time::sleep(backoff(attempt)).await;
+        }
+    }
+}
+
+impl LockServer for GlassyClient {
+    fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    async fn lock(&self, pid: i32, paths: &[String]) -> Result<Vec<FileLock>, GlassyError> {
+        self.send_lock_request("/store/lock", pid, paths, false).await
+    }
+
+    async fn unlock(&self, pid: i32, paths: &[String], force: bool) -> Result<Vec<FileLock>, GlassyError> {
+        self.send_lock_request("/store/unlock", pid, paths, force).await
+    }
+
+    async fn locks(&self, pid: i32) -> Result<Vec<FileLock>, GlassyError> {
+        lock_output(self.get_json(&format!("/store/locks/{}", pid)).await?)
+    }
+}
+
+fn lock_output(output: LockOutput) -> Result<Vec<FileLock>, GlassyError> {
+    match output.body {
+        Some(locks) if output.response == "success" => Ok(locks),
+        _ => Err(GlassyError::Server { response: output.error.unwrap_or(output.response) }),
+    }
+}
+
+/// how long to wait before retry number `attempt`
+pub fn backoff(attempt: u32) -> Duration {
+    Duration::from_millis(RETRY_BASE_DELAY_MS * 2u64.pow(attempt.saturating_sub(1)))
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::REQUEST_TIMEOUT
+}
+
+fn check_status(response: Response) -> Result<Response, GlassyError> {
+    match response.status() {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(GlassyError::Auth),
+        _ => Ok(response),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::from_millis(500));
+        assert_eq!(backoff(2), Duration::from_millis(1000));
+        assert_eq!(backoff(4), Duration::from_millis(4000));
+    }
+}
@@ -0,0 +1,70 @@
+use crate::dal::DataAccessLayer;
+use crate::file::{native_path, sep};
+use crate::types::GlassyError;
+use sqlx::{Pool, Sqlite};
+use std::path::Path;
+
+// solidworks keeps the files it has open locked. on windows a transfer can't move them to the trash or replace them,
+// which undoes the whole transfer, and elsewhere the program would just save over the new revision.
+// so files that are open are found before a transfer and left out of it
+
+/// whether the file at `path` is open in another program. solidworks and office leave a `~$<name>` file next to
+/// the files they have open, and on windows a file that is open can't be opened exclusively
+pub fn is_file_open(path: &Path) -> bool {
+    if !path.is_file() {
+        return false;
+    }
+    if let (Some(parent), Some(name)) = (path.parent(), path.file_name()) {
+        if parent.join(format!("~${}", name.to_string_lossy())).exists() {
+            return true;
+        }
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        const ERROR_SHARING_VIOLATION: i32 = 32;
+        const ERROR_LOCK_VIOLATION: i32 = 33;
+        if let Err(err) = std::fs::OpenOptions::new().read(true).share_mode(0).open(path) {
+            return matches!(err.raw_os_error(), Some(ERROR_SHARING_VIOLATION) | Some(ERROR_LOCK_VIOLATION));
+        }
+    }
+    false
+}
+
+/// which of `rel_paths` in the project at `project_dir` are open in another program
+pub fn open_files_in(project_dir: &str, rel_paths: &[String]) -> Vec<String> {
+    rel_paths
+        .iter()
+        .filter(|rel_path| is_file_open(Path::new(&native_path(&(project_dir.to_string() + &sep().to_string() + rel_path)))))
+        .cloned()
+        .collect()
+}
+
+/// which of the project's `rel_paths` are open in another program, so the user can close them or leave them out
+pub async fn find_open_files(pid: i32, rel_paths: &[String], pool: &Pool<Sqlite>) -> Result<Vec<String>, GlassyError> {
+    let project_dir = DataAccessLayer::new(pool).get_project_dir(pid).await?;
+    if project_dir.is_empty() {
+        return Err(GlassyError::Setup(format!("project {} has no local folder", pid)));
+    }
+    Ok(open_files_in(&project_dir, rel_paths))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_open_files_in() {
+        let dir = std::env::temp_dir().join(format!("glassy-open-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("Parts")).unwrap();
+        fs::write(dir.join("Parts").join("bracket.SLDPRT"), "bracket").unwrap();
+        fs::write(dir.join("Parts").join("~$bracket.SLDPRT"), "").unwrap();
+        fs::write(dir.join("Parts").join("plate.SLDPRT"), "plate").unwrap();
+
+        let paths = vec!["Parts\\bracket.SLDPRT".to_string(), "Parts\\plate.SLDPRT".to_string(), "Parts\\new.SLDPRT".to_string()];
+        let open = open_files_in(&dir.display().to_string(), &paths);
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(open, vec!["Parts\\bracket.SLDPRT".to_string()]);
+    }
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct UploadProgress {
+    pub uploaded_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// where long running operations report their progress.
+/// the app forwards it to the frontend as events, the cli prints it
+pub trait ProgressReporter: Send + Sync {
+    /// a file finished uploading or resetting, `done` files so far
+    fn file_done(&self, _done: u32) {}
+
+    /// a chunk finished downloading, out of `total` chunks
+    fn chunk_downloaded(&self, _total: usize) {}
+
+    /// every chunk is in the cache and files are being assembled
+    fn chunks_cached(&self) {}
+
+    fn upload_progress(&self, _progress: UploadProgress) {}
+
+    /// files a transfer left alone because they are open in another program, see open_files.rs
+    fn files_skipped(&self, _paths: &[String]) {}
+}
+
+/// ignores all progress
+pub struct NoProgress;
+
+impl ProgressReporter for NoProgress {}
@@ -0,0 +1,275 @@
+use crate::commit::{commit_files, resume_commit};
+use crate::config::{Config, Token};
+use crate::conflict::{keep_both, take_theirs};
+use crate::dal::DataAccessLayer;
+use crate::download::download_project_files;
+use crate::job::JobControl;
+use crate::lock::sync_with_locks;
+use crate::network::GlassyClient;
+use crate::progress::ProgressReporter;
+use crate::references::download_with_references;
+use crate::reset::reset_project_files;
+use crate::types::{DownloadRequestMessage, GlassyError, QueuedJob, RemoteFile};
+use crate::util::delete_cache;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Pool, Sqlite};
+
+// syncs, transfers and cache cleanups are queued in the database and run in the background,
+// so commands return right away and queued jobs survive the app closing.
+// the app runs the worker that takes jobs off the queue, see src/jobs.rs
+
+// a job that was interrupted this many times is probably what keeps closing the app
+const MAX_ATTEMPTS: i64 = 3;
+// finished jobs are kept this long so they can still be listed
+const JOB_RETENTION_DAYS: u32 = 7;
+
+/// what a queued job does, saved as json in the job table
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Task {
+    Sync { remote: Vec<RemoteFile>, force_rehash: bool },
+    Download { files: Vec<DownloadRequestMessage> },
+    DownloadWithReferences { filepath: String },
+    Upload { filepaths: Vec<String>, message: String },
+    ResumeUpload,
+    Reset { filepaths: Vec<String> },
+    TakeTheirs { path: String },
+    KeepBoth { path: String },
+    CleanCache,
+}
+
+impl Task {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Task::Sync { .. } => "sync",
+            Task::Download { .. } => "download",
+            Task::DownloadWithReferences { .. } => "download_with_references",
+            Task::Upload { .. } => "upload",
+            Task::ResumeUpload => "resume_upload",
+            Task::Reset { .. } => "reset",
+            Task::TakeTheirs { .. } => "take_theirs",
+            Task::KeepBoth { .. } => "keep_both",
+            Task::CleanCache => "clean_cache",
+        }
+    }
+}
+
+/// jobs that talk to the server wait in the queue until the user's token is known
+pub fn needs_token(kind: &str) -> bool {
+    matches!(kind, "download" | "download_with_references" | "upload" | "resume_upload" | "reset" | "take_theirs" | "keep_both")
+}
+
+/// how a job ended, sent to the frontend
+#[derive(Serialize, Clone)]
+pub struct JobOutcome {
+    pub id: i64,
+    pub pid: i32,
+    pub kind: String,
+    pub result: Option<Value>,
+    pub error: Option<Value>, // the GlassyError the job failed with
+}
+
+/// queues `task` for the active server and returns the job's id
+pub async fn enqueue(pid: i32, user_id: String, task: Task, pool: &Pool<Sqlite>) -> Result<i64, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let url = dal.get_active_server().await?;
+    if url.is_empty() {
+        return Err(GlassyError::Setup("no active server".to_string()));
+    }
+    let json = serde_json::to_string(&task).map_err(|err| GlassyError::Job(err.to_string()))?;
+    let jid = dal.add_job(url, pid, task.kind(), json, user_id).await?;
+    log::info!("queued {} job {} for project {}", task.kind(), jid, pid);
+    Ok(jid)
+}
+
+/// called once when the app starts, before any job is taken off the queue
+pub async fn recover_jobs(pool: &Pool<Sqlite>) -> Result<(), GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let error = GlassyError::Job(format!("interrupted {} times", MAX_ATTEMPTS));
+    let error = serde_json::to_string(&error).unwrap_or_default();
+    dal.requeue_interrupted_jobs(MAX_ATTEMPTS, error).await?;
+    dal.prune_jobs(JOB_RETENTION_DAYS).await
+}
+
+/// picks queued jobs to start, oldest first, so that at most `slots` more run.
+/// `running` has the pids of the jobs that are running. a project only runs one job at a time,
+/// and jobs that aren't about one project (pid -1) run on their own
+pub fn pick_jobs(queued: Vec<QueuedJob>, running: &[i32], slots: usize, has_token: bool) -> Vec<QueuedJob> {
+    let mut busy = running.to_vec();
+    let mut picked = Vec::new();
+    for job in queued {
+        if picked.len() >= slots || busy.contains(&-1) {
+            break;
+        }
+        if job.pid == -1 {
+            // later jobs wait too, so this one isn't starved
+            if busy.is_empty() {
+                busy.push(-1);
+                picked.push(job);
+            }
+            break;
+        }
+        if busy.contains(&job.pid) || (!has_token && needs_token(&job.kind)) {
+            continue;
+        }
+        busy.push(job.pid);
+        picked.push(job);
+    }
+    picked
+}
+
+/// takes the jobs to start next off the queue, see pick_jobs
+pub async fn take_jobs(pool: &Pool<Sqlite>, running: &[i32], slots: usize, has_token: bool) -> Result<Vec<QueuedJob>, GlassyError> {
+    if slots == 0 {
+        return Ok(Vec::new());
+    }
+    let dal = DataAccessLayer::new(pool);
+    let url = dal.get_active_server().await?;
+    let mut jobs = pick_jobs(dal.get_queued_jobs(url).await?, running, slots, has_token);
+    for job in jobs.iter_mut() {
+        dal.start_job(job.jid).await?;
+        job.state = "running".to_string();
+        job.attempts += 1;
+    }
+    Ok(jobs)
+}
+
+/// runs a job taken off the queue as the user who queued it, and saves how it ended
+pub async fn run_job(
+    job: &QueuedJob,
+    token: Token,
+    pool: &Pool<Sqlite>,
+    progress: &dyn ProgressReporter,
+    control: &JobControl,
+) -> JobOutcome {
+    log::info!("running {} job {} for project {}, attempt {}", job.kind, job.jid, job.pid, job.attempts);
+    let res = match serde_json::from_str::<Task>(&job.task) {
+        Ok(task) => run_task(job, task, token, pool, progress, control).await,
+        Err(err) => Err(GlassyError::Job(err.to_string())),
+    };
+
+    let (state, result, error) = match res {
+        Ok(result) => ("done", Some(result), None),
+        Err(GlassyError::Cancelled) => ("cancelled", None, serde_json::to_value(GlassyError::Cancelled).ok()),
+        Err(err) => {
+            log::error!("{} job {} failed: {}", job.kind, job.jid, err);
+            ("failed", None, serde_json::to_value(err).ok())
+        }
+    };
+    let dal = DataAccessLayer::new(pool);
+    let _ = dal
+        .finish_job(
+            job.jid,
+            state,
+            result.as_ref().map(|result| result.to_string()),
+            error.as_ref().map(|err| err.to_string()),
+        )
+        .await;
+
+    JobOutcome { id: job.jid, pid: job.pid, kind: job.kind.clone(), result, error }
+}
+
+async fn run_task(
+    job: &QueuedJob,
+    task: Task,
+    token: Token,
+    pool: &Pool<Sqlite>,
+    progress: &dyn ProgressReporter,
+    control: &JobControl,
+) -> Result<Value, GlassyError> {
+    let pid = job.pid;
+    let config = &Config::load(job.user_id.clone(), pool).await?.with_token(token);
+    match task {
+        Task::Sync { remote, force_rehash } => {
+            let client = GlassyClient::new(config);
+            let server = config.token.get().is_some().then_some(&client);
+            to_value(sync_with_locks(pid, remote, force_rehash, server, pool).await?)
+        }
+        Task::Download { files } => to_value(download_project_files(pid, files, config, pool, progress, control).await?),
+        Task::DownloadWithReferences { filepath } => to_value(download_with_references(pid, filepath, config, pool, progress, control).await?),
+        Task::Upload { filepaths, message } => {
+            // a retried upload finishes the commit job its first attempt left behind
+            let dal = DataAccessLayer::new(pool);
+            if job.attempts > 1 && dal.get_commit_job(pid).await?.is_some() {
+                to_value(resume_commit(pid, config, pool, progress, control).await?)
+            } else {
+                to_value(commit_files(pid, filepaths, message, config, pool, progress, control).await?)
+            }
+        }
+        Task::ResumeUpload => to_value(resume_commit(pid, config, pool, progress, control).await?),
+        Task::Reset { filepaths } => to_value(reset_project_files(pid, filepaths, config, pool, progress, control).await?),
+        Task::TakeTheirs { path } => to_value(take_theirs(pid, path, config, pool, progress, control).await?),
+        Task::KeepBoth { path } => to_value(keep_both(pid, path, config, pool).await?),
+        Task::CleanCache => to_value(delete_cache(pool).await?),
+    }
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, GlassyError> {
+    serde_json::to_value(value).map_err(|err| GlassyError::Job(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::SqlitePool;
+
+    fn queued(jid: i64, pid: i32, kind: &str) -> QueuedJob {
+        QueuedJob {
+            jid,
+            pid,
+            kind: kind.to_string(),
+            task: String::new(),
+            user_id: String::new(),
+            state: "queued".to_string(),
+            attempts: 0,
+            error: None,
+            created: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_pick_jobs() {
+        let jids = |jobs: Vec<QueuedJob>| jobs.iter().map(|job| job.jid).collect::<Vec<i64>>();
+        let queue = vec![queued(1, 3, "download"), queued(2, 3, "sync"), queued(3, 4, "sync"), queued(4, -1, "clean_cache"), queued(5, 5, "sync")];
+
+        // one job per project, and nothing after a job that has to run on its own
+        assert_eq!(jids(pick_jobs(queue.clone(), &[], 4, true)), vec![1, 3]);
+        assert_eq!(jids(pick_jobs(queue.clone(), &[], 1, true)), vec![1]);
+        assert_eq!(jids(pick_jobs(queue.clone(), &[4], 4, true)), vec![1]);
+        // without a token, transfers wait
+        assert_eq!(jids(pick_jobs(queue.clone(), &[], 4, false)), vec![2, 3]);
+        // the cache is cleaned once everything else is done
+        assert_eq!(jids(pick_jobs(queue[3..].to_vec(), &[], 4, true)), vec![4]);
+        assert!(pick_jobs(queue[4..].to_vec(), &[-1], 4, true).is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_recover_jobs(pool: SqlitePool) {
+        let dal = DataAccessLayer::new(&pool);
+        let _ = dal.add_server("url".to_string(), "key".to_string(), "owo/location".to_string(), "test server".to_string()).await;
+
+        let first = enqueue(3, "user".to_string(), Task::Reset { filepaths: vec!["a.SLDPRT".to_string()] }, &pool).await.unwrap();
+        let second = enqueue(-1, "user".to_string(), Task::CleanCache, &pool).await.unwrap();
+        let jobs = take_jobs(&pool, &[], 2, true).await.unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].jid, first);
+        assert_eq!(jobs[0].attempts, 1);
+
+        // the app closed while the first job was running, so it is queued again
+        recover_jobs(&pool).await.unwrap();
+        assert_eq!(dal.get_job(first).await.unwrap().unwrap().state, "queued");
+        assert!(dal.cancel_queued_job(second).await.unwrap());
+
+        // until it was interrupted too often
+        for _ in 1..MAX_ATTEMPTS {
+            take_jobs(&pool, &[], 2, true).await.unwrap();
+            recover_jobs(&pool).await.unwrap();
+        }
+        let job = dal.get_job(first).await.unwrap().unwrap();
+        assert_eq!(job.state, "failed");
+        assert_eq!(job.attempts, MAX_ATTEMPTS);
+        assert!(job.error.unwrap().contains("Job"));
+        assert!(take_jobs(&pool, &[], 2, true).await.unwrap().is_empty());
+    }
+}
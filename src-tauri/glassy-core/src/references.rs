@@ -0,0 +1,423 @@
+use crate::config::Config;
+use crate::dal::DataAccessLayer;
+use crate::download::download_project_files;
+use crate::file::{native_path, sep};
+use crate::job::JobControl;
+use crate::progress::ProgressReporter;
+use crate::sync::FileChange;
+use crate::types::{ChangeType, DownloadRequestMessage, GlassyError};
+use rayon::prelude::*;
+use sqlx::{Pool, Sqlite};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+
+// assemblies, drawings and some other cad files only open if the files they use are there too.
+// what a file references is read from its local copy whenever it changes and kept in file_reference as written in the file.
+// references are matched to project files when they are used, so a part that is added later is still found.
+// that way uploads can warn about new parts that were left out, and an assembly can be downloaded with everything it needs
+
+// download_with_references goes one level of references deeper each round, this deep at most
+const MAX_REFERENCE_DEPTH: usize = 16;
+// shorter runs of text in a binary file can't be a path to a part
+const MIN_RUN_LENGTH: usize = 8;
+
+/// the kinds of files references are read from
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Format {
+    SolidWorks,     // assemblies and drawings keep the full path of each file they use
+    Step,           // an assembly can be split into files, see DOCUMENT_FILE and EXTERNAL_SOURCE
+    KicadProject,   // the schematic and board next to it
+    KicadSchematic, // hierarchical sheets are kept in their own files
+}
+
+fn format_of(path: &str) -> Option<Format> {
+    let name = file_name(path).to_ascii_lowercase();
+    match name.rsplit_once('.')?.1 {
+        "sldasm" | "slddrw" => Some(Format::SolidWorks),
+        "step" | "stp" => Some(Format::Step),
+        "kicad_pro" => Some(Format::KicadProject),
+        "kicad_sch" => Some(Format::KicadSchematic),
+        _ => None,
+    }
+}
+
+/// whether references are read from the file at `path`
+pub fn has_references(path: &str) -> bool {
+    format_of(path).is_some()
+}
+
+/// the paths `data`, the contents of the file at `rel_path`, refers to, as they are written in the file but with
+/// backslashes. they can be absolute, from whoever saved the file, or relative to the file's folder
+pub fn read_references(rel_path: &str, data: &[u8]) -> Vec<String> {
+    let mut output = match format_of(rel_path) {
+        Some(Format::SolidWorks) => {
+            let mut paths = Vec::new();
+            for_each_run(data, &mut |run| paths.extend(paths_in(run, &["sldprt", "sldasm"])));
+            paths
+        }
+        Some(Format::Step) => {
+            // the header names the file itself
+            let text = String::from_utf8_lossy(data);
+            let body = text.find("DATA;").map(|idx| &text[idx..]).unwrap_or(&text);
+            quoted_paths(body, '\'', &["stp", "step"])
+        }
+        Some(Format::KicadProject) => {
+            let stem = file_name(rel_path).rsplit_once('.').map(|(stem, _)| stem).unwrap_or_default();
+            vec![format!("{}.kicad_sch", stem), format!("{}.kicad_pcb", stem)]
+        }
+        Some(Format::KicadSchematic) => quoted_paths(&String::from_utf8_lossy(data), '"', &["kicad_sch"]),
+        None => Vec::new(),
+    };
+    output.sort();
+    output.dedup();
+    output
+}
+
+/// calls `f` with every run of printable text in `data`, read as ascii and as utf-16 at either alignment
+fn for_each_run(data: &[u8], f: &mut impl FnMut(&str)) {
+    let mut run = String::new();
+    let mut flush = |run: &mut String| {
+        if run.len() >= MIN_RUN_LENGTH && run.contains('.') {
+            f(run);
+        }
+        run.clear();
+    };
+    for &byte in data {
+        if (0x20..0x7f).contains(&byte) {
+            run.push(byte as char);
+        } else {
+            flush(&mut run);
+        }
+    }
+    flush(&mut run);
+    for start in 0..2.min(data.len()) {
+        for pair in data[start..].chunks_exact(2) {
+            match char::from_u32(u16::from_le_bytes([pair[0], pair[1]]) as u32) {
+                Some(c) if !c.is_control() => run.push(c),
+                _ => flush(&mut run),
+            }
+        }
+        flush(&mut run);
+    }
+}
+
+/// paths in a run of text that end in one of `extensions`
+fn paths_in(run: &str, extensions: &[&str]) -> Vec<String> {
+    // only ascii letters change case, so indices into lower are indices into run
+    let lower = run.to_ascii_lowercase();
+    let mut output = Vec::new();
+    for extension in extensions {
+        let suffix = format!(".{}", extension);
+        for (idx, _) in lower.match_indices(&suffix) {
+            let end = idx + suffix.len();
+            if lower[end..].chars().next().is_some_and(|c| c.is_alphanumeric()) {
+                continue;
+            }
+            // the drive letter is left off, the rest of the path is what matters
+            let start = run[..idx]
+                .char_indices()
+                .rev()
+                .find(|(_, c)| matches!(c, '"' | '\'' | '<' | '>' | '|' | '*' | '?' | ':'))
+                .map(|(i, c)| i + c.len_utf8())
+                .unwrap_or(0);
+            if start < idx {
+                output.push(run[start..end].replace('/', "\\"));
+            }
+        }
+    }
+    output
+}
+
+/// quoted strings in `text` that are paths ending in one of `extensions`
+fn quoted_paths(text: &str, quote: char, extensions: &[&str]) -> Vec<String> {
+    text.split(quote)
+        .skip(1)
+        .step_by(2)
+        .filter(|part| !part.contains('\n') && extensions.iter().any(|extension| part.to_ascii_lowercase().ends_with(&format!(".{}", extension))))
+        .map(|part| part.replace('/', "\\"))
+        .collect()
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit(['\\', '/']).next().unwrap_or(path)
+}
+
+fn parent(path: &str) -> &str {
+    path.rsplit_once('\\').map(|(parent, _)| parent).unwrap_or("")
+}
+
+/// looks up the project's files by path and by name, ignoring case like windows does
+pub struct PathIndex {
+    by_path: HashMap<String, String>,
+    by_name: HashMap<String, Vec<String>>,
+}
+
+impl PathIndex {
+    pub fn new(paths: Vec<String>) -> PathIndex {
+        let mut index = PathIndex { by_path: HashMap::new(), by_name: HashMap::new() };
+        for path in paths {
+            index.by_name.entry(file_name(&path).to_ascii_lowercase()).or_default().push(path.clone());
+            index.by_path.insert(path.to_ascii_lowercase(), path);
+        }
+        index
+    }
+
+    /// the project file `reference`, read from the file at `from`, is about. an absolute path is from whoever saved the file,
+    /// so it goes to the file that has the longest end of the path in common with it, preferring the folder `from` is in
+    pub fn resolve(&self, reference: &str, from: &str) -> Option<String> {
+        if !reference.starts_with('\\') && !reference.contains(':') {
+            let mut components: Vec<&str> = Vec::new();
+            for component in parent(from).split('\\').chain(reference.split('\\')) {
+                match component {
+                    "" | "." => {}
+                    ".." => {
+                        components.pop();
+                    }
+                    _ => components.push(component),
+                }
+            }
+            return self.by_path.get(&components.join("\\").to_ascii_lowercase()).cloned();
+        }
+
+        let wanted: Vec<String> = reference.split('\\').rev().map(|component| component.to_ascii_lowercase()).collect();
+        let folder = parent(from).to_ascii_lowercase();
+        self.by_name
+            .get(wanted.first()?)?
+            .iter()
+            .max_by_key(|candidate| {
+                let common = candidate
+                    .split('\\')
+                    .rev()
+                    .zip(wanted.iter())
+                    .take_while(|(component, wanted)| component.to_ascii_lowercase() == **wanted)
+                    .count();
+                // then the shallowest path, then the first one alphabetically
+                let depth = candidate.matches('\\').count();
+                (common, parent(candidate).to_ascii_lowercase() == folder, Reverse(depth), Reverse(*candidate))
+            })
+            .cloned()
+    }
+}
+
+/// reads the references of the project's files that changed since they were last read
+pub async fn update_references(pid: i32, pool: &Pool<Sqlite>) -> Result<(), GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let unread: Vec<(String, String)> = dal
+        .get_unread_references(pid)
+        .await?
+        .into_iter()
+        .filter(|(path, _)| has_references(path))
+        .collect();
+    if unread.is_empty() {
+        return Ok(());
+    }
+    let project_dir = dal.get_project_dir(pid).await?;
+
+    let read: Vec<(String, String, Vec<String>)> = unread
+        .into_par_iter()
+        .filter_map(|(rel_path, hash)| {
+            let path = native_path(&(project_dir.clone() + &sep().to_string() + &rel_path));
+            let data = match fs::read(&path) {
+                Ok(data) => data,
+                Err(err) => {
+                    log::warn!("couldn't read references of {}: {}", path, err);
+                    return None;
+                }
+            };
+            let references = read_references(&rel_path, &data);
+            Some((rel_path, hash, references))
+        })
+        .collect();
+    log::info!("read references of {} files in project {}", read.len(), pid);
+    for (rel_path, hash, references) in read {
+        dal.set_references(pid, &rel_path, &hash, &references).await?;
+    }
+    Ok(())
+}
+
+/// the project files each local file needs. references to files that aren't in the project are left out
+pub async fn get_reference_graph(pid: i32, pool: &Pool<Sqlite>) -> Result<HashMap<String, Vec<String>>, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let index = PathIndex::new(dal.get_project_paths(pid).await?);
+    let mut graph = dal.get_references(pid).await?;
+    for (filepath, references) in graph.iter_mut() {
+        let mut resolved: Vec<String> = references
+            .iter()
+            .filter_map(|reference| index.resolve(reference, filepath))
+            .filter(|reference| reference != filepath)
+            .collect();
+        resolved.sort();
+        resolved.dedup();
+        *references = resolved;
+    }
+    Ok(graph)
+}
+
+/// sets the references of each upload to the other new or changed files it needs,
+/// so the frontend can warn when they are left out of an upload
+pub async fn attach_references(pid: i32, uploads: &mut [FileChange], pool: &Pool<Sqlite>) -> Result<(), GlassyError> {
+    update_references(pid, pool).await?;
+    let graph = get_reference_graph(pid, pool).await?;
+    let changed: HashSet<String> = uploads
+        .iter()
+        .filter(|upload| upload.change_type != ChangeType::Delete)
+        .map(|upload| upload.filepath.clone())
+        .collect();
+    for upload in uploads.iter_mut() {
+        if let Some(references) = graph.get(&upload.filepath) {
+            upload.references = references.iter().filter(|reference| changed.contains(*reference)).cloned().collect();
+        }
+    }
+    Ok(())
+}
+
+/// `roots` and every file they need, directly or through other files
+pub fn references_closure(roots: &[String], graph: &HashMap<String, Vec<String>>) -> Vec<String> {
+    let mut seen: HashSet<String> = roots.iter().cloned().collect();
+    let mut queue: VecDeque<String> = roots.iter().cloned().collect();
+    while let Some(path) = queue.pop_front() {
+        for reference in graph.get(&path).into_iter().flatten() {
+            if seen.insert(reference.clone()) {
+                queue.push_back(reference.clone());
+            }
+        }
+    }
+    let mut output: Vec<String> = seen.into_iter().collect();
+    output.sort();
+    output
+}
+
+/// downloads `filepath` and whatever it needs that isn't up to date. a file's references are only known
+/// once it is downloaded, so each round goes a level deeper
+pub async fn download_with_references(
+    pid: i32,
+    filepath: String,
+    config: &Config,
+    pool: &Pool<Sqlite>,
+    progress: &dyn ProgressReporter,
+    job: &JobControl,
+) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let mut downloaded: HashSet<String> = HashSet::new();
+    for _ in 0..MAX_REFERENCE_DEPTH {
+        update_references(pid, pool).await?;
+        let needed: HashSet<String> = references_closure(std::slice::from_ref(&filepath), &get_reference_graph(pid, pool).await?).into_iter().collect();
+        // files deleted on the server are left alone, the assembly still needs them
+        let files: Vec<DownloadRequestMessage> = dal
+            .get_downloads(pid)
+            .await?
+            .into_iter()
+            .filter(|file| file.change_type != ChangeType::Delete && needed.contains(&file.filepath) && !downloaded.contains(&file.filepath))
+            .map(|file| DownloadRequestMessage { commit_id: file.commit_id as i64, rel_path: file.filepath, hash: file.hash, download: true })
+            .collect();
+        if files.is_empty() {
+            return Ok(true);
+        }
+        log::info!("downloading {} files {} needs", files.len(), filepath);
+        downloaded.extend(files.iter().map(|file| file.rel_path.clone()));
+        if !download_project_files(pid, files, config, pool, progress, job).await? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::setup_project;
+    use crate::sync::hash_dir;
+    use sqlx::SqlitePool;
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_read_references() {
+        // solidworks keeps paths as utf-16 among binary data
+        let mut assembly = vec![0u8, 7, 1];
+        assembly.extend(utf16("C:\\Users\\bob\\glassy\\team\\car\\Parts\\bracket.SLDPRT"));
+        assembly.extend([0, 0, 3]);
+        assembly.extend(utf16("D:/old/Sub.sldasm"));
+        assembly.extend([0, 0]);
+        assert_eq!(
+            read_references("top.SLDASM", &assembly),
+            vec!["\\Users\\bob\\glassy\\team\\car\\Parts\\bracket.SLDPRT".to_string(), "\\old\\Sub.sldasm".to_string()]
+        );
+
+        let step = "ISO-10303-21;\nHEADER;\nFILE_NAME('top.stp','',(''),(''),'','','');\nENDSEC;\nDATA;\n\
+            #10=DOCUMENT_FILE('parts/bolt.stp','',#11,'','','');\n#20=EXTERNAL_SOURCE(IDENTIFIER('nut.STEP'));\nENDSEC;\n";
+        assert_eq!(read_references("top.stp", step.as_bytes()), vec!["nut.STEP".to_string(), "parts\\bolt.stp".to_string()]);
+
+        let schematic = "(kicad_sch (sheet (property \"Sheetname\" \"Power\") (property \"Sheetfile\" \"power.kicad_sch\")))";
+        assert_eq!(read_references("pcb\\board.kicad_sch", schematic.as_bytes()), vec!["power.kicad_sch".to_string()]);
+        assert_eq!(read_references("pcb\\board.kicad_pro", b"{}"), vec!["board.kicad_pcb".to_string(), "board.kicad_sch".to_string()]);
+        assert!(read_references("bracket.SLDPRT", &assembly).is_empty());
+    }
+
+    #[test]
+    fn test_resolve_references() {
+        let index = PathIndex::new(vec![
+            "Parts\\bracket.SLDPRT".to_string(),
+            "Old\\Parts\\bracket.SLDPRT".to_string(),
+            "Frame\\bolt.SLDPRT".to_string(),
+            "Hardware\\bolt.SLDPRT".to_string(),
+            "pcb\\power.kicad_sch".to_string(),
+        ]);
+        // the longest common end of the path wins, then the folder of the assembly
+        assert_eq!(index.resolve("\\Users\\bob\\car\\Parts\\BRACKET.sldprt", "top.SLDASM").as_deref(), Some("Parts\\bracket.SLDPRT"));
+        assert_eq!(index.resolve("\\Users\\bob\\car\\Old\\Parts\\bracket.SLDPRT", "top.SLDASM").as_deref(), Some("Old\\Parts\\bracket.SLDPRT"));
+        assert_eq!(index.resolve("\\elsewhere\\bolt.SLDPRT", "Hardware\\kit.SLDASM").as_deref(), Some("Hardware\\bolt.SLDPRT"));
+        assert_eq!(index.resolve("\\elsewhere\\bolt.SLDPRT", "top.SLDASM").as_deref(), Some("Frame\\bolt.SLDPRT"));
+        assert_eq!(index.resolve("\\toolbox\\washer.SLDPRT", "top.SLDASM"), None);
+        // relative paths are from the file's folder
+        assert_eq!(index.resolve("power.kicad_sch", "pcb\\board.kicad_sch").as_deref(), Some("pcb\\power.kicad_sch"));
+        assert_eq!(index.resolve("..\\Parts\\bracket.SLDPRT", "Frame\\frame.stp").as_deref(), Some("Parts\\bracket.SLDPRT"));
+        assert_eq!(index.resolve("power.kicad_sch", "board.kicad_sch"), None);
+
+        let graph = HashMap::from([
+            ("top.SLDASM".to_string(), vec!["Sub.SLDASM".to_string(), "a.SLDPRT".to_string()]),
+            ("Sub.SLDASM".to_string(), vec!["b.SLDPRT".to_string(), "top.SLDASM".to_string()]),
+        ]);
+        assert_eq!(references_closure(&["top.SLDASM".to_string()], &graph), vec!["Sub.SLDASM", "a.SLDPRT", "b.SLDPRT", "top.SLDASM"]);
+    }
+
+    #[sqlx::test]
+    async fn test_attach_references(pool: SqlitePool) {
+        let project = setup_project("references", &pool).await;
+        let project_dir = &project.project_dir;
+        let dal = DataAccessLayer::new(&pool);
+        fs::create_dir_all(project_dir.join("Parts")).unwrap();
+
+        let mut assembly = vec![0u8, 0];
+        assembly.extend(utf16("C:\\Users\\someone\\car\\Parts\\bracket.SLDPRT"));
+        assembly.extend([0, 0]);
+        assembly.extend(utf16("C:\\Users\\someone\\car\\Parts\\plate.SLDPRT"));
+        fs::write(project_dir.join("top.SLDASM"), &assembly).unwrap();
+        fs::write(project_dir.join("Parts").join("bracket.SLDPRT"), "bracket").unwrap();
+        hash_dir(3, project_dir.clone(), &pool, false).await;
+        let mut uploads = dal.get_uploads(3).await.unwrap();
+        attach_references(3, &mut uploads, &pool).await.unwrap();
+        let top = uploads.iter().find(|upload| upload.filepath == "top.SLDASM").unwrap();
+        assert_eq!(top.references, vec!["Parts\\bracket.SLDPRT"]);
+
+        // a part added after the assembly was read is still found
+        fs::write(project_dir.join("Parts").join("plate.SLDPRT"), "plate").unwrap();
+        hash_dir(3, project_dir.clone(), &pool, false).await;
+        let mut uploads = dal.get_uploads(3).await.unwrap();
+        attach_references(3, &mut uploads, &pool).await.unwrap();
+        let top = uploads.iter().find(|upload| upload.filepath == "top.SLDASM").unwrap();
+        assert_eq!(top.references, vec!["Parts\\bracket.SLDPRT", "Parts\\plate.SLDPRT"]);
+        assert!(uploads.iter().filter(|upload| upload.filepath != "top.SLDASM").all(|upload| upload.references.is_empty()));
+
+        // read once, until the assembly changes
+        assert!(dal.get_unread_references(3).await.unwrap().iter().all(|(path, _)| !has_references(path)));
+        fs::write(project_dir.join("top.SLDASM"), utf16("C:\\car\\Parts\\plate.SLDPRT")).unwrap();
+        hash_dir(3, project_dir.clone(), &pool, false).await;
+        update_references(3, &pool).await.unwrap();
+        let graph = get_reference_graph(3, &pool).await.unwrap();
+        assert_eq!(graph["top.SLDASM"], vec!["Parts\\plate.SLDPRT"]);
+    }
+}
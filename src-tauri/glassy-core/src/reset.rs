@@ -0,0 +1,68 @@
+use crate::config::Config;
+use crate::job::JobControl;
+use crate::progress::ProgressReporter;
+use crate::transfer::{run_transfer, TransferPlan, TransferReason};
+use crate::types::{DownloadRequestMessage, GlassyError};
+use sqlx::{Pool, Row, Sqlite};
+
+/// throws away local changes to `filepaths`, restoring them from the cache or server
+pub async fn reset_project_files(
+    pid: i32,
+    filepaths: Vec<String>,
+    config: &Config,
+    pool: &Pool<Sqlite>,
+    progress: &dyn ProgressReporter,
+    job: &JobControl,
+) -> Result<bool, GlassyError> {
+    // separate into download and delete lists
+    let mut to_copy: Vec<DownloadRequestMessage> = Vec::new();
+    let mut to_delete: Vec<DownloadRequestMessage> = Vec::new();
+    for file in filepaths.clone() {
+        let result = sqlx::query(
+            "
+            SELECT base_commitid, base_hash, curr_hash FROM file WHERE
+            pid = $1 AND filepath = $2 LIMIT 1;
+            ",
+        )
+        .bind(pid)
+        .bind(file.clone())
+        .fetch_one(pool)
+        .await;
+
+        match result {
+            Ok(row) => {
+                let commit: i64 = row.get::<i64, &str>("base_commitid");
+                let base_hash: String = row.get::<String, &str>("base_hash");
+                let curr_hash: String = row.get::<String, &str>("curr_hash");
+                if commit >= 0 {
+                    // the transfer restores it from the cache if it's there
+                    to_copy.push(DownloadRequestMessage {
+                        hash: base_hash,
+                        rel_path: file,
+                        commit_id: commit,
+                        download: true,
+                    });
+                } else {
+                    to_delete.push(DownloadRequestMessage {
+                        commit_id: -1,
+                        download: false,
+                        rel_path: file,
+                        hash: curr_hash,
+                    });
+                }
+            }
+            Err(err) => {
+                log::error!("reset files error: {}", err);
+                return Err(err.into());
+            }
+        }
+    }
+
+    let plan = TransferPlan {
+        pid,
+        reason: TransferReason::Reset,
+        fetch: to_copy,
+        delete: to_delete,
+    };
+    run_transfer(plan, config, pool, progress, job).await
+}
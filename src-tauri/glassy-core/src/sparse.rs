@@ -0,0 +1,127 @@
+use crate::dal::DataAccessLayer;
+use crate::file::{build_rules, is_valid_ignore_rule, match_rules};
+use crate::types::GlassyError;
+use ignore::gitignore::Gitignore;
+use sqlx::{Pool, Sqlite};
+
+// a sparse checkout only keeps some of a project's folders on this computer, e.g. just `Suspension/`.
+// its patterns use the same syntax as ignore rules (see file.rs), but list what to check out, and `!` leaves something out again.
+// files outside of it stay tracked on the server: they aren't downloaded, hashed or shown as deleted,
+// and copies of them that are already on disk are left alone. a project without patterns is checked out fully
+
+/// which of a project's files are checked out
+pub struct SparseSpec {
+    rules: Option<Gitignore>,
+}
+
+impl SparseSpec {
+    pub fn new(patterns: &[String]) -> SparseSpec {
+        if patterns.is_empty() {
+            return SparseSpec { rules: None };
+        }
+        SparseSpec { rules: Some(build_rules(patterns)) }
+    }
+
+    pub async fn load(pid: i32, pool: &Pool<Sqlite>) -> SparseSpec {
+        let dal = DataAccessLayer::new(pool);
+        SparseSpec::new(&dal.get_sparse_rules(pid).await.unwrap_or_default())
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.rules.is_none()
+    }
+
+    /// `rel_path` is a path in the project with backslashes, like in the file table
+    pub fn includes(&self, rel_path: &str) -> bool {
+        match &self.rules {
+            Some(rules) => match_rules(rules, rel_path).is_ignore(),
+            None => true,
+        }
+    }
+}
+
+/// adds `pattern` to the project's sparse checkout. returns false if it can't be parsed or the checkout has it already.
+/// the files it adds show up in get_downloads right away
+pub async fn add_sparse_pattern(pid: i32, pattern: String, pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let pattern = pattern.trim().to_string();
+    if !is_valid_ignore_rule(&pattern) || dal.get_sparse_rules(pid).await?.contains(&pattern) {
+        return Ok(false);
+    }
+    dal.add_sparse_rule(pid, pattern).await
+}
+
+/// takes `pattern` out of the project's sparse checkout. removing the last one checks out the whole project
+pub async fn remove_sparse_pattern(pid: i32, pattern: String, pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    dal.remove_sparse_rule(pid, pattern).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::setup_project;
+    use crate::sync::sync_project;
+    use crate::types::RemoteFile;
+    use sqlx::SqlitePool;
+    use std::fs;
+
+    #[test]
+    fn test_sparse_spec() {
+        let spec = SparseSpec::new(&["Suspension/".to_string(), "!Suspension/old/".to_string(), "*.pdf".to_string()]);
+        assert!(spec.includes("SDM-25\\Suspension\\upright.SLDPRT"));
+        assert!(spec.includes("Suspension\\arms\\a-arm.SLDPRT"));
+        assert!(!spec.includes("Suspension\\old\\upright.SLDPRT"));
+        assert!(!spec.includes("Drivetrain\\diff.SLDPRT"));
+        assert!(spec.includes("Drivetrain\\drawing.PDF"));
+
+        let full = SparseSpec::new(&[]);
+        assert!(full.is_full());
+        assert!(full.includes("Drivetrain\\diff.SLDPRT"));
+    }
+
+    #[sqlx::test]
+    async fn test_sparse_checkout(pool: SqlitePool) {
+        let project = setup_project("sparse", &pool).await;
+        let project_dir = &project.project_dir;
+        let dal = DataAccessLayer::new(&pool);
+        fs::create_dir_all(project_dir.join("Suspension")).unwrap();
+        fs::write(project_dir.join("Suspension").join("upright.SLDPRT"), "upright").unwrap();
+
+        let remote_file = |path: &str, hash: &str| RemoteFile {
+            frid: 1,
+            path: path.to_string(),
+            commitid: 2,
+            filehash: hash.to_string(),
+            changetype: 1,
+            blocksize: 1,
+        };
+        let remote = vec![
+            remote_file("Suspension\\upright.SLDPRT", "upright hash"),
+            remote_file("Suspension\\a-arm.SLDPRT", "a-arm hash"),
+            remote_file("Drivetrain\\diff.SLDPRT", "diff hash"),
+        ];
+        // the diff was downloaded before the project was made sparse, and isn't on disk anymore
+        sync_project(3, remote.clone(), false, &pool).await.unwrap();
+        dal.update_downloaded_file_entry(3, "Drivetrain\\diff.SLDPRT".to_string()).await.unwrap();
+
+        assert!(add_sparse_pattern(3, "Suspension/".to_string(), &pool).await.unwrap());
+        assert!(!add_sparse_pattern(3, "Suspension/".to_string(), &pool).await.unwrap());
+        let res = sync_project(3, remote.clone(), false, &pool).await;
+
+        let mut downloads: Vec<String> = dal.get_downloads(3).await.unwrap().into_iter().map(|file| file.filepath).collect();
+        downloads.sort();
+        assert_eq!(downloads, vec!["Suspension\\a-arm.SLDPRT", "Suspension\\upright.SLDPRT"]);
+        assert!(dal.get_conflicts(3).await.unwrap().iter().all(|file| file.filepath.starts_with("Suspension")));
+        // the diff isn't shown as deleted
+        let uploads: Vec<String> = dal.get_uploads(3).await.unwrap().into_iter().map(|file| file.filepath).collect();
+        assert_eq!(uploads, vec!["Suspension\\upright.SLDPRT"]);
+        assert!(!dal.get_file_info(3, "Drivetrain\\diff.SLDPRT".to_string()).await.unwrap().in_fs);
+
+        // checking out the whole project again
+        assert!(remove_sparse_pattern(3, "Suspension/".to_string(), &pool).await.unwrap());
+        sync_project(3, remote, false, &pool).await.unwrap();
+        assert!(res.unwrap());
+        assert!(dal.get_uploads(3).await.unwrap().iter().any(|file| file.filepath == "Drivetrain\\diff.SLDPRT" && file.change_type == 3));
+    }
+}
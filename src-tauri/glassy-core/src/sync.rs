@@ -0,0 +1,391 @@
+use crate::{
+    file::{translate_filepath, IgnoreRules}, sparse::SparseSpec, types::{FileFingerprint, GlassyError, RemoteFile}, dal::DataAccessLayer
+};
+use merkle_hash::{bytes_to_hex, Algorithm, MerkleTree};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::path::{Path, PathBuf};
+use std::fs::{self, Metadata};
+use std::time::UNIX_EPOCH;
+
+pub async fn hash_dir(pid: i32, dir_path: PathBuf, pool: &Pool<Sqlite>, force_rehash: bool) {
+    log::info!("starting hashing directory");
+    log::info!("directory: {}", dir_path.display());
+    let dal = DataAccessLayer::new(pool);
+
+    if force_rehash {
+        log::info!("full rehash requested, ignoring stored fingerprints");
+        let _ = dal.clear_file_fingerprints(pid).await;
+    }
+    let known = dal.get_local_fingerprints(pid).await.unwrap_or_default();
+    let rules = IgnoreRules::load(pid, &dir_path, pool).await;
+    let spec = SparseSpec::load(pid, pool).await;
+
+    // tracked files outside of the sparse checkout look deleted here, get_uploads leaves them out
+    let _ = dal.reset_fs_state(pid).await;
+
+    let mut paths: Vec<PathBuf> = vec![];
+    walk_dir(&dir_path, &mut paths);
+    log::info!("found {} files", paths.len());
+
+    // files whose fingerprint matches what we stored last time keep their hash
+    let mut unchanged: Vec<(String, String, FileFingerprint)> = vec![];
+    let mut to_hash: Vec<(String, PathBuf, FileFingerprint)> = vec![];
+    // files that are there but can't be read, e.g. while solidworks has them open, keep what we stored
+    let mut unreadable: Vec<String> = vec![];
+    for path in paths {
+        let rel_path = match get_relative_path(&dir_path, &path) {
+            Some(p) => p,
+            None => continue,
+        };
+
+        // ignored files aren't put back in the file table, so they don't show up as uploads
+        if should_skip_path(&rel_path) || !spec.includes(&rel_path) || rules.is_ignored(&rel_path) {
+            continue;
+        }
+
+        let metadata = match fs::metadata(&path) {
+            Ok(m) => m,
+            Err(err) => {
+                log::warn!("couldn't read metadata for {}: {}", path.display(), err);
+                unreadable.push(rel_path);
+                continue;
+            }
+        };
+        if metadata.len() == 0 {
+            //log::trace!("skiping empty file");
+            continue;
+        }
+
+        let fingerprint = get_fingerprint(&metadata);
+        match known.get(&rel_path) {
+            Some((hash, stored)) if *stored == fingerprint => {
+                unchanged.push((rel_path, hash.clone(), fingerprint))
+            }
+            _ => to_hash.push((rel_path, path, fingerprint)),
+        }
+    }
+    log::info!("{} files unchanged, hashing {} files", unchanged.len(), to_hash.len());
+
+    let hashed: Vec<(String, Option<String>, FileFingerprint)> = to_hash
+        .into_par_iter()
+        .map(|(rel_path, path, fingerprint)| (rel_path, hash_file(&path), fingerprint))
+        .collect();
+
+    // write to sqlite
+    for (rel_path, hash, fingerprint) in unchanged {
+        let _ = dal.insert_local_file(rel_path, pid, hash, fingerprint.size as u64).await;
+    }
+    for (rel_path, hash, fingerprint) in hashed {
+        let hash = match hash {
+            Some(hash) => hash,
+            None => {
+                unreadable.push(rel_path);
+                continue;
+            }
+        };
+        if dal.insert_local_file(rel_path.clone(), pid, hash, fingerprint.size as u64).await.is_ok() {
+            let _ = dal.set_file_fingerprint(rel_path, pid, &fingerprint).await;
+        }
+    }
+    // so they aren't shown as deleted
+    for rel_path in unreadable {
+        let _ = dal.mark_present(pid, rel_path).await;
+    }
+    log::info!("files parsed");
+    let _ = dal.update_change_types(pid).await;
+
+    log::info!("hashing directory complete");
+}
+
+/// re-hashes only the given files or folders instead of the whole project directory
+pub async fn hash_paths(pid: i32, dir_path: &Path, paths: Vec<PathBuf>, pool: &Pool<Sqlite>) {
+    let dal = DataAccessLayer::new(pool);
+    let rules = IgnoreRules::load(pid, dir_path, pool).await;
+    let spec = SparseSpec::load(pid, pool).await;
+
+    for path in paths {
+        let rel_path = match get_relative_path(dir_path, &path) {
+            Some(p) => p,
+            None => continue,
+        };
+        if should_skip_path(&rel_path) {
+            continue;
+        }
+
+        if path.is_dir() {
+            let mut files: Vec<PathBuf> = vec![];
+            walk_dir(&path, &mut files);
+            for file in files {
+                hash_local_file(pid, dir_path, &file, &rules, &spec, &dal).await;
+            }
+        } else if !spec.includes(&rel_path) {
+            continue;
+        } else if !hash_local_file(pid, dir_path, &path, &rules, &spec, &dal).await {
+            // path was deleted or renamed away; if it was a folder this covers its children too
+            let _ = dal.mark_missing(pid, rel_path).await;
+        }
+    }
+    let _ = dal.update_change_types(pid).await;
+}
+
+/// hashes a single file and saves it to the file table; returns false if the file isn't there to hash.
+/// a file that is there but can't be read keeps what was stored for it
+async fn hash_local_file(pid: i32, dir_path: &Path, path: &Path, rules: &IgnoreRules, spec: &SparseSpec, dal: &DataAccessLayer<'_>) -> bool {
+    let rel_path = match get_relative_path(dir_path, path) {
+        Some(p) => p,
+        None => return false,
+    };
+    if should_skip_path(&rel_path) || !spec.includes(&rel_path) {
+        return true;
+    }
+    if rules.is_ignored(&rel_path) {
+        // it may have been added before it was ignored
+        let _ = dal.mark_missing(pid, rel_path).await;
+        return true;
+    }
+    let metadata = match fs::metadata(path) {
+        Ok(m) => m,
+        Err(_) => return path.symlink_metadata().is_ok(),
+    };
+    if metadata.len() == 0 {
+        return false;
+    }
+
+    let fingerprint = get_fingerprint(&metadata);
+    let hash = match hash_file(path) {
+        Some(h) => h,
+        // still there, but can't be read right now; keep what we stored
+        None => return true,
+    };
+    if dal.insert_local_file(rel_path.clone(), pid, hash, fingerprint.size as u64).await.is_ok() {
+        let _ = dal.set_file_fingerprint(rel_path, pid, &fingerprint).await;
+    }
+    true
+}
+
+/// files that are never tracked: temp solidworks files and glassy's own cache, trash and staging folders
+pub fn should_skip_path(rel_path: &str) -> bool {
+    rel_path.contains("~$")
+        || rel_path.split('\\').any(|component| {
+            component == ".glassycache"
+                || component == ".glassytrash"
+                || component == ".glassystaging"
+                || component == ".glassybackup"
+        })
+}
+
+/// recursively collects every file under dir. symlinked folders aren't followed
+pub(crate) fn walk_dir(dir: &Path, output: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            log::warn!("couldn't read directory {}: {}", dir.display(), err);
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let file_type = match entry.file_type() {
+            Ok(t) => t,
+            Err(err) => {
+                log::warn!("couldn't read file type for {}: {}", path.display(), err);
+                continue;
+            }
+        };
+        if file_type.is_dir() {
+            walk_dir(&path, output);
+        } else if file_type.is_symlink() && path.is_dir() {
+            // a link back up the tree would recurse forever
+            log::warn!("skipping symlinked directory {}", path.display());
+        } else {
+            output.push(path);
+        }
+    }
+}
+
+/// gets the path of a file relative to the project directory, stored as a 'windows' path
+pub fn get_relative_path(project_dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(project_dir).ok()?.display().to_string();
+    if relative.is_empty() {
+        return None;
+    }
+    #[cfg(target_os = "windows")]
+    {
+        Some(relative)
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Some(translate_filepath(&relative, false))
+    }
+}
+
+pub fn get_fingerprint(metadata: &Metadata) -> FileFingerprint {
+    let mtime = match metadata.modified().map(|time| time.duration_since(UNIX_EPOCH)) {
+        Ok(Ok(duration)) => duration.as_nanos() as i64,
+        _ => 0,
+    };
+    let file_id;
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::fs::MetadataExt;
+        file_id = metadata.creation_time() as i64;
+    }
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::fs::MetadataExt;
+        file_id = metadata.ino() as i64;
+    }
+    FileFingerprint {
+        size: metadata.len() as i64,
+        mtime,
+        file_id,
+    }
+}
+
+pub fn hash_file(path: &Path) -> Option<String> {
+    match MerkleTree::builder(path.display().to_string())
+        .algorithm(Algorithm::Blake3)
+        .hash_names(false)
+        .build() {
+            Ok(tree) => Some(bytes_to_hex(tree.root.item.hash)),
+            Err(err) => {
+                log::warn!("couldn't hash {}: {}", path.display(), err);
+                None
+            }
+    }
+}
+
+/// hashes the project's local files and records the remote state of the project
+pub async fn sync_project(pid: i32, remote: Vec<RemoteFile>, force_rehash: bool, pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    log::info!("syncing changes for project {}", pid);
+
+    let dal = DataAccessLayer::new(pool);
+    let project_dir = dal.get_project_dir(pid).await?;
+    if project_dir.is_empty() {
+        return Err(GlassyError::Setup(format!("project {} has no local folder", pid)));
+    }
+
+    // create folder if it does not exist
+    fs::create_dir_all(&project_dir).map_err(|err| GlassyError::io(&project_dir, err))?;
+
+    // hash local files
+    hash_dir(pid, project_dir.clone().into(), pool, force_rehash).await;
+
+    log::info!("updating db with remote files...");
+    // update table with remote files
+    for file in remote {
+        dal.insert_remote_file(file.path, pid, file.commitid, file.filehash, file.changetype, file.blocksize).await?;
+    }
+    log::info!("remote files updated");
+    // TODO update last_synced in project table
+    Ok(true)
+}
+
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone)]
+pub struct FileChange {
+    pub filepath: String,
+    pub size: u32,
+    pub change_type: u32,
+    pub hash: String,
+    pub commit_id: i32,
+    #[sqlx(default)]
+    pub old_filepath: Option<String>, // only set for renames/moves
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub references: Vec<String>, // uploads this file needs, only set by attach_references in references.rs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::setup_project;
+    use sqlx::SqlitePool;
+
+    #[sqlx::test]
+    async fn test_sync_project(pool: SqlitePool) {
+        let project = setup_project("sync", &pool).await;
+        let project_dir = &project.project_dir;
+        let dal = DataAccessLayer::new(&pool);
+        fs::create_dir_all(project_dir.join("Parts")).unwrap();
+        fs::write(project_dir.join("Parts").join("bracket.SLDPRT"), "bracket").unwrap();
+
+        let remote = vec![RemoteFile {
+            frid: 1,
+            path: "top.SLDASM".to_string(),
+            commitid: 2,
+            filehash: "remote hash".to_string(),
+            changetype: 1,
+            blocksize: 1,
+        }];
+        let res = sync_project(3, remote, false, &pool).await;
+        assert!(res.unwrap());
+
+        let uploads = dal.get_uploads(3).await.unwrap();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].filepath, "Parts\\bracket.SLDPRT");
+        assert_eq!(uploads[0].change_type, 1);
+
+        let downloads = dal.get_downloads(3).await.unwrap();
+        assert_eq!(downloads.len(), 1);
+        assert_eq!(downloads[0].filepath, "top.SLDASM");
+        assert_eq!(downloads[0].commit_id, 2);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[sqlx::test]
+    async fn test_unreadable_files_are_not_deleted(pool: SqlitePool) {
+        use std::os::unix::fs::PermissionsExt;
+        let project = setup_project("sync_unreadable", &pool).await;
+        let project_dir = &project.project_dir;
+        let dal = DataAccessLayer::new(&pool);
+        let locked = project_dir.join("locked.SLDPRT");
+        let broken = project_dir.join("broken.SLDPRT");
+        fs::write(&locked, "locked").unwrap();
+        fs::write(&broken, "broken").unwrap();
+
+        let remote_file = |path: &str, file: &Path| RemoteFile {
+            frid: 1,
+            path: path.to_string(),
+            commitid: 2,
+            filehash: hash_file(file).unwrap(),
+            changetype: 1,
+            blocksize: 1,
+        };
+        let remote = vec![remote_file("locked.SLDPRT", &locked), remote_file("broken.SLDPRT", &broken)];
+        sync_project(3, remote.clone(), false, &pool).await.unwrap();
+        for file in &remote {
+            dal.update_downloaded_file_entry(3, file.path.clone()).await.unwrap();
+        }
+
+        // an edited file that can't be opened, and one whose metadata can't be read
+        fs::write(&locked, "locked, edited").unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        fs::remove_file(&broken).unwrap();
+        std::os::unix::fs::symlink(project_dir.join("missing"), &broken).unwrap();
+        sync_project(3, remote.clone(), false, &pool).await.unwrap();
+        hash_paths(3, project_dir, vec![locked.clone(), broken.clone()], &pool).await;
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o644)).unwrap();
+
+        assert!(dal.get_uploads(3).await.unwrap().iter().all(|file| file.change_type != 3));
+        let info = dal.get_file_info(3, "broken.SLDPRT".to_string()).await.unwrap();
+        assert!(info.in_fs);
+        assert_eq!(info.hash, remote[1].filehash);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[sqlx::test]
+    async fn test_walk_dir_skips_symlinked_dirs(pool: SqlitePool) {
+        let project = setup_project("walk_symlink", &pool).await;
+        let project_dir = &project.project_dir;
+        fs::create_dir_all(project_dir.join("Parts")).unwrap();
+        fs::write(project_dir.join("Parts").join("bracket.SLDPRT"), "bracket").unwrap();
+        // links back up to the project folder
+        std::os::unix::fs::symlink(project_dir, project_dir.join("Parts").join("loop")).unwrap();
+
+        let mut paths: Vec<PathBuf> = vec![];
+        walk_dir(project_dir, &mut paths);
+        assert_eq!(paths, vec![project_dir.join("Parts").join("bracket.SLDPRT")]);
+    }
+}
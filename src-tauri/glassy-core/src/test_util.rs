@@ -0,0 +1,29 @@
+use crate::dal::DataAccessLayer;
+use sqlx::{Pool, Sqlite};
+use std::fs;
+use std::path::PathBuf;
+
+// shared setup for tests that need a project folder on disk
+
+/// project 3 on a test server, with its folder in a temp dir named after the test.
+/// the temp dir is removed when this is dropped
+pub struct TestProject {
+    local_dir: PathBuf,
+    pub project_dir: PathBuf,
+}
+
+impl Drop for TestProject {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.local_dir);
+    }
+}
+
+pub async fn setup_project(name: &str, pool: &Pool<Sqlite>) -> TestProject {
+    let local_dir = std::env::temp_dir().join(format!("glassy-{}-test-{}", name, std::process::id()));
+    let dal = DataAccessLayer::new(pool);
+    let _ = dal.add_server("url".to_string(), "key".to_string(), local_dir.display().to_string(), "test server".to_string()).await;
+    let _ = dal.add_project(3, "project".to_string(), "team".to_string(), 1).await;
+    let project_dir = PathBuf::from(dal.get_project_dir(3).await.unwrap());
+    fs::create_dir_all(&project_dir).unwrap();
+    TestProject { local_dir, project_dir }
+}
@@ -0,0 +1,563 @@
+use crate::backup::{backup_local_edits, prune_backups};
+use crate::config::{get_cache_setting, Config};
+use crate::dal::DataAccessLayer;
+use crate::download::{
+    assemble_file, compare_directory_deep, download_with_client, get_directories, load_manifest, record_blocks,
+    save_manifest, unique_blocks, verify_cache,
+};
+use crate::file::{native_path, sep};
+use crate::job::JobControl;
+use crate::journal::{apply_journal, commit_journal, discard_journal, get_journal_dir};
+use crate::lock::apply_lock_permissions;
+use crate::network::GlassyClient;
+use crate::open_files::open_files_in;
+use crate::progress::ProgressReporter;
+use crate::sync::{hash_dir, hash_file};
+use crate::trash::{prune_trash, trash_file};
+use crate::types::{ChangeType, DownloadRequestMessage, FileChunk, GlassyError, JournalEntry};
+use crate::util::{delete_cache, evict_cache, get_block_dir, get_cache_dir, get_staging_dir};
+use futures::{stream, StreamExt};
+use log::{info, warn};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use std::fs::{self, remove_dir};
+use std::path::{Path, PathBuf};
+
+// downloads, resets, single file downloads and conflict resolution all put revisions from the server
+// into the project the same way: fetch the blocks, stage the files, then swap them in with a journal
+
+const CONCURRENT_SERVER_REQUESTS: usize = 6;
+const CONCURRENT_AWS_REQUESTS: usize = 6;
+
+/// why files are being transferred. decides what the file table is updated to,
+/// and is recorded with the backups and trash entries the transfer makes
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TransferReason {
+    Download, // files go to the revision that was synced from the server
+    Reset,    // files go back to the revision local edits were made on top of
+}
+
+impl TransferReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferReason::Download => "download",
+            TransferReason::Reset => "reset",
+        }
+    }
+}
+
+/// what a transfer does to a project
+pub struct TransferPlan {
+    pub pid: i32,
+    pub reason: TransferReason,
+    pub fetch: Vec<DownloadRequestMessage>,  // revisions to put in the project, restored from the cache if they're in it
+    pub delete: Vec<DownloadRequestMessage>, // files to take out of the project, they go to the trash
+}
+
+impl TransferPlan {
+    /// sorts `files` into fetches and deletes by their download flag
+    pub fn new(pid: i32, reason: TransferReason, files: Vec<DownloadRequestMessage>) -> TransferPlan {
+        let (fetch, delete) = files.into_iter().partition(|file| file.download);
+        TransferPlan { pid, reason, fetch, delete }
+    }
+}
+
+/// carries out a plan. returns Ok(false) if it failed before the project was touched,
+/// or if it was interrupted part way and will be finished on the next start, see journal.rs.
+/// `job` can pause or cancel it until the project starts changing
+pub async fn run_transfer(
+    plan: TransferPlan,
+    config: &Config,
+    pool: &Pool<Sqlite>,
+    progress: &dyn ProgressReporter,
+    job: &JobControl,
+) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let pid = plan.pid;
+    let reason = plan.reason.as_str();
+
+    let project_dir = dal.get_project_dir(pid).await?;
+    let cache_dir = get_cache_dir(pool).await?;
+    let staging_dir = get_staging_dir(pool).await?;
+    let block_dir = get_block_dir(&cache_dir);
+    if project_dir.is_empty() || cache_dir.is_empty() || config.server_url.is_empty() || staging_dir.is_empty() {
+        log::error!("{}: project or cache dir is invalid", reason);
+        return Err(GlassyError::Setup(format!("project {} has no folder to {} into", pid, reason)));
+    }
+
+    let mut to_copy = plan.fetch;
+    let mut to_delete = plan.delete;
+    let mut to_move = match plan.reason {
+        TransferReason::Download => find_moves(pid, &mut to_copy, &mut to_delete, pool).await,
+        TransferReason::Reset => Vec::new(),
+    };
+
+    // files that are open in cad are left alone, the next sync lists them again. see open_files.rs
+    let mut targets: Vec<String> = to_copy.iter().chain(to_delete.iter()).map(|file| file.rel_path.clone()).collect();
+    targets.extend(to_move.iter().flat_map(|(from, to)| [from.rel_path.clone(), to.rel_path.clone()]));
+    let open = open_files_in(&project_dir, &targets);
+    if !open.is_empty() {
+        warn!("{} files are open in another program, leaving them out of the {}", open.len(), reason);
+        to_copy.retain(|file| !open.contains(&file.rel_path));
+        to_delete.retain(|file| !open.contains(&file.rel_path));
+        to_move.retain(|(from, to)| !open.contains(&from.rel_path) && !open.contains(&to.rel_path));
+        progress.files_skipped(&open);
+    }
+
+    let client = GlassyClient::new(config);
+    let manifests = match fetch_blocks(pid, &mut to_copy, &client, &block_dir, pool, progress, job).await? {
+        Some(manifests) => manifests,
+        None => return Ok(false),
+    };
+
+    // assemble every file outside of the project first, so a failure here leaves the project untouched
+    let jid = dal.create_download_journal(pid, reason).await?;
+    let journal_dir = get_journal_dir(&staging_dir, jid);
+    let backup_dir = journal_dir.clone() + &(sep().to_string()) + "backup";
+    let _ = fs::create_dir_all(&backup_dir);
+    let mut entries: Vec<JournalEntry> = Vec::new();
+    for file in to_copy.iter() {
+        let idx = entries.len() as i64;
+        let staged_str = journal_dir.clone() + &(sep().to_string()) + &idx.to_string();
+        if let Err(err) = job.checkpoint().await {
+            log::info!("{} of project {} cancelled", reason, pid);
+            discard_journal(jid, pool).await;
+            return Err(err);
+        }
+        if !stage_file(&block_dir, &manifests[&file.hash], &file.hash, &staged_str) {
+            log::error!("couldn't stage {} for {}", file.rel_path, reason);
+            discard_journal(jid, pool).await;
+            return Ok(false);
+        }
+        progress.file_done(idx as u32 + 1);
+        entries.push(JournalEntry {
+            idx,
+            rel_path: file.rel_path.clone(),
+            target: project_dir.clone() + &(sep().to_string()) + file.rel_path.as_str(),
+            source: staged_str,
+            backup: backup_dir.clone() + &(sep().to_string()) + &idx.to_string(),
+            download: true,
+            done: false,
+        });
+    }
+    for (from, to) in to_move.iter() {
+        let idx = entries.len() as i64;
+        entries.push(JournalEntry {
+            idx,
+            rel_path: to.rel_path.clone(),
+            target: project_dir.clone() + &(sep().to_string()) + to.rel_path.as_str(),
+            source: project_dir.clone() + &(sep().to_string()) + from.rel_path.as_str(),
+            backup: backup_dir.clone() + &(sep().to_string()) + &idx.to_string(),
+            download: true,
+            done: false,
+        });
+        // the old path only needs its file table entry removed
+        entries.push(JournalEntry {
+            idx: idx + 1,
+            rel_path: from.rel_path.clone(),
+            target: "".to_string(),
+            source: "".to_string(),
+            backup: "".to_string(),
+            download: false,
+            done: false,
+        });
+    }
+    for file in to_delete.iter() {
+        let idx = entries.len() as i64;
+        entries.push(JournalEntry {
+            idx,
+            rel_path: file.rel_path.clone(),
+            target: project_dir.clone() + &(sep().to_string()) + file.rel_path.as_str(),
+            source: "".to_string(),
+            backup: backup_dir.clone() + &(sep().to_string()) + &idx.to_string(),
+            download: false,
+            done: false,
+        });
+    }
+
+    // last chance to stop, the project is about to change
+    if let Err(err) = job.checkpoint().await {
+        log::info!("{} of project {} cancelled", reason, pid);
+        discard_journal(jid, pool).await;
+        return Err(err);
+    }
+
+    // keep a copy of any local edits this transfer is about to overwrite or delete
+    let mut touched: Vec<String> = to_copy.iter().chain(to_delete.iter()).map(|file| file.rel_path.clone()).collect();
+    touched.extend(to_move.iter().flat_map(|(from, to)| [from.rel_path.clone(), to.rel_path.clone()]));
+    if !backup_local_edits(pid, &touched, reason, pool).await {
+        log::error!("couldn't back up local edits, cancelling {}", reason);
+        discard_journal(jid, pool).await;
+        return Ok(false);
+    }
+
+    // from here on an interrupted transfer is finished or undone on the next start, see journal.rs
+    if dal.write_journal_entries(jid, &entries).await.is_err() {
+        discard_journal(jid, pool).await;
+        return Ok(false);
+    }
+    if !apply_journal(jid, pool).await {
+        discard_journal(jid, pool).await;
+        return Ok(false);
+    }
+    // deleted files are sitting in the journal's backup folder, keep them in the trash for a while
+    for entry in entries.iter().filter(|entry| entry.source.is_empty() && !entry.backup.is_empty()) {
+        if !Path::new(&native_path(&entry.backup)).exists() {
+            continue;
+        }
+        let hash = to_delete.iter().find(|file| file.rel_path == entry.rel_path).map(|file| file.hash.clone()).unwrap_or_default();
+        if trash_file(pid, &entry.rel_path, &hash, &entry.backup, reason, pool).await.is_err() {
+            log::warn!("couldn't move {} to the trash, it will be deleted", entry.rel_path);
+        }
+    }
+    if !commit_journal(jid, pid, pool).await {
+        log::error!("couldn't update the file table, {} will be finished on next start", reason);
+        return Ok(false);
+    }
+    prune_backups(pool).await;
+    prune_trash(pool).await;
+    apply_lock_permissions(pid, &config.user_id, pool).await;
+
+    // folders of deleted and moved files may be empty now
+    let mut emptied = to_delete.clone();
+    emptied.extend(to_move.iter().map(|(from, _)| from.clone()));
+    remove_empty_dirs(&project_dir, &emptied);
+
+    // reset files were restored to a revision the file table doesn't track, rescan them
+    if plan.reason == TransferReason::Reset {
+        hash_dir(pid, project_dir.into(), pool, false).await;
+    }
+
+    clean_cache(pool).await?;
+    Ok(true)
+}
+
+/// a revision of one file to put somewhere, see transfer_revision
+pub struct RevisionRequest {
+    pub pid: i32,
+    pub path: String,
+    pub commit_id: i64,
+    pub download_path: String, // can be outside of the project
+}
+
+/// puts the requested revision at its download path. nothing in the project or file table changes
+pub async fn transfer_revision(
+    request: RevisionRequest,
+    config: &Config,
+    pool: &Pool<Sqlite>,
+    progress: &dyn ProgressReporter,
+    job: &JobControl,
+) -> Result<bool, GlassyError> {
+    let cache_dir = get_cache_dir(pool).await?;
+    let block_dir = get_block_dir(&cache_dir);
+
+    // the hash isn't known until the server says which revision is at commit_id
+    let mut files = vec![DownloadRequestMessage {
+        commit_id: request.commit_id,
+        rel_path: request.path,
+        hash: "".to_string(),
+        download: true,
+    }];
+    let client = GlassyClient::new(config);
+    let manifests = match fetch_blocks(request.pid, &mut files, &client, &block_dir, pool, progress, job).await? {
+        Some(manifests) => manifests,
+        None => return Ok(false),
+    };
+    let out = assemble_file(&block_dir, &manifests[&files[0].hash], &request.download_path);
+
+    clean_cache(pool).await?;
+    Ok(out)
+}
+
+/// a remote rename shows up as a delete plus a download of the same content,
+/// so move the local file instead of downloading it again. returns (from, to) pairs
+async fn find_moves(
+    pid: i32,
+    to_copy: &mut Vec<DownloadRequestMessage>,
+    to_delete: &mut Vec<DownloadRequestMessage>,
+    pool: &Pool<Sqlite>,
+) -> Vec<(DownloadRequestMessage, DownloadRequestMessage)> {
+    let mut to_move: Vec<(DownloadRequestMessage, DownloadRequestMessage)> = Vec::new();
+    if to_delete.is_empty() {
+        return to_move;
+    }
+    let dal = DataAccessLayer::new(pool);
+    let mut deletes_by_hash: HashMap<String, Vec<DownloadRequestMessage>> = HashMap::new();
+    for file in to_delete.iter() {
+        match dal.get_file_info(pid, file.rel_path.clone()).await {
+            // only move files that haven't been edited locally
+            Ok(info) if info.in_fs && info.change == ChangeType::NoChange => {
+                deletes_by_hash.entry(info.hash).or_default().push(file.clone());
+            }
+            _ => {}
+        }
+    }
+    to_copy.retain(|file| match deletes_by_hash.get_mut(&file.hash).and_then(|from| from.pop()) {
+        Some(from) => {
+            to_move.push((from, file.clone()));
+            false
+        }
+        None => true,
+    });
+    to_delete.retain(|file| !to_move.iter().any(|(from, _)| from.rel_path == file.rel_path));
+    log::info!("moving {} files instead of downloading them", to_move.len());
+    to_move
+}
+
+/// gets every block of `files` into the block store, downloading the ones that aren't cached.
+/// files without a hash are looked up on the server and get the hash filled in.
+/// returns each file's manifest by hash, or None if anything couldn't be fetched.
+/// chunks that were partly downloaded when the job is cancelled are resumed by the next download
+async fn fetch_blocks(
+    pid: i32,
+    files: &mut [DownloadRequestMessage],
+    client: &GlassyClient,
+    block_dir: &String,
+    pool: &Pool<Sqlite>,
+    progress: &dyn ProgressReporter,
+    job: &JobControl,
+) -> Result<Option<HashMap<String, Vec<FileChunk>>>, GlassyError> {
+    let mut manifests: HashMap<String, Vec<FileChunk>> = HashMap::new();
+    let mut to_request: Vec<usize> = Vec::new();
+    for (idx, file) in files.iter().enumerate() {
+        if !file.hash.is_empty() && !manifests.contains_key(&file.hash) {
+            let manifest = load_manifest(pool, &file.hash).await;
+            if verify_cache(block_dir, &manifest) {
+                manifests.insert(file.hash.clone(), manifest);
+                continue;
+            }
+        }
+        if file.hash.is_empty() || !manifests.contains_key(&file.hash) {
+            to_request.push(idx);
+        }
+    }
+
+    // request S3 presigned urls
+    let links: Vec<_> = stream::iter(to_request)
+        .map(|idx| {
+            let file = &files[idx];
+            async move {
+                if let Err(err) = job.checkpoint().await {
+                    return (idx, Err(err));
+                }
+                (idx, client.request_download(pid.into(), &file.rel_path, file.commit_id).await)
+            }
+        })
+        .buffer_unordered(CONCURRENT_SERVER_REQUESTS)
+        .collect()
+        .await;
+    job.checkpoint().await?;
+    let mut chunks: Vec<FileChunk> = Vec::new();
+    let mut failed = 0;
+    for (idx, res) in links {
+        match res {
+            Ok(info) => {
+                let _ = save_manifest(pool, &info).await;
+                files[idx].hash = info.file_hash.clone();
+                chunks.extend(info.file_chunks);
+            }
+            Err(err) => {
+                failed += 1;
+                log::error!("couldn't get download links for {}: {}", files[idx].rel_path, err);
+            }
+        }
+    }
+    if failed > 0 {
+        log::error!("couldn't get download links for {} files", failed);
+        return Ok(None);
+    }
+
+    // download chunks
+    let chunks = unique_blocks(chunks);
+    let num_chunks = chunks.len();
+    log::info!("s3 urls obtained, downloading {} chunks...", num_chunks);
+    let failed = stream::iter(chunks)
+        .map(|chunk| async move {
+            if job.checkpoint().await.is_err() {
+                return false;
+            }
+            match download_with_client(block_dir, chunk, client.http()).await {
+                Ok(true) => {
+                    progress.chunk_downloaded(num_chunks);
+                    true
+                }
+                Ok(false) => {
+                    log::error!("error downloading chunk: chunk could not be verified");
+                    false
+                }
+                Err(err) => {
+                    log::error!("error downloading chunk {}", err);
+                    false
+                }
+            }
+        })
+        .buffer_unordered(CONCURRENT_AWS_REQUESTS)
+        .filter(|ok| std::future::ready(!ok))
+        .count()
+        .await;
+    job.checkpoint().await?;
+    if failed > 0 {
+        log::error!("couldn't download {} of {} chunks", failed, num_chunks);
+        return Ok(None);
+    }
+
+    // verify the chunks exist
+    for file in files.iter() {
+        if manifests.contains_key(&file.hash) {
+            continue;
+        }
+        let manifest = load_manifest(pool, &file.hash).await;
+        if !verify_cache(block_dir, &manifest) {
+            log::error!("verifying cache failed: {}", file.hash);
+            return Ok(None);
+        }
+        manifests.insert(file.hash.clone(), manifest);
+    }
+    record_blocks(pool, block_dir, manifests.values().flatten()).await;
+
+    progress.chunks_cached();
+    Ok(Some(manifests))
+}
+
+/// assembles a file at `staged_path` and checks it came out as `hash`
+fn stage_file(block_dir: &String, manifest: &[FileChunk], hash: &String, staged_path: &String) -> bool {
+    if !assemble_file(block_dir, manifest, staged_path) {
+        return false;
+    }
+    match hash_file(Path::new(&native_path(staged_path))) {
+        Some(staged_hash) if staged_hash == *hash => true,
+        _ => {
+            log::error!("assembled file {} does not match hash {}", staged_path, hash);
+            false
+        }
+    }
+}
+
+/// removes the folders `emptied` were in if nothing is left in them, deepest first
+fn remove_empty_dirs(project_dir: &str, emptied: &Vec<DownloadRequestMessage>) {
+    let mut directories = Vec::from_iter(get_directories(emptied));
+    directories.sort_by(|a, b| compare_directory_deep(a, b));
+    info!("deleting directories");
+
+    for folder in directories {
+        info!("{}", folder);
+        let proj_dir = project_dir.to_string() + &(sep().to_string()) + &folder;
+        let path = PathBuf::from(native_path(&proj_dir));
+        // if file's folder is empty, delete it (ie use remove_dir() which will delete only if it is empty)
+        match remove_dir(path) {
+            Ok(()) => info!("successful delete"),
+            Err(_e) => warn!("no delete"),
+        };
+    }
+}
+
+/// if configured, delete cache, otherwise trim it down to its size limit
+async fn clean_cache(pool: &Pool<Sqlite>) -> Result<(), GlassyError> {
+    if get_cache_setting(pool).await? {
+        let _ = delete_cache(pool).await;
+    } else {
+        let _ = evict_cache(pool).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Token;
+    use crate::test_util::setup_project;
+    use crate::progress::NoProgress;
+    use sqlx::SqlitePool;
+
+    #[test]
+    fn test_transfer_plan() {
+        let file = |rel_path: &str, download: bool| DownloadRequestMessage {
+            commit_id: 2,
+            rel_path: rel_path.to_string(),
+            hash: "hash".to_string(),
+            download,
+        };
+        let plan = TransferPlan::new(3, TransferReason::Download, vec![file("a", true), file("b", false), file("c", true)]);
+        let fetch: Vec<&str> = plan.fetch.iter().map(|f| f.rel_path.as_str()).collect();
+        assert_eq!(fetch, vec!["a", "c"]);
+        assert_eq!(plan.delete.len(), 1);
+        assert_eq!(plan.delete[0].rel_path, "b");
+    }
+
+    #[sqlx::test]
+    async fn test_reset_from_cache(pool: SqlitePool) {
+        let project = setup_project("transfer", &pool).await;
+        let project_dir = &project.project_dir;
+        let dal = DataAccessLayer::new(&pool);
+
+        // the synced revision of part.SLDPRT is in the cache
+        let data = b"synced part";
+        let file_hash = blake3::hash(data).to_hex().to_string();
+        let block_dir = get_block_dir(&get_cache_dir(&pool).await.unwrap());
+        fs::create_dir_all(&block_dir).unwrap();
+        fs::write(PathBuf::from(&block_dir).join(&file_hash), data).unwrap();
+        let chunk = FileChunk {
+            s3_url: String::new(),
+            block_hash: file_hash.clone(),
+            chunk_index: 0,
+            file_hash: file_hash.clone(),
+            offset: None,
+            size: None,
+        };
+        let _ = dal.save_cache_manifest("url".to_string(), file_hash.clone(), &vec![chunk]).await;
+        let _ = dal.insert_remote_file("part.SLDPRT".to_string(), 3, 2, file_hash.clone(), ChangeType::Create as i32, 11).await;
+        let _ = dal.insert_local_file("part.SLDPRT".to_string(), 3, file_hash.clone(), 11).await;
+        let _ = dal.update_downloaded_file_entry(3, "part.SLDPRT".to_string()).await;
+
+        // then it was edited, and a new file was added
+        fs::write(project_dir.join("part.SLDPRT"), "edited part").unwrap();
+        fs::write(project_dir.join("new.SLDPRT"), "new part").unwrap();
+        hash_dir(3, project_dir.clone(), &pool, false).await;
+        assert_eq!(dal.get_uploads(3).await.unwrap().len(), 2);
+
+        // nothing needs to come from the server, so the config's server is never contacted
+        let config = Config { server_url: "http://localhost:0".to_string(), user_id: "user".to_string(), token: Token::default() };
+        let plan = TransferPlan {
+            pid: 3,
+            reason: TransferReason::Reset,
+            fetch: vec![DownloadRequestMessage { commit_id: 2, rel_path: "part.SLDPRT".to_string(), hash: file_hash, download: true }],
+            delete: vec![DownloadRequestMessage { commit_id: -1, rel_path: "new.SLDPRT".to_string(), hash: "".to_string(), download: false }],
+        };
+        let res = run_transfer(plan, &config, &pool, &NoProgress, &JobControl::default()).await;
+        let part = fs::read(project_dir.join("part.SLDPRT"));
+        let new_exists = project_dir.join("new.SLDPRT").exists();
+        let uploads = dal.get_uploads(3).await.unwrap();
+
+        assert!(res.unwrap());
+        assert_eq!(part.unwrap(), data);
+        assert!(!new_exists);
+        assert_eq!(uploads.len(), 0);
+    }
+
+    #[sqlx::test]
+    async fn test_open_files_are_skipped(pool: SqlitePool) {
+        let project = setup_project("open-transfer", &pool).await;
+        let project_dir = &project.project_dir;
+        let dal = DataAccessLayer::new(&pool);
+
+        // two new files are reset, but one of them is open in solidworks
+        fs::write(project_dir.join("open.SLDPRT"), "open part").unwrap();
+        fs::write(project_dir.join("~$open.SLDPRT"), "").unwrap();
+        fs::write(project_dir.join("closed.SLDPRT"), "closed part").unwrap();
+        hash_dir(3, project_dir.clone(), &pool, false).await;
+
+        let config = Config { server_url: "http://localhost:0".to_string(), user_id: "user".to_string(), token: Token::default() };
+        let delete = |rel_path: &str| DownloadRequestMessage { commit_id: -1, rel_path: rel_path.to_string(), hash: "".to_string(), download: false };
+        let plan = TransferPlan { pid: 3, reason: TransferReason::Reset, fetch: vec![], delete: vec![delete("open.SLDPRT"), delete("closed.SLDPRT")] };
+        let res = run_transfer(plan, &config, &pool, &NoProgress, &JobControl::default()).await;
+        let open_exists = project_dir.join("open.SLDPRT").exists();
+        let closed_exists = project_dir.join("closed.SLDPRT").exists();
+        let uploads = dal.get_uploads(3).await.unwrap();
+
+        assert!(res.unwrap());
+        assert!(open_exists);
+        assert!(!closed_exists);
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].filepath, "open.SLDPRT");
+    }
+}
@@ -0,0 +1,149 @@
+use crate::dal::DataAccessLayer;
+use crate::file::{native_path, sep};
+use crate::sync::hash_paths;
+use crate::types::GlassyError;
+use crate::util::{get_trash_dir, set_readonly};
+use sqlx::{Pool, Sqlite};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// files that glassy deletes are moved here instead, and recorded in the trash table.
+// each one is kept at .glassytrash/<tid>/<file name> until the retention period runs out or it is purged
+
+/// moves the file at source into the trash and records where it came from.
+/// source is usually the file in the project, but can be anywhere on the same drive
+pub async fn trash_file(pid: i32, rel_path: &str, hash: &str, source: &str, reason: &str, pool: &Pool<Sqlite>) -> Result<i64, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let url = dal.get_active_server().await?;
+    let trash_dir = get_trash_dir(pool).await?;
+    if url.is_empty() || trash_dir.is_empty() {
+        log::error!("couldn't get trash folder");
+        return Err(GlassyError::Setup("no server is set up, so there is no trash folder".to_string()));
+    }
+
+    let tid = dal.add_trash_entry(url, pid, rel_path.to_string(), hash.to_string(), reason).await?;
+    let source = native_path(source);
+    let target = native_path(&get_trash_path(&trash_dir, tid, rel_path));
+    if let Some(prefix) = Path::new(&target).parent() {
+        let _ = fs::create_dir_all(prefix);
+    }
+    if let Err(err) = fs::rename(&source, &target) {
+        log::error!("couldn't move {} to the trash: {}", source, err);
+        let _ = dal.delete_trash_entry(tid).await;
+        return Err(GlassyError::io(source, err));
+    }
+    // read-only files can't be purged on windows, see lock.rs
+    set_readonly(Path::new(&target), false);
+    log::info!("moved {} in project {} to the trash", rel_path, pid);
+    Ok(tid)
+}
+
+/// moves a trashed file back into its project. won't overwrite a file that is already there
+pub async fn recover_file(tid: i64, pool: &Pool<Sqlite>) -> bool {
+    let dal = DataAccessLayer::new(pool);
+    let entry = match dal.get_trash_entry(tid).await {
+        Ok(entry) => entry,
+        Err(_) => return false,
+    };
+    let project_dir = dal.get_project_dir(entry.pid).await.unwrap_or_default();
+    let trash_dir = get_trash_dir(pool).await.unwrap_or_default();
+    if project_dir.is_empty() || trash_dir.is_empty() {
+        log::error!("couldn't get project or trash folder for project {}", entry.pid);
+        return false;
+    }
+
+    let source = native_path(&get_trash_path(&trash_dir, tid, &entry.rel_path));
+    let target = native_path(&(project_dir + &(sep().to_string()) + &entry.rel_path));
+    if Path::new(&target).exists() {
+        log::warn!("couldn't restore trash entry {}, {} already exists", tid, target);
+        return false;
+    }
+    if let Some(prefix) = Path::new(&target).parent() {
+        let _ = fs::create_dir_all(prefix);
+    }
+    if let Err(err) = fs::rename(&source, &target) {
+        log::error!("couldn't restore trash entry {} to {}: {}", tid, target, err);
+        return false;
+    }
+    let _ = fs::remove_dir(native_path(&get_trash_folder(&trash_dir, tid)));
+    log::info!("restored trash entry {} to {}", tid, target);
+    dal.delete_trash_entry(tid).await.is_ok()
+}
+
+/// restores a trashed file and updates the file table right away
+pub async fn restore_from_trash(tid: i64, pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let entry = dal.get_trash_entry(tid).await?;
+    if !recover_file(tid, pool).await {
+        return Ok(false);
+    }
+
+    // the watcher would pick this up too, but the file table should be right when we return
+    let project_dir = dal.get_project_dir(entry.pid).await?;
+    let target = native_path(&(project_dir.clone() + &(sep().to_string()) + &entry.rel_path));
+    hash_paths(entry.pid, &PathBuf::from(native_path(&project_dir)), vec![PathBuf::from(target)], pool).await;
+    Ok(true)
+}
+
+/// permanently deletes a trashed file, or everything in the trash if tid is None
+pub async fn purge(tid: Option<i64>, pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let tids = match tid {
+        Some(tid) => vec![tid],
+        None => {
+            let url = dal.get_active_server().await?;
+            dal.get_trash_entries(url, None).await?.iter().map(|entry| entry.tid).collect()
+        }
+    };
+
+    let mut ok = true;
+    for tid in tids {
+        ok = purge_entry(tid, pool).await && ok;
+    }
+    Ok(ok)
+}
+
+/// permanently deletes trashed files that are older than the retention period
+pub async fn prune_trash(pool: &Pool<Sqlite>) {
+    let dal = DataAccessLayer::new(pool);
+    let url = dal.get_active_server().await.unwrap_or_default();
+    let retention_days = dal.get_trash_retention_days().await.unwrap_or_default();
+    if url.is_empty() || retention_days == 0 {
+        return;
+    }
+
+    let expired = dal.get_expired_trash_entries(url, retention_days).await.unwrap_or_default();
+    for entry in expired.iter() {
+        purge_entry(entry.tid, pool).await;
+    }
+    if !expired.is_empty() {
+        log::info!("purged {} trashed files older than {} days", expired.len(), retention_days);
+    }
+}
+
+async fn purge_entry(tid: i64, pool: &Pool<Sqlite>) -> bool {
+    let dal = DataAccessLayer::new(pool);
+    let trash_dir = get_trash_dir(pool).await.unwrap_or_default();
+    if trash_dir.is_empty() {
+        return false;
+    }
+
+    let folder = native_path(&get_trash_folder(&trash_dir, tid));
+    if Path::new(&folder).exists() {
+        if let Err(err) = fs::remove_dir_all(&folder) {
+            log::warn!("couldn't remove trash folder {}: {}", folder, err);
+            return false;
+        }
+    }
+    dal.delete_trash_entry(tid).await.is_ok()
+}
+
+fn get_trash_folder(trash_dir: &str, tid: i64) -> String {
+    trash_dir.to_owned() + &(sep().to_string()) + &tid.to_string()
+}
+
+/// keeps the file's name so the trash can be browsed from the file explorer
+fn get_trash_path(trash_dir: &str, tid: i64, rel_path: &str) -> String {
+    let name = rel_path.rsplit('\\').next().unwrap_or(rel_path);
+    get_trash_folder(trash_dir, tid) + &(sep().to_string()) + name
+}
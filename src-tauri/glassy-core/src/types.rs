@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use thiserror;
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub enum ChangeType {
+    NoChange = 0,
+    Create,
+    Update,
+    Delete,
+    Rename, // also used for moves; the file's content is unchanged
+}
+
+impl PartialEq<ChangeType> for u32 {
+    fn eq(&self, other: &ChangeType) -> bool {
+        *self == *other as u32
+    }
+}
+
+// Optionally, you might want the reverse comparison as well
+impl PartialEq<u32> for ChangeType {
+    fn eq(&self, other: &u32) -> bool {
+        *self as u32 == *other
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SettingsOptions {
+    pub local_dir: String,
+    pub debug_active: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UpdatedFile {
+    pub path: String, // relative
+    pub hash: String,
+    pub size: i64,
+    pub change: ChangeType,
+    pub in_fs: bool
+}
+
+/// a local file that was saved before a download or reset replaced it, see backup.rs
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone)]
+pub struct Backup {
+    pub bid: i64,
+    pub pid: i32,
+    pub rel_path: String,
+    pub hash: String,
+    pub size: i64,
+    pub base_commitid: i64,
+    pub reason: String,
+    pub created: String,
+}
+
+/// a file that was moved into the trash instead of being deleted, see trash.rs
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone)]
+pub struct TrashEntry {
+    pub tid: i64,
+    pub pid: i32,
+    pub rel_path: String,
+    pub hash: String,
+    pub reason: String,
+    pub created: String,
+}
+
+/// someone's lock on a file in a project, see lock.rs
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FileLock {
+    pub path: String,
+    pub user_id: String,
+    pub created: String,
+}
+
+/// the latest revision of a file on the server, as of the last sync
+#[derive(sqlx::FromRow, Clone)]
+pub struct TrackedRevision {
+    pub tracked_hash: String,
+    pub tracked_commitid: i64,
+    pub tracked_changetype: i32,
+    pub tracked_size: i64,
+}
+
+/// cheap on-disk identity of a local file, used to skip re-hashing unchanged files
+#[derive(sqlx::FromRow, Clone, PartialEq, Debug)]
+pub struct FileFingerprint {
+    pub size: i64,
+    pub mtime: i64, // nanoseconds since unix epoch
+    pub file_id: i64, // inode on linux, creation time on windows
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RemoteFile {
+    pub frid: i32,
+    pub path: String,
+    pub commitid: i32,
+    pub filehash: String,
+    pub changetype: i32, // TODO use enum
+    pub blocksize: i32,
+}
+
+/// what went wrong in a command. the frontend gets `{ kind, message }` so it can pick what to show
+#[derive(Debug, thiserror::Error)]
+pub enum GlassyError {
+    #[error("database error: {0}")]
+    Db(#[from] sqlx::Error),
+    #[error("couldn't access {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Network(#[from] reqwest::Error),
+    #[error("the server responded with {response}")]
+    Server { response: String },
+    #[error("not signed in, or not allowed to do that")]
+    Auth,
+    #[error("cache error: {0}")]
+    Cache(String),
+    #[error("{path} was changed both locally and on the server")]
+    Conflict { path: String },
+    #[error("{0} changed since it was synced")]
+    FileChanged(String),
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("{0}")]
+    Setup(String), // no server, project folder or similar set up yet
+    #[error("cancelled")]
+    Cancelled, // the job was cancelled, see job.rs
+    #[error("job error: {0}")]
+    Job(String), // a queued job couldn't be read or its result couldn't be saved, see queue.rs
+    #[error("{0}")]
+    Locked(String), // someone else has the files checked out, see lock.rs
+}
+
+impl GlassyError {
+    pub fn io(path: impl Into<String>, source: std::io::Error) -> GlassyError {
+        GlassyError::Io { path: path.into(), source }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GlassyError::Db(_) => "Db",
+            GlassyError::Io { .. } => "Io",
+            GlassyError::Network(_) => "Network",
+            GlassyError::Server { .. } => "Server",
+            GlassyError::Auth => "Auth",
+            GlassyError::Cache(_) => "Cache",
+            GlassyError::Conflict { .. } => "Conflict",
+            GlassyError::FileChanged(_) => "FileChanged",
+            GlassyError::NotFound(_) => "NotFound",
+            GlassyError::Setup(_) => "Setup",
+            GlassyError::Cancelled => "Cancelled",
+            GlassyError::Job(_) => "Job",
+            GlassyError::Locked(_) => "Locked",
+        }
+    }
+}
+
+// we must also implement serde::Serialize
+impl serde::Serialize for GlassyError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("GlassyError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DownloadServerOutput {
+    pub response: String,
+    pub body: Option<DownloadInformation>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DownloadInformation {
+    pub file_hash: String,
+    pub file_path: String,
+    pub commit_id: i64,
+    pub file_chunks: Vec<FileChunk>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+
+pub struct FileChunk {
+    pub s3_url: String,
+    pub block_hash: String,
+    pub chunk_index: i64,
+    pub file_hash: String,
+    // content-defined chunks vary in size, so the server sends where each one goes.
+    // fixed size chunks from older servers have neither
+    #[serde(default)]
+    pub offset: Option<u64>,
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DownloadRequestMessage {
+    pub commit_id: i64,
+    pub rel_path: String,
+    pub hash: String,
+    pub download: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DownloadRequest {
+    pub project_id: i64,
+    pub path: String,
+    pub commit_id: i64,
+    pub user_id: String,
+}
+
+/// a single pending change to the project folder, recorded before a download touches the project
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct JournalEntry {
+    pub idx: i64,
+    pub rel_path: String,
+    pub target: String,
+    pub source: String,
+    pub backup: String,
+    pub download: bool,
+    pub done: bool,
+}
+
+/// an upload that is being committed, kept until every part is committed so it can be resumed. see commit.rs
+#[derive(sqlx::FromRow, Serialize, Deserialize, Clone)]
+pub struct CommitJob {
+    pub cjid: i64,
+    pub pid: i32,
+    pub message: String,
+    pub created: String,
+}
+
+#[derive(sqlx::FromRow, Clone, Debug, PartialEq)]
+pub struct CommitJobFile {
+    pub idx: i64,
+    pub part: i64,
+    pub source: String,
+    pub path: String,
+    pub hash: String,
+    pub changetype: i32,
+    pub commitid: i32,
+    pub done: bool,
+}
+
+/// a job in the queue, see queue.rs
+#[derive(sqlx::FromRow, Serialize, Clone)]
+pub struct QueuedJob {
+    pub jid: i64,
+    pub pid: i32,
+    pub kind: String,
+    #[serde(skip_serializing)]
+    pub task: String,
+    #[serde(skip_serializing)]
+    pub user_id: String,
+    pub state: String,
+    pub attempts: i64,
+    pub error: Option<String>,
+    pub created: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LocalProject {
+    pub pid: i32,
+    pub title: String,
+    pub team_name: String,
+}
\ No newline at end of file
@@ -0,0 +1,320 @@
+use std::sync::Arc;
+
+use crate::chunking::{chunk_file, negotiate_chunking_strategy, read_chunk, ChunkBoundary};
+use crate::file::{sep, translate_filepath};
+use crate::types::{ChangeType, GlassyError, UpdatedFile};
+use crate::util::verify_file;
+use crate::dal::DataAccessLayer;
+use crate::config::Config;
+use crate::job::JobControl;
+use crate::network::GlassyClient;
+use crate::progress::{ProgressReporter, UploadProgress};
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use tokio::sync::Mutex;
+
+const CONCURRENT_UPLOAD_REQUESTS: usize = 2;
+
+#[derive(Serialize, Deserialize)]
+pub struct UploadResponse {
+    pub response: String,
+    pub body: Option<ServerDefaultSuccessBody>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ServerDefaultSuccessBody {
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum UploadChunkError {
+    ErrOk = 0,
+    ErrGeneric,
+    ErrInvalidFile,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UploadChunkResponse {
+    pub success: bool,
+    pub error: UploadChunkError,
+    pub message: Option<String>
+}
+
+/// uploads the chunks of `filepaths` the server doesn't have yet. the files still need to be committed
+pub async fn upload_project_files(
+    pid: i32,
+    filepaths: Vec<String>,
+    config: &Config,
+    pool: &Pool<Sqlite>,
+    progress: &dyn ProgressReporter,
+    job: &JobControl,
+) -> Result<UploadChunkResponse, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let project_dir = dal.get_project_dir(pid).await?;
+    let client = GlassyClient::new(config);
+    log::debug!("uploading files for project {}", pid);
+    let strategy = negotiate_chunking_strategy(&client).await;
+    log::debug!("chunking files with strategy {}", strategy.as_str());
+
+    let mut to_upload: Vec<UpdatedFile> = vec![];
+    let mut uploaded: u32 = 0;
+    for filepath in filepaths {
+        let file: UpdatedFile = dal.get_file_info(pid, filepath.clone()).await?;
+
+        // verify file information
+        if !verify_file(&filepath, pid, pool).await? {
+            return Ok(UploadChunkResponse {
+                success: false,
+                error: UploadChunkError::ErrInvalidFile,
+                message: Some(filepath)
+            });
+        }
+
+        // figure out which files actually need to be uploaded
+        // renamed files have the same content as a file the server already has
+        if file.change == ChangeType::Delete || file.change == ChangeType::Rename || file.size == 0 {
+            uploaded += 1;
+            progress.file_done(uploaded);
+            continue;
+        } else {
+            to_upload.push(file)
+        }
+    }
+
+    log::debug!("files sorted");
+    let total_bytes: u64 = to_upload.iter().map(|file| file.size as u64).sum();
+    let uploaded_bytes = Arc::new(Mutex::new(0u64));
+    for upload in to_upload {
+        job.checkpoint().await?;
+        let file_hash = upload.hash.clone();
+        let abs_path;
+
+        #[cfg(target_os = "windows")]
+        {
+            abs_path = project_dir.clone() + &(sep().to_string()) + &upload.path;
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            abs_path = translate_filepath(&(project_dir.clone() + &(sep().to_string()) + &upload.path), true);
+        }
+
+        // only chunk boundaries and hashes are kept in memory, chunk data is read when it is uploaded
+        log::debug!("hashing chunks of file {}", abs_path.clone());
+        let boundaries: Vec<ChunkBoundary> = match chunk_file(&abs_path, strategy) {
+            Ok(boundaries) => boundaries,
+            Err(err) => {
+                log::error!("couldn't chunk {}: {}", abs_path, err);
+                return Ok(UploadChunkResponse {
+                    success: false,
+                    error: UploadChunkError::ErrInvalidFile,
+                    message: Some(upload.path.clone()),
+                });
+            }
+        };
+        let len = boundaries.len();
+        let chunks: Vec<ChunkDescriptor> = boundaries
+            .into_iter()
+            .map(|chunk| ChunkDescriptor {
+                file_hash: file_hash.clone(),
+                block_hash: chunk.block_hash,
+                chunk_index: chunk.chunk_index,
+                offset: chunk.offset,
+                size: chunk.length,
+            })
+            .collect();
+
+        // skip chunks the server already has, e.g. from an upload that was interrupted
+        let missing = get_missing_chunks(&client, &chunks).await;
+        let missing: Vec<ChunkDescriptor> = chunks
+            .iter()
+            .filter(|chunk| missing.iter().any(|m| m.chunk_index == chunk.chunk_index))
+            .cloned()
+            .collect();
+        log::debug!("{} of {} chunks need to be uploaded", missing.len(), len);
+        let already_uploaded: u64 = chunks
+            .iter()
+            .filter(|chunk| !missing.iter().any(|m| m.chunk_index == chunk.chunk_index))
+            .map(|chunk| chunk.size)
+            .sum();
+        {
+            let mut bytes = uploaded_bytes.lock().await;
+            *bytes += already_uploaded;
+            progress.upload_progress(UploadProgress { uploaded_bytes: *bytes, total_bytes });
+        }
+
+        let copied_client = &client;
+        let copied_path = &abs_path;
+        let chunk_reqs = stream::iter(missing)
+            .map(|chunk| async move {
+                if job.checkpoint().await.is_err() {
+                    return (0, Err(UploadChunkError::ErrGeneric));
+                }
+                let data = match read_chunk(copied_path, chunk.offset, chunk.size) {
+                    Ok(data) => data,
+                    Err(err) => {
+                        log::error!("couldn't read chunk {} of {}: {}", chunk.chunk_index, copied_path, err);
+                        return (0, Err(UploadChunkError::ErrInvalidFile));
+                    }
+                };
+                // file changed since it was hashed
+                if blake3::hash(&data).to_hex().as_str() != chunk.block_hash {
+                    log::error!("chunk {} of {} changed during upload", chunk.chunk_index, copied_path);
+                    return (0, Err(UploadChunkError::ErrInvalidFile));
+                }
+                log::trace!("block hash: {}\tfile hash: {}", chunk.block_hash, chunk.file_hash);
+                let res = copied_client.upload_chunk(&chunk, len, strategy, data).await;
+                (chunk.size, Ok(res))
+            })
+            .buffer_unordered(CONCURRENT_UPLOAD_REQUESTS);
+
+        let error_flag = Arc::new(Mutex::new(None::<UploadChunkError>));
+        let moved_error_flag = &error_flag;
+        let moved_uploaded_bytes = &uploaded_bytes;
+        let moved_progress = &progress;
+        chunk_reqs
+            .for_each(|(size, res)| async move {
+                match res {
+                    Ok(Ok(())) => {
+                        let mut bytes = moved_uploaded_bytes.lock().await;
+                        *bytes += size;
+                        moved_progress.upload_progress(UploadProgress { uploaded_bytes: *bytes, total_bytes });
+                    }
+                    Ok(Err(err)) => {
+                        log::error!("error uploading a chunk: {}", err);
+                        let mut error = moved_error_flag.lock().await;
+                        *error = Some(UploadChunkError::ErrGeneric);
+                    }
+                    Err(upload_error) => {
+                        let mut error = moved_error_flag.lock().await;
+                        *error = Some(upload_error);
+                    }
+                }
+            })
+            .await;
+
+        job.checkpoint().await?;
+        if let Some(error) = error_flag.lock().await.take() {
+            let message = match error {
+                UploadChunkError::ErrInvalidFile => Some(upload.path.clone()),
+                _ => None,
+            };
+            return Ok(UploadChunkResponse { success: false, error, message });
+        }
+        uploaded += 1;
+        progress.file_done(uploaded);
+    }
+
+    log::debug!("files uploaded!");
+    Ok(UploadChunkResponse { success: true, error: UploadChunkError::ErrOk, message: None })
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ChunkDescriptor {
+    pub file_hash: String,
+    pub block_hash: String,
+    pub chunk_index: usize,
+    // older servers only echo back file_hash, block_hash and chunk_index
+    #[serde(default)]
+    pub offset: u64,
+    #[serde(default)]
+    pub size: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MissingChunksRequest {
+    pub user_id: String,
+    pub chunks: Vec<ChunkDescriptor>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MissingChunksResponse {
+    pub response: String,
+    pub body: Option<MissingChunksBody>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MissingChunksBody {
+    pub missing: Vec<ChunkDescriptor>,
+}
+
+/// asks the server which chunks of a file it doesn't have yet.
+/// if the server can't tell us, every chunk is treated as missing
+pub async fn get_missing_chunks(client: &GlassyClient, chunks: &[ChunkDescriptor]) -> Vec<ChunkDescriptor> {
+    match client.missing_chunks(chunks).await {
+        Ok(missing) => missing,
+        Err(err) => {
+            log::warn!("couldn't ask server for missing chunks: {}", err);
+            chunks.to_vec()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UploadedFile {
+    pub path: String,
+    pub hash: String,
+    pub changetype: i32,
+}
+
+/// updates the file table once `files` are committed
+pub async fn mark_uploaded(pid: i32, commit: i32, files: Vec<UploadedFile>, pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+
+    log::debug!("updating db with uploaded files...");
+    for file in files {
+        if file.changetype == 3 {
+            // delete
+            let owo = sqlx::query(
+                "DELETE FROM file
+                WHERE pid = $1 AND filepath = $2",
+            )
+            .bind(pid)
+            .bind(file.path.clone())
+            .execute(pool)
+            .await;
+            match owo {
+                Ok(_) => {}
+                Err(err) => {
+                    log::error!(
+                        "encountered error when deleting {} from db for project {}: {}",
+                        file.path,
+                        pid,
+                        err
+                    );
+                }
+            }
+        } else if file.changetype == 1 || file.changetype == 2 {
+            let uwu = sqlx::query(
+                "UPDATE file SET
+                change_type = 0,
+                base_hash = curr_hash,
+                tracked_hash = curr_hash,
+                base_commitid = $1,
+                tracked_commitid = $1
+                WHERE pid = $2 AND filepath = $3",
+            )
+            .bind(commit)
+            .bind(pid)
+            .bind(file.path.clone())
+            .execute(pool)
+            .await;
+
+            match uwu {
+                Ok(_) => {}
+                Err(err) => {
+                    log::error!(
+                        "encountered error when updating {} from db for project {}: {}",
+                        file.path,
+                        pid,
+                        err
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(true)
+}
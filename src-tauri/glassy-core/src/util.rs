@@ -0,0 +1,213 @@
+use log::error;
+use std::process::Command;
+use sqlx::{Pool, Sqlite};
+use std::fs::{self, create_dir_all, read_dir, remove_dir_all, remove_file};
+use std::io::ErrorKind;
+//use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::result::Result::Ok;
+use crate::file::sep;
+//use std::alloc;
+//use cap::Cap;
+use crate::config::get_server_dir;
+use crate::dal::DataAccessLayer;
+use crate::sync::hash_file;
+use crate::types::GlassyError;
+
+pub async fn get_cache_dir(pool: &Pool<Sqlite>) -> Result<String, GlassyError> {
+    let dir = get_server_dir(pool).await?;
+    Ok(dir + &(sep().to_string()) + ".glassycache")
+}
+
+// content-addressed block store shared by every cached file, see download.rs
+pub fn get_block_dir(cache_dir: &String) -> String {
+    cache_dir.to_owned() + &(sep().to_string()) + "blocks"
+}
+
+pub async fn get_trash_dir(pool: &Pool<Sqlite>) -> Result<String, GlassyError> {
+    let dir = get_server_dir(pool).await?;
+    if dir.is_empty() {
+        return Ok("".to_string());
+    }
+    let output = dir + &(sep().to_string()) + ".glassytrash";
+    create_dir_all(&output).map_err(|err| GlassyError::io(&output, err))?;
+    Ok(output)
+}
+
+// staged downloads and their backups, see journal.rs
+pub async fn get_staging_dir(pool: &Pool<Sqlite>) -> Result<String, GlassyError> {
+    let dir = get_server_dir(pool).await?;
+    if dir.is_empty() {
+        return Ok("".to_string());
+    }
+    Ok(dir + &(sep().to_string()) + ".glassystaging")
+}
+
+// local edits that were saved before a download or reset replaced them, see backup.rs
+pub async fn get_backup_dir(pool: &Pool<Sqlite>) -> Result<String, GlassyError> {
+    let dir = get_server_dir(pool).await?;
+    if dir.is_empty() {
+        return Ok("".to_string());
+    }
+    Ok(dir + &(sep().to_string()) + ".glassybackup")
+}
+
+pub async fn delete_cache(pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    log::info!("deleting the cache...");
+    let cache_dir = get_cache_dir(pool).await?;
+    let dal = DataAccessLayer::new(pool);
+    let url = dal.get_active_server().await?;
+    dal.clear_cache_tables(url).await?;
+    match remove_dir_all(Path::new(&cache_dir)) {
+        Ok(_res) => {
+            log::info!("cache successfully deleted");
+            Ok(true)
+        },
+        // nothing was cached yet
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(true),
+        Err(err) => {
+            log::error!("could not delete the cache: {}", err);
+            Err(GlassyError::io(cache_dir, err))
+        }
+    }
+}
+
+/// evicts least recently used files from the cache until it fits in the configured maximum size.
+/// returns the number of files evicted
+pub async fn evict_cache(pool: &Pool<Sqlite>) -> Result<u32, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let url = dal.get_active_server().await?;
+    let cache_dir = get_cache_dir(pool).await?;
+    if url.is_empty() || cache_dir.is_empty() {
+        return Ok(0);
+    }
+    let block_dir = get_block_dir(&cache_dir);
+    remove_legacy_cache(&cache_dir);
+
+    let max_size = dal.get_cache_max_size().await?;
+    if max_size == 0 {
+        return Ok(0);
+    }
+
+    let mut size = dal.get_cached_size(url.clone()).await?;
+    let mut evicted = 0;
+    while size > max_size {
+        let file_hash = match dal.get_lru_cache_file(url.clone()).await? {
+            Some(hash) => hash,
+            None => break,
+        };
+        for block_hash in dal.drop_cache_manifest(url.clone(), file_hash.clone()).await? {
+            let path = block_dir.clone() + &(sep().to_string()) + &block_hash;
+            if let Err(err) = remove_file(&path) {
+                if err.kind() != ErrorKind::NotFound {
+                    log::warn!("couldn't remove cached block {}: {}", path, err);
+                }
+            }
+        }
+        evicted += 1;
+        size = dal.get_cached_size(url.clone()).await?;
+    }
+    if evicted > 0 {
+        log::info!("evicted {} files from the cache, cache is now {} bytes", evicted, size);
+    }
+    Ok(evicted)
+}
+
+// before the block store, every cached file had its own folder of chunks
+fn remove_legacy_cache(cache_dir: &String) {
+    let entries = match read_dir(cache_dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        if entry.file_name() != "blocks" && entry.path().is_dir() {
+            let _ = remove_dir_all(entry.path());
+        }
+    }
+}
+
+/// opens `pb` in the file explorer. returns false where that isn't supported yet
+pub fn open_directory(pb: PathBuf) -> Result<bool, GlassyError> {
+    if !cfg!(target_os = "windows") {
+        // TODO
+        log::warn!("opening {} is only implemented on windows", pb.display());
+        return Ok(false);
+    }
+    Command::new("explorer")
+        .arg(&pb)
+        .spawn()
+        .map_err(|err| GlassyError::io(pb.display().to_string(), err))?;
+    Ok(true)
+}
+/// makes a file read-only or writable for its owner. returns false if the permissions couldn't be changed
+pub fn set_readonly(path: &Path, readonly: bool) -> bool {
+    let mut permissions = match fs::metadata(path) {
+        Ok(metadata) => metadata.permissions(),
+        Err(_) => return false,
+    };
+    if permissions.readonly() == readonly {
+        return true;
+    }
+    #[cfg(target_os = "windows")]
+    {
+        permissions.set_readonly(readonly);
+    }
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = permissions.mode();
+        permissions.set_mode(if readonly { mode & !0o222 } else { mode | 0o200 });
+    }
+    match fs::set_permissions(path, permissions) {
+        Ok(_) => true,
+        Err(err) => {
+            log::warn!("couldn't make {} {}: {}", path.display(), if readonly { "read-only" } else { "writable" }, err);
+            false
+        }
+    }
+}
+
+/*
+#[global_allocator]
+static ALLOCATOR: Cap<alloc::System> = Cap::new(alloc::System, usize::max_value());
+
+pub fn get_allocated() -> usize {
+    ALLOCATOR.allocated()
+}
+
+pub fn get_max_allocated() -> usize {
+    ALLOCATOR.max_allocated()
+}
+     */
+
+pub async fn verify_file(rel_path: &String, pid: i32, pool: &Pool<Sqlite>) -> Result<bool, GlassyError> {
+    let dal = DataAccessLayer::new(pool);
+    let project_dir = dal.get_project_dir(pid).await?;
+    let absolute_path = project_dir + &(sep().to_string()) + rel_path;
+    let abs_path = Path::new(&absolute_path);
+    // get current file info
+    let file_info = dal.get_file_info(pid, rel_path.to_string()).await?;
+
+    // check file existence before any sort of hashing
+    if !abs_path.exists() && !file_info.in_fs {
+        // valid
+        return Ok(true);
+    } else if (!abs_path.exists() && file_info.in_fs) || (abs_path.exists() && !file_info.in_fs)  {
+        // invalid
+        error!("path {} does not match up: current {}, stored {}", rel_path, abs_path.exists(), file_info.in_fs);
+        return Ok(false);
+    }
+
+
+    let curr_hash = match hash_file(abs_path) {
+        Some(hash) => hash,
+        None => return Ok(false),
+    };
+    //let metadata = std::fs::metadata(&absolute_path).unwrap();
+
+    if file_info.hash != curr_hash {
+        error!("path {} does not match: current {}, stored {}", rel_path, curr_hash, file_info.hash);
+        return Ok(false)
+    }
+    Ok(true)
+}
\ No newline at end of file
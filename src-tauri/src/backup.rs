@@ -0,0 +1,47 @@
+use glassy_core::backup::{prune_backups, remove_backup, restore_from_backup};
+use glassy_core::dal::DataAccessLayer;
+use glassy_core::types::{Backup, GlassyError};
+use sqlx::{Pool, Sqlite};
+use tauri::State;
+use tokio::sync::Mutex;
+
+#[tauri::command]
+pub async fn list_backups(pid: Option<i32>, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<Vec<Backup>, GlassyError> {
+    let pool = state_mutex.lock().await;
+    let dal = DataAccessLayer::new(&pool);
+    let url = dal.get_active_server().await?;
+
+    dal.get_backups(url, pid).await
+}
+
+/// copies a backup back into its project, backing up whatever is there now if it has local edits
+#[tauri::command]
+pub async fn restore_backup(bid: i64, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await;
+    restore_from_backup(bid, &pool).await
+}
+
+#[tauri::command]
+pub async fn delete_backup(bid: i64, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await;
+    Ok(remove_backup(bid, &pool).await)
+}
+
+#[tauri::command]
+pub async fn cmd_get_backup_retention(state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<u32, GlassyError> {
+    let pool = state_mutex.lock().await;
+    let dal = DataAccessLayer::new(&pool);
+
+    dal.get_backup_retention_days().await
+}
+
+/// sets how many days backups are kept for, 0 to keep them forever
+#[tauri::command]
+pub async fn cmd_set_backup_retention(days: u32, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await;
+    let dal = DataAccessLayer::new(&pool);
+
+    let res = dal.set_backup_retention_days(days).await;
+    prune_backups(&pool).await;
+    res
+}
@@ -0,0 +1,377 @@
+use clap::{Parser, Subcommand};
+use glassy_core::commit::{commit_files, resume_commit};
+use glassy_core::config::Config;
+use glassy_core::dal::DataAccessLayer;
+use glassy_core::download::download_project_files;
+use glassy_core::job::JobControl;
+use glassy_core::journal::recover_journals;
+use glassy_core::network::GlassyClient;
+use glassy_core::progress::{ProgressReporter, UploadProgress};
+use glassy_core::reset::reset_project_files;
+use glassy_core::sync::{sync_project, FileChange};
+use glassy_core::types::{ChangeType, DownloadRequestMessage, GlassyError};
+use serde::Deserialize;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{Pool, Sqlite, SqlitePool};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// sync glassyPDM projects without opening the app.
+/// uses the app's database, so the server and project need to be set up in the app first
+#[derive(Parser)]
+#[command(name = "glassy-cli", version)]
+struct Cli {
+    /// id of the project to work on
+    #[arg(short, long)]
+    project: i32,
+
+    /// database to use instead of the app's
+    #[arg(long, env = "GLASSY_DB")]
+    db: Option<PathBuf>,
+
+    /// json file with `user_id` and `token`, used when GLASSY_USER_ID and GLASSY_TOKEN aren't set
+    #[arg(long, env = "GLASSY_TOKEN_FILE")]
+    token_file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// list local and remote changes
+    Status,
+    /// download every remote change
+    Pull,
+    /// upload every local change and commit it
+    Push {
+        #[arg(short, long)]
+        message: String,
+    },
+    /// throw away local changes to the given files, or to every file
+    Reset { paths: Vec<String> },
+    /// list the project's commits, newest first
+    Log {
+        #[arg(long, default_value_t = 0)]
+        offset: u32,
+    },
+}
+
+#[derive(Deserialize)]
+struct Credentials {
+    user_id: String,
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct CommitHistoryOutput {
+    response: String,
+    body: Option<CommitHistory>,
+}
+
+#[derive(Deserialize)]
+struct CommitHistory {
+    num_commits: u32,
+    commits: Vec<CommitDescription>,
+}
+
+#[derive(Deserialize)]
+struct CommitDescription {
+    commit_number: i32,
+    num_files: u32,
+    author: String,
+    comment: String,
+}
+
+/// prints progress to stderr so stdout stays parseable
+#[derive(Default)]
+struct ProgressPrinter {
+    chunks: AtomicU64,
+    files: AtomicU64,
+}
+
+impl ProgressReporter for ProgressPrinter {
+    fn file_done(&self, _done: u32) {
+        let done = self.files.fetch_add(1, Ordering::Relaxed) + 1;
+        eprint!("\r{} files done", done);
+    }
+
+    fn chunk_downloaded(&self, total: usize) {
+        let done = self.chunks.fetch_add(1, Ordering::Relaxed) + 1;
+        eprint!("\rdownloaded {} of {} chunks", done, total);
+    }
+
+    fn chunks_cached(&self) {
+        eprintln!("\nassembling files...");
+    }
+
+    fn upload_progress(&self, progress: UploadProgress) {
+        eprint!("\ruploaded {} of {} bytes", progress.uploaded_bytes, progress.total_bytes);
+    }
+
+    fn files_skipped(&self, paths: &[String]) {
+        eprintln!("\nskipped {} files that are open in another program:", paths.len());
+        for path in paths {
+            eprintln!("  {}", path);
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let pool = match open_db(cli.db.clone()).await {
+        Ok(pool) => pool,
+        Err(err) => {
+            eprintln!("couldn't open the database: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    // finish or undo downloads that were interrupted last time
+    recover_journals(&pool).await;
+
+    let res = match &cli.command {
+        Command::Status => status(&cli, &pool).await,
+        Command::Pull => pull(&cli, &pool).await,
+        Command::Push { message } => push(&cli, message, &pool).await,
+        Command::Reset { paths } => reset(&cli, paths.clone(), &pool).await,
+        Command::Log { offset } => show_log(&cli, *offset, &pool).await,
+    };
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// ctrl+c stops the job at its next safe point instead of killing it part way through
+fn cancel_on_ctrl_c() -> JobControl {
+    let job = JobControl::default();
+    let cancel = job.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!("\ncancelling...");
+            cancel.cancel();
+        }
+    });
+    job
+}
+
+async fn status(cli: &Cli, pool: &Pool<Sqlite>) -> Result<(), String> {
+    let config = get_config(cli, pool).await?;
+    sync(cli.project, &config, pool).await?;
+
+    let dal = DataAccessLayer::new(pool);
+    let uploads = dal.get_uploads(cli.project).await.map_err(|err| format!("couldn't query uploads: {}", err))?;
+    let downloads = dal.get_downloads(cli.project).await.map_err(|err| format!("couldn't query downloads: {}", err))?;
+    let conflicts = dal.get_conflicts(cli.project).await.map_err(|err| format!("couldn't query conflicts: {}", err))?;
+    print_changes("local changes", &uploads);
+    print_changes("remote changes", &downloads);
+    print_changes("conflicts", &conflicts);
+    Ok(())
+}
+
+async fn pull(cli: &Cli, pool: &Pool<Sqlite>) -> Result<(), String> {
+    let config = get_config(cli, pool).await?;
+    sync(cli.project, &config, pool).await?;
+
+    let dal = DataAccessLayer::new(pool);
+    let downloads = dal.get_downloads(cli.project).await.map_err(|err| format!("couldn't query downloads: {}", err))?;
+    if downloads.is_empty() {
+        println!("already up to date");
+        return Ok(());
+    }
+    let files: Vec<DownloadRequestMessage> = downloads
+        .iter()
+        .map(|file| DownloadRequestMessage {
+            commit_id: file.commit_id.into(),
+            rel_path: file.filepath.clone(),
+            hash: file.hash.clone(),
+            download: file.change_type != ChangeType::Delete,
+        })
+        .collect();
+
+    let progress = ProgressPrinter::default();
+    match download_project_files(cli.project, files, &config, pool, &progress, &cancel_on_ctrl_c()).await {
+        Ok(true) => {
+            eprintln!();
+            println!("downloaded {} files", downloads.len());
+            Ok(())
+        }
+        Ok(false) => Err("\ndownload failed, see the app's log for details".to_string()),
+        Err(err) => Err(format!("\ndownload failed: {}", err)),
+    }
+}
+
+async fn push(cli: &Cli, message: &str, pool: &Pool<Sqlite>) -> Result<(), String> {
+    let config = get_config(cli, pool).await?;
+    sync(cli.project, &config, pool).await?;
+
+    let dal = DataAccessLayer::new(pool);
+    let progress = ProgressPrinter::default();
+    // an upload that was interrupted last time is finished first
+    if let Some(job) = dal.get_commit_job(cli.project).await.map_err(|err| err.to_string())? {
+        eprintln!("finishing interrupted upload \"{}\"", job.message);
+        let res = resume_commit(cli.project, &config, pool, &progress, &cancel_on_ctrl_c())
+            .await
+            .map_err(|err| format!("\nupload failed: {}", err))?;
+        eprintln!();
+        if !res.success {
+            return Err("couldn't finish the interrupted upload, check your connection and try again".to_string());
+        }
+    }
+
+    let uploads = dal.get_uploads(cli.project).await.map_err(|err| format!("couldn't query uploads: {}", err))?;
+    if uploads.is_empty() {
+        println!("nothing to upload");
+        return Ok(());
+    }
+    if !dal.get_conflicts(cli.project).await.map_err(|err| format!("couldn't query conflicts: {}", err))?.is_empty() {
+        return Err("resolve conflicts in the app before uploading".to_string());
+    }
+
+    let paths: Vec<String> = uploads.iter().map(|file| file.filepath.clone()).collect();
+    let res = commit_files(cli.project, paths, message.to_string(), &config, pool, &progress, &cancel_on_ctrl_c())
+        .await
+        .map_err(|err| format!("\nupload failed: {}", err))?;
+    eprintln!();
+    if !res.success {
+        return Err(match res.message {
+            Some(path) => format!("upload failed: {} changed since it was synced, run status and try again", path),
+            None => "upload failed, check your permissions and connection".to_string(),
+        });
+    }
+    println!("committed {} files", uploads.len());
+    Ok(())
+}
+
+async fn reset(cli: &Cli, paths: Vec<String>, pool: &Pool<Sqlite>) -> Result<(), String> {
+    let config = get_config(cli, pool).await?;
+    let paths: Vec<String> = if paths.is_empty() {
+        let dal = DataAccessLayer::new(pool);
+        let uploads = dal.get_uploads(cli.project).await.map_err(|err| format!("couldn't query uploads: {}", err))?;
+        uploads.into_iter().map(|file| file.filepath).collect()
+    } else {
+        // paths in the file table always use backslashes
+        paths.iter().map(|path| path.replace('/', "\\")).collect()
+    };
+    if paths.is_empty() {
+        println!("nothing to reset");
+        return Ok(());
+    }
+
+    let num_paths = paths.len();
+    let progress = ProgressPrinter::default();
+    match reset_project_files(cli.project, paths, &config, pool, &progress, &cancel_on_ctrl_c()).await {
+        Ok(true) => {
+            eprintln!();
+            println!("reset {} files", num_paths);
+            Ok(())
+        }
+        Ok(false) => Err("\nreset failed, see the app's log for details".to_string()),
+        Err(err) => Err(format!("\nreset failed: {}", err)),
+    }
+}
+
+async fn show_log(cli: &Cli, offset: u32, pool: &Pool<Sqlite>) -> Result<(), String> {
+    let config = get_config(cli, pool).await?;
+    let output: CommitHistoryOutput = GlassyClient::new(&config)
+        .get_json(&format!("/commit/select/by-project/{}?offset={}", cli.project, offset))
+        .await
+        .map_err(|err| format!("couldn't get commits: {}", err))?;
+    let history = match output.body {
+        Some(body) if output.response == "success" => body,
+        _ => return Err(format!("couldn't get commits: {}", output.response)),
+    };
+
+    for commit in history.commits.iter() {
+        println!("#{}\t{}\t{} files\t{}", commit.commit_number, commit.author, commit.num_files, commit.comment);
+    }
+    println!("{} commits in total", history.num_commits);
+    Ok(())
+}
+
+/// gets the project's state from the server and updates the file table with it
+async fn sync(pid: i32, config: &Config, pool: &Pool<Sqlite>) -> Result<(), String> {
+    let dal = DataAccessLayer::new(pool);
+    if dal.get_project_dir(pid).await.unwrap_or_default() == "" {
+        return Err(format!("project {} isn't set up, open it in the app first", pid));
+    }
+
+    let remote = GlassyClient::new(config)
+        .project_status(pid, "latest")
+        .await
+        .map_err(|err| format!("couldn't sync: {}", err))?;
+
+    sync_project(pid, remote, false, pool)
+        .await
+        .map_err(|err| format!("couldn't update the file table: {}", err))?;
+    Ok(())
+}
+
+fn print_changes(title: &str, changes: &[FileChange]) {
+    println!("{} ({}):", title, changes.len());
+    for change in changes.iter() {
+        let label = match change.change_type {
+            1 => "created",
+            2 => "updated",
+            3 => "deleted",
+            4 => "renamed",
+            _ => "unchanged",
+        };
+        println!("  {:<8} {}", label, change.filepath);
+    }
+}
+
+async fn get_config(cli: &Cli, pool: &Pool<Sqlite>) -> Result<Config, String> {
+    let credentials = get_credentials(cli)?;
+    let config = match Config::load(credentials.user_id.clone(), pool).await {
+        Ok(config) => config,
+        Err(GlassyError::Setup(_)) => return Err("no server is set up, add one in the app first".to_string()),
+        Err(err) => return Err(format!("couldn't get the server url: {}", err)),
+    };
+    Ok(config.with_token(Some(credentials.token)))
+}
+
+/// reads credentials from GLASSY_USER_ID and GLASSY_TOKEN, or from the token file
+fn get_credentials(cli: &Cli) -> Result<Credentials, String> {
+    if let (Ok(user_id), Ok(token)) = (std::env::var("GLASSY_USER_ID"), std::env::var("GLASSY_TOKEN")) {
+        return Ok(Credentials { user_id, token });
+    }
+    let path = match &cli.token_file {
+        Some(path) => path.clone(),
+        None => get_app_data_dir()?.join("cli-token.json"),
+    };
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|err| format!("couldn't read token file {}: {}, set GLASSY_USER_ID and GLASSY_TOKEN instead", path.display(), err))?;
+    serde_json::from_str(&contents).map_err(|err| format!("couldn't parse token file {}: {}", path.display(), err))
+}
+
+async fn open_db(path: Option<PathBuf>) -> Result<Pool<Sqlite>, String> {
+    let path = match path {
+        Some(path) => path,
+        None => get_app_data_dir()?.join("glassypdm.db"),
+    };
+    // the app creates the database, there is nothing to sync without it
+    let options = SqliteConnectOptions::new().filename(&path).create_if_missing(false);
+    let pool = SqlitePool::connect_with(options).await.map_err(|err| format!("{}: {}", path.display(), err))?;
+    glassy_core::MIGRATOR.run(&pool).await.map_err(|err| err.to_string())?;
+    Ok(pool)
+}
+
+/// same folder as the app's app_data_dir
+fn get_app_data_dir() -> Result<PathBuf, String> {
+    const IDENTIFIER: &str = "org.glassypdm.app";
+    #[cfg(target_os = "windows")]
+    let base = std::env::var("APPDATA").map(PathBuf::from);
+    #[cfg(target_os = "macos")]
+    let base = std::env::var("HOME").map(|home| PathBuf::from(home).join("Library").join("Application Support"));
+    #[cfg(target_os = "linux")]
+    let base = std::env::var("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| std::env::var("HOME").map(|home| PathBuf::from(home).join(".local").join("share")));
+    base.map(|dir| dir.join(IDENTIFIER)).map_err(|_| "couldn't find the app's data folder, pass --db".to_string())
+}
@@ -1,23 +1,24 @@
-use crate::{
-    file::translate_filepath, types::RemoteFile, util::open_directory, dal::DataAccessLayer
+use glassy_core::{
+    types::GlassyError, util::open_directory, dal::DataAccessLayer
 };
-use merkle_hash::{bytes_to_hex, Algorithm, MerkleTree};
-use serde::{Deserialize, Serialize};
 use sqlx::{Pool, Row, Sqlite};
 use std::path::{Path, PathBuf};
 use std::fs::{self, remove_dir_all};
 use tauri::State;
 use tokio::sync::Mutex;
-use crate::types::LocalProject;
+use glassy_core::file::{native_path, sep};
+use glassy_core::trash::trash_file;
+use glassy_core::types::LocalProject;
+use crate::watcher::{stop_watching, ProjectWatchers};
 
 
 #[tauri::command]
 pub async fn get_local_projects(
     state_mutex: State<'_, Mutex<Pool<Sqlite>>>,
-) -> Result<Vec<LocalProject>, ()> {
+) -> Result<Vec<LocalProject>, GlassyError> {
     let pool = state_mutex.lock().await;
     let dal = DataAccessLayer::new(&pool);
-    let server = dal.get_active_server().await.unwrap();
+    let server = dal.get_active_server().await?;
 
     let pid_query = sqlx::query("SELECT DISTINCT pid FROM file")
         .fetch_all(&*pool)
@@ -36,7 +37,7 @@ pub async fn get_local_projects(
 
                 match query {
                     Ok(row) => output.push(LocalProject {
-                        pid: pid,
+                        pid,
                         title: row.get::<String, &str>("title").to_string(),
                         team_name: row.get::<String, &str>("team_name").to_string(),
                     }),
@@ -57,63 +58,70 @@ pub async fn get_local_projects(
 pub async fn open_project_dir(
     pid: i32,
     state_mutex: State<'_, Mutex<Pool<Sqlite>>>,
-) -> Result<(), ()> {
+) -> Result<(), GlassyError> {
     let pool = state_mutex.lock().await;
     let dal = DataAccessLayer::new(&pool);
 
-    let project_dir = dal.get_project_dir(pid).await.unwrap();
-    let _ = fs::create_dir_all(&project_dir);
+    let project_dir = dal.get_project_dir(pid).await?;
+    fs::create_dir_all(&project_dir).map_err(|err| GlassyError::io(&project_dir, err))?;
     let mut pb = PathBuf::new();
     pb.push(project_dir);
-    open_directory(pb);
+    open_directory(pb)?;
 
-    return Ok(());
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn clear_file_table(pid: i32, commit: String, delete: bool, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<(), ()> {
+pub async fn clear_file_table(pid: i32, commit: String, delete: bool, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<(), GlassyError> {
     let pool = state_mutex.lock().await;
     let dal = DataAccessLayer::new(&pool);
 
     if commit == "latest" {
-        let _ = dal.clear_file_table_for_project(pid).await;
+        dal.clear_file_table_for_project(pid).await?;
         return Ok(());
     }
-    let res = dal.clear_file_table_for_project_after_commit(pid, commit.parse().unwrap()).await;
+    let commit_id: i32 = commit.parse().map_err(|_| GlassyError::NotFound(format!("commit {}", commit)))?;
+    let res = dal.clear_file_table_for_project_after_commit(pid, commit_id).await;
     if !delete {
         return Ok(())
     }
     match res {
         Ok(hehez) => {
             // delete the files!
-            let proj_dir = dal.get_project_dir(pid).await.unwrap();
-            if proj_dir == "" {
+            let proj_dir = dal.get_project_dir(pid).await?;
+            if proj_dir.is_empty() {
                 log::warn!("did not get a project dir, so could not delete files");
                 return Ok(());
             }
             for hehe in hehez {
-                let path = proj_dir.clone() + "\\" + &hehe;
+                let path = native_path(&(proj_dir.clone() + &sep().to_string() + &hehe));
                 let _ = fs::remove_file(path);
 
                 // TODO try to delete its directories
             }
         },
         Err(err) => {
-            log::warn!("did not delete the files")
+            log::warn!("did not delete the files: {}", err)
         }
     }
-    return Ok(());
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn delete_project(pid: i32, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<(), String> {
+pub async fn delete_project(pid: i32, state_mutex: State<'_, Mutex<Pool<Sqlite>>>, watchers: State<'_, ProjectWatchers>) -> Result<(), GlassyError> {
+    stop_watching(pid, &watchers);
     let pool = state_mutex.lock().await;
     let dal = DataAccessLayer::new(&pool);
 
-    let _ = dal.clear_file_table_for_project(pid).await;
+    // keep the project's files in the trash for a while before deleting the folder
+    let project_dir = dal.get_project_dir(pid).await?;
+    for (rel_path, hash) in dal.get_local_files(pid).await? {
+        let proj_path = native_path(&(project_dir.clone() + &sep().to_string() + &rel_path));
+        let _ = trash_file(pid, &rel_path, &hash, &proj_path, "project", &pool).await;
+    }
+    dal.clear_file_table_for_project(pid).await?;
 
     // delete project folder
-    let project_dir = dal.get_project_dir(pid).await.unwrap();
     match remove_dir_all(Path::new(&project_dir)) {
         Ok(_res) => {},
         Err(err) => {
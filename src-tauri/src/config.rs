@@ -1,11 +1,15 @@
-use crate::{dal::DataAccessLayer, types::SettingsOptions};
+use crate::watcher::{stop_watching_all, ProjectWatchers};
+use glassy_core::config::{get_cache_setting, get_server_dir};
+use glassy_core::dal::DataAccessLayer;
+use glassy_core::types::{GlassyError, SettingsOptions};
+use glassy_core::util::evict_cache;
 use fs_extra::dir::{move_dir, CopyOptions};
 use sqlx::{Pool, Row, Sqlite};
 use tauri::State;
 use tokio::sync::Mutex;
 
 #[tauri::command]
-pub async fn get_server_name(state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<String, ()> {
+pub async fn get_server_name(state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<String, GlassyError> {
     let pool = state_mutex.lock().await;
     let dal = DataAccessLayer::new(&pool);
 
@@ -13,7 +17,7 @@ pub async fn get_server_name(state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Res
 }
 
 #[tauri::command]
-pub async fn get_server_url(state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<String, ()> {
+pub async fn get_server_url(state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<String, GlassyError> {
     let pool = state_mutex.lock().await;
     let dal = DataAccessLayer::new(&pool);
 
@@ -21,42 +25,27 @@ pub async fn get_server_url(state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Resu
 }
 
 #[tauri::command]
-pub async fn set_debug(debug: i32, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<(), ()> {
+pub async fn set_debug(debug: i32, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<(), GlassyError> {
     let pool = state_mutex.lock().await;
 
-    let _ = sqlx::query("UPDATE server SET debug_active = ? WHERE active = 1")
+    sqlx::query("UPDATE server SET debug_active = ? WHERE active = 1")
         .bind(debug)
         .execute(&*pool)
-        .await;
+        .await?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub async fn get_server_clerk(state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<String, ()> {
+pub async fn get_server_clerk(state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<String, GlassyError> {
     let pool = state_mutex.lock().await;
     let output = sqlx::query("SELECT clerk_publickey FROM server WHERE active = 1")
-        .fetch_one(&*pool)
-        .await;
+        .fetch_optional(&*pool)
+        .await?;
+    // empty until a server is added
     match output {
-        Ok(row) => Ok(row.get::<String, &str>("clerk_publickey")),
-        Err(err) => {
-            println!("ooga booga {}", err);
-            Ok("".to_string())
-        }
-    }
-}
-
-pub async fn get_server_dir(pool: &Pool<Sqlite>) -> Result<String, ()> {
-    let output = sqlx::query("SELECT local_dir FROM server WHERE active = 1")
-        .fetch_one(&*pool)
-        .await;
-    match output {
-        Ok(row) => Ok(row.get::<String, &str>("local_dir")),
-        Err(err) => {
-            println!("dir not found {}", err);
-            Ok("".to_string())
-        }
+        Some(row) => Ok(row.get::<String, &str>("clerk_publickey")),
+        None => Ok("".to_string()),
     }
 }
 
@@ -67,33 +56,32 @@ pub async fn add_server(
     local_dir: String,
     name: String,
     state_mutex: State<'_, Mutex<Pool<Sqlite>>>,
-) -> Result<bool, ()> {
+) -> Result<bool, GlassyError> {
     let pool = state_mutex.lock().await;
     let dal = DataAccessLayer::new(&pool);
-    let _ = dal.add_server(url, clerk, local_dir, name).await;
+    dal.add_server(url, clerk, local_dir, name).await?;
     Ok(true)
 }
 
 #[tauri::command]
 pub async fn init_settings_options(
     state_mutex: State<'_, Mutex<Pool<Sqlite>>>,
-) -> Result<SettingsOptions, ()> {
+) -> Result<SettingsOptions, GlassyError> {
     let pool = state_mutex.lock().await;
 
     let output = sqlx::query("SELECT local_dir, debug_active FROM server WHERE active = 1")
-        .fetch_one(&*pool)
-        .await;
+        .fetch_optional(&*pool)
+        .await?;
 
     match output {
-        Ok(row) => {
+        Some(row) => {
             let result = SettingsOptions {
                 local_dir: row.get::<String, &str>("local_dir").to_string(),
                 debug_active: row.get::<i32, &str>("debug_active"),
             };
             Ok(result)
         }
-        Err(_err) => {
-            // TODO do something with error, and handle this case better lol
+        None => {
             Ok(SettingsOptions {
                 local_dir: "".to_string(),
                 debug_active: 0,
@@ -108,16 +96,19 @@ pub async fn set_local_dir(
     dir: String,
     move_files: bool,
     state_mutex: State<'_, Mutex<Pool<Sqlite>>>,
-) -> Result<bool, ()> {
+    watchers: State<'_, ProjectWatchers>,
+) -> Result<bool, GlassyError> {
     log::info!("setting local directory to {}", dir);
+    // project folders are about to move, so the watchers would be looking at stale paths
+    stop_watching_all(&watchers);
     log::info!("parent dir: {}", parent_dir);
     let pool = state_mutex.lock().await;
     let dal = DataAccessLayer::new(&pool);
-    let old_server_dir = get_server_dir(&pool).await.unwrap();
-    let _ = sqlx::query("UPDATE server SET local_dir = ? WHERE active = 1")
+    let old_server_dir = get_server_dir(&pool).await?;
+    sqlx::query("UPDATE server SET local_dir = ? WHERE active = 1")
         .bind(dir.clone())
         .execute(&*pool)
-        .await;
+        .await?;
 
     if move_files {
         log::info!("moving files...");
@@ -132,38 +123,52 @@ pub async fn set_local_dir(
         }
     }
 
-    let url = dal.get_active_server().await.unwrap();
-    if url == "" {
+    let url = dal.get_active_server().await?;
+    if url.is_empty() {
         log::warn!("could not obtain active server url");
         return Ok(false);
     }
 
     // clear file and project table
-    let _ = dal.clear_project_table(url).await;
-    let _ = dal.clear_file_table().await;    
+    dal.clear_project_table(url).await?;
+    dal.clear_file_table().await?;
     Ok(true)
 }
 
 #[tauri::command]
-pub async fn cmd_get_cache_setting(state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, ()> {
+pub async fn cmd_get_cache_setting(state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, GlassyError> {
     let pool = state_mutex.lock().await;
     return get_cache_setting(&pool).await;
 }
 
 #[tauri::command]
-pub async fn cmd_set_cache_setting(new_cache: bool, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, ()> {
+pub async fn cmd_set_cache_setting(new_cache: bool, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, GlassyError> {
     let pool = state_mutex.lock().await;
     let dal = DataAccessLayer::new(&pool);
 
     dal.update_cache_setting(new_cache).await
 }
 
-pub async fn get_cache_setting(pool: &Pool<Sqlite>) -> Result<bool, ()> {
-    let dal = DataAccessLayer::new(pool);
-    dal.get_cache_setting().await
+#[tauri::command]
+pub async fn cmd_get_cache_max_size(state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<u64, GlassyError> {
+    let pool = state_mutex.lock().await;
+    let dal = DataAccessLayer::new(&pool);
+
+    dal.get_cache_max_size().await
+}
+
+/// sets the maximum cache size in bytes, 0 for no limit. the cache is trimmed to the new size right away
+#[tauri::command]
+pub async fn cmd_set_cache_max_size(max_size: u64, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await;
+    let dal = DataAccessLayer::new(&pool);
+
+    let res = dal.set_cache_max_size(max_size).await?;
+    evict_cache(&pool).await?;
+    Ok(res)
 }
 
 #[tauri::command]
 pub fn is_dev_mode() -> bool {
-    return tauri::is_dev();
-}
\ No newline at end of file
+    tauri::is_dev()
+}
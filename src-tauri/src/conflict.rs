@@ -0,0 +1,42 @@
+use crate::jobs::enqueue_job;
+use glassy_core::conflict::keep_mine;
+use glassy_core::queue::Task;
+use glassy_core::types::GlassyError;
+use sqlx::{Pool, Sqlite};
+use tauri::{AppHandle, State};
+use tokio::sync::Mutex;
+
+/// keeps the local file; it uploads as an update on top of the latest commit
+#[tauri::command]
+pub async fn resolve_conflict_keep_mine(
+    pid: i32,
+    path: String,
+    state_mutex: State<'_, Mutex<Pool<Sqlite>>>,
+) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await;
+    keep_mine(pid, path, &pool).await
+}
+
+/// queues replacing the local file with the latest remote revision, after backing up the local file. returns the job's id
+#[tauri::command]
+pub async fn resolve_conflict_take_theirs(
+    pid: i32,
+    path: String,
+    user: String,
+    token: Option<String>,
+    app_handle: AppHandle,
+) -> Result<i64, GlassyError> {
+    enqueue_job(&app_handle, pid, user, token, Task::TakeTheirs { path }).await
+}
+
+/// queues saving the latest remote revision next to the local file as `name (commit N).ext`. returns the job's id
+#[tauri::command]
+pub async fn resolve_conflict_keep_both(
+    pid: i32,
+    path: String,
+    user: String,
+    token: Option<String>,
+    app_handle: AppHandle,
+) -> Result<i64, GlassyError> {
+    enqueue_job(&app_handle, pid, user, token, Task::KeepBoth { path }).await
+}
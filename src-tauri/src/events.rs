@@ -0,0 +1,44 @@
+use glassy_core::progress::{ProgressReporter, UploadProgress};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+/// a progress event, tagged with the job it is from since several jobs can run at once
+#[derive(Serialize, Clone)]
+struct JobEvent<T: Serialize + Clone> {
+    jid: i64,
+    data: T,
+}
+
+/// forwards a job's progress to the frontend as events
+pub struct AppReporter {
+    pub app_handle: AppHandle,
+    pub jid: i64,
+}
+
+impl AppReporter {
+    fn emit<T: Serialize + Clone>(&self, event: &str, data: T) {
+        let _ = self.app_handle.emit(event, JobEvent { jid: self.jid, data });
+    }
+}
+
+impl ProgressReporter for AppReporter {
+    fn file_done(&self, done: u32) {
+        self.emit("fileAction", done);
+    }
+
+    fn chunk_downloaded(&self, total: usize) {
+        self.emit("downloadedFile", total);
+    }
+
+    fn chunks_cached(&self) {
+        self.emit("cacheComplete", 4);
+    }
+
+    fn upload_progress(&self, progress: UploadProgress) {
+        self.emit("uploadProgress", progress);
+    }
+
+    fn files_skipped(&self, paths: &[String]) {
+        self.emit("filesSkipped", paths.to_vec());
+    }
+}
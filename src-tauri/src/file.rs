@@ -1,181 +1,100 @@
-use std::collections::HashSet;
-use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
+use glassy_core::dal::DataAccessLayer;
+use glassy_core::file::{
+    add_ignore_list_entry, list_directory, list_ignore_rules, preview_ignore_rule, remove_ignore_list_entry,
+    DirectorySummary, IgnoreMatch, IgnoreRule,
+};
+use glassy_core::open_files::find_open_files;
+use glassy_core::sparse::{add_sparse_pattern, remove_sparse_pattern};
+use glassy_core::sync::hash_dir;
+use glassy_core::types::GlassyError;
 use sqlx::{Pool, Sqlite};
 use tauri::State;
+use tokio::sync::Mutex;
 
-#[derive(sqlx::FromRow, Clone, Serialize, Deserialize)]
-
-pub struct FileSummary {
-    pub filepath: String,
-    pub change_type: i32,
-    pub in_fs: i32
-}
-
-#[derive(Clone, Serialize, Deserialize)]
-pub struct DirectorySummary {
-    pub files: Vec<FileSummary>,
-    pub folders: HashSet<String>
+#[tauri::command]
+pub async fn get_files(project_id: i32, directory: String, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<DirectorySummary, GlassyError> {
+    let pool = state_mutex.lock().await;
+    list_directory(project_id, directory, &pool).await
 }
 
-
+/// the rules from the project's .glassyignore and the ones added on this computer
 #[tauri::command]
-pub async fn get_files(project_id: i32, directory: String, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<DirectorySummary, ()> {
+pub async fn get_ignore_rules(pid: i32, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<Vec<IgnoreRule>, GlassyError> {
     let pool = state_mutex.lock().await;
-
-    // get files that have the path in the project
-    // only return files and folders that are in the directory
-    let all_files: Vec<FileSummary> = match sqlx::query_as("SELECT filepath, tracked_changetype as change_type, in_fs FROM file WHERE pid = $1 ORDER BY filepath")
-        .bind(project_id).fetch_all(&*pool)
-        .await {
-            Ok(files) => files,
-            Err(err) => {
-                log::error!("encountered error while querying db: {}", err);
-                Vec::<FileSummary>::new()
-            }
-    };
-    let mut files = Vec::<FileSummary>::new();
-    let mut folders = HashSet::<String>::new();
-    for file in all_files {
-        // skip if file doesnt include path
-        let rel_path = match file.filepath.strip_prefix(&directory) {
-            Some(path) => path,
-            None => {
-                continue;
-            }
-        };
-
-        if rel_path.contains('\\') {
-            let folder_name = rel_path.split_at(rel_path.find('\\').unwrap()).0;
-            folders.insert(folder_name.to_string() + "\\");
-        } else {
-            files.push(file);
-        }
-    }
-    return Ok(DirectorySummary{ folders, files})
+    list_ignore_rules(pid, &pool).await
 }
 
-pub async fn add_ignore_list_entry(project_id: i32, server_url: String, path: String, pool: &Pool<Sqlite>) -> Result<bool, ()> {
-    match sqlx::query("INSERT INTO projectignorelist(pid, url, path) VALUES ($1, $2, $3);")
-        .bind(project_id)
-        .bind(server_url)
-        .bind(path)
-        .execute(pool)
-        .await {
-            Ok(res) => {
-                // TODO check res, lmao
-                Ok(true)
-            },
-            Err(err) => {
-                // TODO
-                Ok(false)
-            }
+/// returns false if the rule is invalid or already added
+#[tauri::command]
+pub async fn add_ignore_rule(pid: i32, rule: String, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await.clone();
+    let dal = DataAccessLayer::new(&pool);
+    let url = dal.get_active_server().await?;
+    if !add_ignore_list_entry(pid, url, rule, &pool).await? {
+        return Ok(false);
     }
+    rehash_project(pid, &pool).await?;
+    Ok(true)
 }
 
-pub async fn remove_ignore_list_entry(project_id: i32, server_url: String, path: String, pool: &Pool<Sqlite>) -> Result<bool, ()> {
-    match sqlx::query("DELETE FROM projectignorelist WHERE pid = $1 AND url = $2 AND path = $3;")
-        .bind(project_id)
-        .bind(server_url)
-        .bind(path)
-        .execute(pool)
-        .await {
-            Ok(res) => {
-                // TODO check res, lmao
-                Ok(true)
-            },
-            Err(err) => {
-                // TODO
-                Ok(false)
-            }
+#[tauri::command]
+pub async fn remove_ignore_rule(pid: i32, rule: String, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await.clone();
+    let dal = DataAccessLayer::new(&pool);
+    let url = dal.get_active_server().await?;
+    if !remove_ignore_list_entry(pid, url, rule, &pool).await? {
+        return Ok(false);
     }
+    rehash_project(pid, &pool).await?;
+    Ok(true)
 }
 
-/* */
-pub async fn get_ignore_list(project_id: i32, server_url: String, pool: &Pool<Sqlite>) -> Result<Vec<String>, ()> {
-    let output: Vec<String> = match sqlx::query_scalar("SELECT path FROM projectignorelist WHERE pid = $1 AND url = $2;")
-        .bind(project_id)
-        .bind(server_url)
-        .fetch_all(pool)
-        .await {
-            Ok(res) => res,
-            Err(err) => {
-                // TODO print and return error, not empty list
-                Vec::<String>::new()
-            }
-    };
-    Ok(output)
+/// the project's files that `rule` would match
+#[tauri::command]
+pub async fn cmd_preview_ignore_rule(pid: i32, rule: String, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<Vec<IgnoreMatch>, GlassyError> {
+    let pool = state_mutex.lock().await.clone();
+    preview_ignore_rule(pid, rule, &pool).await
 }
 
-// check if file is in the ignore list for a given project
-pub async fn should_file_be_ignored(project_id: i32, server_url: String, file: String, pool: &Pool<Sqlite>) -> Result<bool, ()> {
-    let ignore_list = get_ignore_list(project_id, server_url, pool).await.unwrap();
-    // TODO get the parent path from the file (basically the path of its containing folder)
-    
-    // TODO check if the parent path is a substring of any of the paths in the ignore list
-    // break this into a function so that can be unit tested
-    Ok(true)
+/// the folders and globs of the project that are checked out, empty if all of it is
+#[tauri::command]
+pub async fn get_sparse_checkout(pid: i32, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<Vec<String>, GlassyError> {
+    let pool = state_mutex.lock().await;
+    let dal = DataAccessLayer::new(&pool);
+    dal.get_sparse_rules(pid).await
 }
 
-// convert forward slashes to backward slashes or vice versa
-pub fn translate_filepath(path: &String, to_unix: bool) -> String {
-    let out;
-    if to_unix { // backward slashes to forwards
-        out = path.replace('\\', "/");
-    } else { // forward slashes to backwards
-        out = path.replace("/", "\\");
+/// returns false if the pattern is invalid or already checked out
+#[tauri::command]
+pub async fn add_to_sparse_checkout(pid: i32, pattern: String, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await.clone();
+    if !add_sparse_pattern(pid, pattern, &pool).await? {
+        return Ok(false);
     }
-    out
+    rehash_project(pid, &pool).await?;
+    Ok(true)
 }
 
-pub fn sep() -> char {
-    #[cfg(target_os = "windows")]
-    {
-        '\\'
-    }
-    #[cfg(target_os = "linux")]
-    {
-        '/'
+#[tauri::command]
+pub async fn remove_from_sparse_checkout(pid: i32, pattern: String, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await.clone();
+    if !remove_sparse_pattern(pid, pattern, &pool).await? {
+        return Ok(false);
     }
+    rehash_project(pid, &pool).await?;
+    Ok(true)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    
-    struct TestCaseTranslateFilePath {
-        set_path: String,
-        set_to_unix: bool,
-        exp_path: String
-    }
-
-    #[test]
-    fn test_translate_filepath() {
-        // initialize test cases
-        let test_cases: Vec<TestCaseTranslateFilePath> = vec![
-            TestCaseTranslateFilePath { set_path: "path/to/file".to_string(), set_to_unix: false, exp_path: "path\\to\\file".to_string()},
-            TestCaseTranslateFilePath { set_path: "path/to/file".to_string(), set_to_unix: true, exp_path: "path/to/file".to_string()},
-            TestCaseTranslateFilePath { set_path: "path\\to\\file".to_string(), set_to_unix: true, exp_path: "path/to/file".to_string()},
-            TestCaseTranslateFilePath { set_path: "path\\to\\file".to_string(), set_to_unix: false, exp_path: "path\\to\\file".to_string()}
-        ];
-        
-        // run function under test and validate results
-        for case in test_cases {
-            let ret = translate_filepath(&case.set_path, case.set_to_unix);
-            assert_eq!(ret, case.exp_path);
-        }
-    }
-
-    #[test]
-    fn test_sep() {
-        #[cfg(target_os = "windows")]
-        {
-            assert_eq!('\\', sep());
-        }
+/// which of `filepaths` are open in another program. downloads and resets leave them alone
+#[tauri::command]
+pub async fn cmd_find_open_files(pid: i32, filepaths: Vec<String>, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<Vec<String>, GlassyError> {
+    let pool = state_mutex.lock().await.clone();
+    find_open_files(pid, &filepaths, &pool).await
+}
 
-        #[cfg(target_os = "linux")]
-        {
-            assert_eq!('/', sep());
-        }
-    }
-}
\ No newline at end of file
+// the uploads list follows the new rules right away. unchanged files aren't hashed again
+async fn rehash_project(pid: i32, pool: &Pool<Sqlite>) -> Result<(), GlassyError> {
+    let project_dir = DataAccessLayer::new(pool).get_project_dir(pid).await?;
+    hash_dir(pid, project_dir.into(), pool, false).await;
+    Ok(())
+}
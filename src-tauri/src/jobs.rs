@@ -0,0 +1,189 @@
+use crate::events::AppReporter;
+use crate::watcher::start_watching;
+use glassy_core::config::Token;
+use glassy_core::dal::DataAccessLayer;
+use glassy_core::job::{JobState, Jobs};
+use glassy_core::queue::{enqueue, run_job, take_jobs, JobOutcome, Task};
+use glassy_core::types::{GlassyError, QueuedJob};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::{Mutex, Notify};
+
+// how many of the latest jobs list_jobs returns
+const JOB_LIST_LIMIT: u32 = 50;
+
+/// runs queued jobs in the background, see glassy_core::queue
+#[derive(Default)]
+pub struct JobQueue {
+    wake: Notify,
+    // the user's latest token, jobs that talk to the server wait for it.
+    // running jobs share it, so they pick up refreshed tokens
+    token: Token,
+    // pid of every running job, by job id
+    running: std::sync::Mutex<HashMap<i64, i32>>,
+}
+
+impl JobQueue {
+    fn running_pids(&self) -> Vec<i32> {
+        match self.running.lock() {
+            Ok(running) => running.values().cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn set_running(&self, jid: i64, pid: Option<i32>) {
+        if let Ok(mut running) = self.running.lock() {
+            match pid {
+                Some(pid) => running.insert(jid, pid),
+                None => running.remove(&jid),
+            };
+        }
+    }
+}
+
+/// queues a job and returns its id right away. the frontend hears how it ended from the jobFinished event
+pub async fn enqueue_job(app_handle: &AppHandle, pid: i32, user: String, token: Option<String>, task: Task) -> Result<i64, GlassyError> {
+    let queue = app_handle.state::<JobQueue>();
+    if token.is_some() {
+        queue.token.set(token);
+    }
+    let pool = app_handle.state::<Mutex<Pool<Sqlite>>>().lock().await.clone();
+    let jid = enqueue(pid, user, task, &pool).await?;
+    queue.wake.notify_one();
+    Ok(jid)
+}
+
+/// takes jobs off the queue for as long as the app runs
+pub fn start_worker(app_handle: AppHandle, pool: Pool<Sqlite>) {
+    tauri::async_runtime::spawn(async move {
+        let queue = app_handle.state::<JobQueue>();
+        loop {
+            let concurrency = DataAccessLayer::new(&pool).get_job_concurrency().await.unwrap_or(1);
+            let running = queue.running_pids();
+            let slots = (concurrency as usize).saturating_sub(running.len());
+            match take_jobs(&pool, &running, slots, queue.token.get().is_some()).await {
+                Ok(jobs) => {
+                    for job in jobs {
+                        spawn_job(&app_handle, pool.clone(), job);
+                    }
+                }
+                Err(err) => log::error!("couldn't take jobs off the queue: {}", err),
+            }
+            // wait until a job is queued or finished
+            queue.wake.notified().await;
+        }
+    });
+}
+
+fn spawn_job(app_handle: &AppHandle, pool: Pool<Sqlite>, job: QueuedJob) {
+    app_handle.state::<JobQueue>().set_running(job.jid, Some(job.pid));
+    let control = app_handle.state::<Jobs>().register(job.jid);
+    let _ = app_handle.emit("jobStarted", &job);
+
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let queue = app_handle.state::<JobQueue>();
+        let outcome = run_job(&job, queue.token.clone(), &pool, &AppReporter { app_handle: app_handle.clone(), jid: job.jid }, &control).await;
+
+        // keep the file table up to date until the next sync
+        if job.kind == "sync" && outcome.error.is_none() {
+            if let Ok(project_dir) = DataAccessLayer::new(&pool).get_project_dir(job.pid).await {
+                start_watching(job.pid, project_dir.into(), &app_handle);
+            }
+        }
+
+        app_handle.state::<Jobs>().finish(job.jid);
+        queue.set_running(job.jid, None);
+        let _ = app_handle.emit("jobFinished", outcome);
+        queue.wake.notify_one();
+    });
+}
+
+/// gives the queue the user's latest token, so jobs waiting for it can run
+#[tauri::command]
+pub async fn resume_jobs(token: Option<String>, queue: State<'_, JobQueue>) -> Result<(), GlassyError> {
+    queue.token.set(token);
+    queue.wake.notify_one();
+    Ok(())
+}
+
+/// the latest jobs, newest first
+#[tauri::command]
+pub async fn list_jobs(state_mutex: State<'_, Mutex<Pool<Sqlite>>>, jobs: State<'_, Jobs>) -> Result<Vec<QueuedJob>, GlassyError> {
+    let pool = state_mutex.lock().await;
+    let dal = DataAccessLayer::new(&pool);
+    let url = dal.get_active_server().await?;
+    let mut output = dal.get_jobs(url, JOB_LIST_LIMIT).await?;
+    for job in output.iter_mut() {
+        if jobs.get(job.jid).is_some_and(|control| control.state() == JobState::Paused) {
+            job.state = "paused".to_string();
+        }
+    }
+    Ok(output)
+}
+
+/// the job waits at its next safe point until it is resumed
+#[tauri::command]
+pub async fn pause_job(id: i64, jobs: State<'_, Jobs>) -> Result<bool, GlassyError> {
+    match jobs.get(id) {
+        Some(job) => Ok(job.pause()),
+        None => Err(GlassyError::NotFound(format!("job {}", id))),
+    }
+}
+
+#[tauri::command]
+pub async fn resume_job(id: i64, jobs: State<'_, Jobs>) -> Result<bool, GlassyError> {
+    match jobs.get(id) {
+        Some(job) => Ok(job.resume()),
+        None => Err(GlassyError::NotFound(format!("job {}", id))),
+    }
+}
+
+/// a running job stops at its next safe point, a queued job is taken off the queue
+#[tauri::command]
+pub async fn cancel_job(id: i64, app_handle: AppHandle) -> Result<bool, GlassyError> {
+    if let Some(job) = app_handle.state::<Jobs>().get(id) {
+        job.cancel();
+        return Ok(true);
+    }
+
+    let pool = app_handle.state::<Mutex<Pool<Sqlite>>>().lock().await.clone();
+    let dal = DataAccessLayer::new(&pool);
+    let job = match dal.get_job(id).await? {
+        Some(job) => job,
+        None => return Err(GlassyError::NotFound(format!("job {}", id))),
+    };
+    if !dal.cancel_queued_job(id).await? {
+        return Ok(false);
+    }
+    let outcome = JobOutcome {
+        id,
+        pid: job.pid,
+        kind: job.kind,
+        result: None,
+        error: serde_json::to_value(GlassyError::Cancelled).ok(),
+    };
+    let _ = app_handle.emit("jobFinished", outcome);
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn cmd_get_job_concurrency(state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<u32, GlassyError> {
+    let pool = state_mutex.lock().await;
+    let dal = DataAccessLayer::new(&pool);
+    dal.get_job_concurrency().await
+}
+
+#[tauri::command]
+pub async fn cmd_set_job_concurrency(
+    concurrency: u32,
+    state_mutex: State<'_, Mutex<Pool<Sqlite>>>,
+    queue: State<'_, JobQueue>,
+) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await;
+    let dal = DataAccessLayer::new(&pool);
+    let res = dal.set_job_concurrency(concurrency).await?;
+    queue.wake.notify_one();
+    Ok(res)
+}
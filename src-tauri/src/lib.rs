@@ -0,0 +1,240 @@
+pub mod config;
+pub mod download;
+pub mod file;
+pub mod reset;
+pub mod sync;
+pub mod upload;
+pub mod util;
+pub mod commands;
+pub mod watcher;
+pub mod conflict;
+pub mod backup;
+pub mod trash;
+pub mod events;
+pub mod jobs;
+pub mod lock;
+
+use crate::config::*;
+use conflict::{resolve_conflict_keep_both, resolve_conflict_keep_mine, resolve_conflict_take_theirs};
+use backup::{cmd_get_backup_retention, cmd_set_backup_retention, delete_backup, list_backups, restore_backup};
+use trash::{cmd_get_trash_retention, cmd_set_trash_retention, list_trash, purge_trash, restore_trash_entry};
+use download::{download_files, download_single_file, download_with_references};
+use log::{debug, error, info, warn};
+use reset::reset_files;
+use sqlx::{sqlite::SqliteConnectOptions, SqlitePool};
+use std::fs;
+use std::path::Path;
+use util::{cmd_delete_cache, get_cache_size, open_app_data_dir, open_log_dir};
+use sync::{
+    get_conflicts, get_downloads, get_project_name, get_uploads, sync_changes, update_project_info,
+};
+use commands::project::{open_project_dir, get_local_projects, clear_file_table, delete_project};
+use file::{
+    add_ignore_rule, add_to_sparse_checkout, cmd_find_open_files, cmd_preview_ignore_rule, get_files, get_ignore_rules, get_sparse_checkout, remove_from_sparse_checkout,
+    remove_ignore_rule,
+};
+use glassy_core::job::Jobs;
+use lock::{cmd_get_readonly_checkout, cmd_refresh_locks, cmd_set_readonly_checkout, get_locks, lock_files, unlock_files};
+use glassy_core::types::GlassyError;
+use tauri::{Emitter, Manager};
+use tauri_plugin_updater::UpdaterExt;
+use tokio::sync::Mutex;
+use upload::{discard_upload, get_unfinished_upload, resume_upload, upload_and_commit};
+use watcher::{unwatch_project, watch_project, ProjectWatchers};
+use jobs::{
+    cancel_job, cmd_get_job_concurrency, cmd_set_job_concurrency, list_jobs, pause_job, resume_job, resume_jobs, start_worker, JobQueue,
+};
+
+pub fn run() {
+    tauri::Builder::default()
+        .invoke_handler(tauri::generate_handler![
+            sync_changes,
+            set_local_dir,
+            set_debug,
+            get_server_url,
+            get_server_clerk,
+            add_server,
+            init_settings_options,
+            get_server_name,
+            update_project_info,
+            get_uploads,
+            get_project_name,
+            open_project_dir,
+            upload_and_commit,
+            get_unfinished_upload,
+            resume_upload,
+            discard_upload,
+            get_local_projects,
+            get_downloads,
+            get_conflicts,
+            resolve_conflict_keep_mine,
+            resolve_conflict_take_theirs,
+            resolve_conflict_keep_both,
+            list_backups,
+            restore_backup,
+            delete_backup,
+            cmd_get_backup_retention,
+            cmd_set_backup_retention,
+            list_trash,
+            restore_trash_entry,
+            purge_trash,
+            cmd_get_trash_retention,
+            cmd_set_trash_retention,
+            download_files,
+            reset_files,
+            list_jobs,
+            pause_job,
+            resume_job,
+            cancel_job,
+            resume_jobs,
+            cmd_get_job_concurrency,
+            cmd_set_job_concurrency,
+            check_update,
+            restart,
+            cmd_delete_cache,
+            get_cache_size,
+            open_log_dir,
+            open_app_data_dir,
+            download_single_file,
+            download_with_references,
+            cmd_get_cache_setting,
+            cmd_set_cache_setting,
+            cmd_get_cache_max_size,
+            cmd_set_cache_max_size,
+            get_files,
+            get_ignore_rules,
+            add_ignore_rule,
+            remove_ignore_rule,
+            cmd_preview_ignore_rule,
+            get_sparse_checkout,
+            add_to_sparse_checkout,
+            remove_from_sparse_checkout,
+            cmd_find_open_files,
+            lock_files,
+            unlock_files,
+            get_locks,
+            cmd_refresh_locks,
+            cmd_get_readonly_checkout,
+            cmd_set_readonly_checkout,
+            is_dev_mode,
+            clear_file_table,
+            delete_project,
+            watch_project,
+            unwatch_project,
+            dev
+        ])
+        .plugin(
+            tauri_plugin_log::Builder::new()
+                .level(log::LevelFilter::Info)
+                .target(tauri_plugin_log::Target::new(
+                    tauri_plugin_log::TargetKind::LogDir {
+                        file_name: Some("glassy.log".to_string()),
+                    },
+                ))
+                .max_file_size(50_000 /* bytes */)
+                .rotation_strategy(tauri_plugin_log::RotationStrategy::KeepAll)
+                .build(),
+        )
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .manage(ProjectWatchers::default())
+        .manage(Jobs::default())
+        .manage(JobQueue::default())
+        .setup(|app| {
+            //let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                //let _ = update(handle).await;
+            });
+            tauri::async_runtime::block_on(async move {
+                let _ = fs::create_dir_all(app.path().app_data_dir().unwrap());
+                let db_path = app.path().app_data_dir().unwrap().join("glassypdm.db");
+                log::debug!("db {}", db_path.display());
+                let options = SqliteConnectOptions::new()
+                    .filename(db_path)
+                    .create_if_missing(true);
+                let pool = SqlitePool::connect_with(options).await;
+                match pool {
+                    Ok(db) => {
+                        let res = glassy_core::MIGRATOR.run(&db).await;
+                        match res {
+                            Ok(()) => {}
+                            Err(err) => {
+                                error!("{}", err);
+                            }
+                        }
+                        // finish or undo downloads that were interrupted last time
+                        glassy_core::journal::recover_journals(&db).await;
+                        // requeue jobs that were running when the app closed
+                        if let Err(err) = glassy_core::queue::recover_jobs(&db).await {
+                            error!("couldn't recover jobs: {}", err);
+                        }
+                        app.manage(Mutex::new(db.clone()));
+                        start_worker(app.handle().clone(), db.clone());
+                    }
+                    Err(e) => {
+                        // TODO what errors could we get? maybe panic and exit tauri
+                        log::error!("db something wrong with connection? {}", e);
+                    }
+                }
+                log::info!("done initializing");
+            });
+            Ok(())
+        })
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
+
+async fn update(app: tauri::AppHandle) -> tauri::Result<()> {
+    let update = app.updater().unwrap().check().await;
+    match update {
+        Ok(up) => {
+            if up.is_some() {
+                let mut downloaded = 0;
+
+                // alternatively we could also call update.download() and update.install() separately
+                let _ = up
+                    .unwrap()
+                    .download_and_install(
+                        |chunk_length, content_length| {
+                            downloaded += chunk_length;
+                            info!("downloaded {downloaded} from {content_length:?}");
+                        },
+                        || {
+                            info!("download finished");
+                        },
+                    )
+                    .await;
+                info!("updates installed");
+                app.restart();
+            } else {
+                debug!("no update available");
+            }
+        }
+        Err(err) => {
+            warn!("error! {}", err);
+            app.emit("update", 0).unwrap();
+        }
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn check_update(app: tauri::AppHandle) -> Result<bool, GlassyError> {
+    let _ = update(app).await;
+    return Ok(true);
+}
+
+#[tauri::command]
+async fn restart(app: tauri::AppHandle) -> tauri::Result<()> {
+    Ok(())
+}
+
+#[tauri::command]
+async fn dev(app: tauri::AppHandle) {
+    // these are the same (they both return true on winblows)
+    println!("does path exist? {}", Path::new("D:\\glassyPDM\\Sun Devil Motorsports\\SDM-25\\Drivetrain\\Parts\\Diff Mounts\\Jack bar.SLDPRT").exists());
+    println!("does path exist? {}", Path::new("D:\\glassyPDM\\Sun Devil Motorsports\\SDM-25\\Drivetrain\\Parts\\diff mounts\\jack bar.SLDPRT").exists());
+}
\ No newline at end of file
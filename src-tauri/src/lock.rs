@@ -0,0 +1,86 @@
+use glassy_core::config::Config;
+use glassy_core::dal::DataAccessLayer;
+use glassy_core::lock::{acquire_locks, apply_lock_permissions, clear_lock_permissions, refresh_locks, release_locks};
+use glassy_core::network::GlassyClient;
+use glassy_core::types::{FileLock, GlassyError};
+use sqlx::{Pool, Sqlite};
+use tauri::State;
+use tokio::sync::Mutex;
+
+/// checks out `paths`, returns the locks someone else already has on some of them
+#[tauri::command]
+pub async fn lock_files(
+    pid: i32,
+    paths: Vec<String>,
+    user: String,
+    token: Option<String>,
+    state_mutex: State<'_, Mutex<Pool<Sqlite>>>,
+) -> Result<Vec<FileLock>, GlassyError> {
+    let pool = state_mutex.lock().await.clone();
+    let config = Config::load(user, &pool).await?.with_token(token);
+    acquire_locks(pid, &paths, &GlassyClient::new(&config), &pool).await
+}
+
+/// checks in `paths`. `force` breaks other people's locks, if the server allows it
+#[tauri::command]
+pub async fn unlock_files(
+    pid: i32,
+    paths: Vec<String>,
+    force: bool,
+    user: String,
+    token: Option<String>,
+    state_mutex: State<'_, Mutex<Pool<Sqlite>>>,
+) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await.clone();
+    let config = Config::load(user, &pool).await?.with_token(token);
+    release_locks(pid, &paths, force, &GlassyClient::new(&config), &pool).await?;
+    Ok(true)
+}
+
+/// the project's locks as of the last time they were fetched
+#[tauri::command]
+pub async fn get_locks(pid: i32, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<Vec<FileLock>, GlassyError> {
+    let pool = state_mutex.lock().await;
+    let dal = DataAccessLayer::new(&pool);
+    dal.get_locks(pid).await
+}
+
+#[tauri::command]
+pub async fn cmd_refresh_locks(
+    pid: i32,
+    user: String,
+    token: Option<String>,
+    state_mutex: State<'_, Mutex<Pool<Sqlite>>>,
+) -> Result<Vec<FileLock>, GlassyError> {
+    let pool = state_mutex.lock().await.clone();
+    let config = Config::load(user, &pool).await?.with_token(token);
+    refresh_locks(pid, &GlassyClient::new(&config), &pool).await
+}
+
+#[tauri::command]
+pub async fn cmd_get_readonly_checkout(pid: i32, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await;
+    let dal = DataAccessLayer::new(&pool);
+    dal.get_readonly_checkout(pid).await
+}
+
+/// files that aren't checked out are made read-only right away, or writable again when this is turned off
+#[tauri::command]
+pub async fn cmd_set_readonly_checkout(
+    pid: i32,
+    readonly: bool,
+    user: String,
+    state_mutex: State<'_, Mutex<Pool<Sqlite>>>,
+) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await.clone();
+    let dal = DataAccessLayer::new(&pool);
+    if !dal.set_readonly_checkout(pid, readonly).await? {
+        return Ok(false);
+    }
+    if readonly {
+        apply_lock_permissions(pid, &user, &pool).await;
+    } else {
+        clear_lock_permissions(pid, &pool).await;
+    }
+    Ok(true)
+}
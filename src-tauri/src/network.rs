@@ -1,7 +0,0 @@
-
-
-// function for uploading chunks of a file
-
-// function for requesting a list of S3 URLs for a list of files
-
-// function for downloading a bunch of things
\ No newline at end of file
@@ -0,0 +1,48 @@
+use glassy_core::dal::DataAccessLayer;
+use glassy_core::trash::{prune_trash, purge, restore_from_trash};
+use glassy_core::types::{GlassyError, TrashEntry};
+use sqlx::{Pool, Sqlite};
+use tauri::State;
+use tokio::sync::Mutex;
+
+#[tauri::command]
+pub async fn list_trash(pid: Option<i32>, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<Vec<TrashEntry>, GlassyError> {
+    let pool = state_mutex.lock().await;
+    let dal = DataAccessLayer::new(&pool);
+    let url = dal.get_active_server().await?;
+
+    dal.get_trash_entries(url, pid).await
+}
+
+/// moves a trashed file back to where it was in its project
+#[tauri::command]
+pub async fn restore_trash_entry(tid: i64, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await;
+    restore_from_trash(tid, &pool).await
+}
+
+/// permanently deletes a trashed file, or everything in the trash if tid is None
+#[tauri::command]
+pub async fn purge_trash(tid: Option<i64>, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await;
+    purge(tid, &pool).await
+}
+
+#[tauri::command]
+pub async fn cmd_get_trash_retention(state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<u32, GlassyError> {
+    let pool = state_mutex.lock().await;
+    let dal = DataAccessLayer::new(&pool);
+
+    dal.get_trash_retention_days().await
+}
+
+/// sets how many days trashed files are kept for, 0 to keep them until they are purged
+#[tauri::command]
+pub async fn cmd_set_trash_retention(days: u32, state_mutex: State<'_, Mutex<Pool<Sqlite>>>) -> Result<bool, GlassyError> {
+    let pool = state_mutex.lock().await;
+    let dal = DataAccessLayer::new(&pool);
+
+    let res = dal.set_trash_retention_days(days).await;
+    prune_trash(&pool).await;
+    res
+}
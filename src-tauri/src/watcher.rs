@@ -0,0 +1,124 @@
+use glassy_core::dal::DataAccessLayer;
+use glassy_core::sync::hash_paths;
+use glassy_core::types::GlassyError;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use sqlx::{Pool, Sqlite};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::mpsc;
+use tokio::sync::Mutex;
+
+// how long the project folder has to be quiet before we re-hash what changed
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(750);
+
+/// one filesystem watcher per project, keyed by pid
+#[derive(Default)]
+pub struct ProjectWatchers {
+    watchers: std::sync::Mutex<HashMap<i32, Debouncer<RecommendedWatcher>>>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct ProjectFilesChanged {
+    pub pid: i32,
+    pub num_paths: usize,
+}
+
+#[tauri::command]
+pub async fn watch_project(pid: i32, app_handle: AppHandle) -> Result<bool, GlassyError> {
+    let project_dir = {
+        let state_mutex = app_handle.state::<Mutex<Pool<Sqlite>>>();
+        let pool = state_mutex.lock().await;
+        let dal = DataAccessLayer::new(&pool);
+        dal.get_project_dir(pid).await?
+    };
+    if project_dir.is_empty() {
+        log::warn!("could not get project directory to watch for project {}", pid);
+        return Ok(false);
+    }
+
+    Ok(start_watching(pid, PathBuf::from(project_dir), &app_handle))
+}
+
+#[tauri::command]
+pub fn unwatch_project(pid: i32, watchers: State<'_, ProjectWatchers>) -> bool {
+    stop_watching(pid, &watchers)
+}
+
+/// starts watching a project directory, unless it is already being watched
+pub fn start_watching(pid: i32, project_dir: PathBuf, app_handle: &AppHandle) -> bool {
+    let watchers = app_handle.state::<ProjectWatchers>();
+    let mut watchers = match watchers.watchers.lock() {
+        Ok(watchers) => watchers,
+        Err(_) => {
+            log::error!("couldn't watch project {}, the watcher list is poisoned", pid);
+            return false;
+        }
+    };
+    if watchers.contains_key(&pid) {
+        return true;
+    }
+
+    let (tx, rx) = mpsc::unbounded_channel::<Vec<PathBuf>>();
+    let debouncer = new_debouncer(DEBOUNCE_TIMEOUT, move |res: DebounceEventResult| match res {
+        Ok(events) => {
+            let paths = events.into_iter().map(|event| event.path).collect();
+            let _ = tx.send(paths);
+        }
+        Err(err) => {
+            log::warn!("file watcher error: {}", err);
+        }
+    });
+    let mut debouncer = match debouncer {
+        Ok(d) => d,
+        Err(err) => {
+            log::error!("couldn't create file watcher for project {}: {}", pid, err);
+            return false;
+        }
+    };
+    if let Err(err) = debouncer.watcher().watch(&project_dir, RecursiveMode::Recursive) {
+        log::error!("couldn't watch {}: {}", project_dir.display(), err);
+        return false;
+    }
+
+    log::info!("watching project {} at {}", pid, project_dir.display());
+    watchers.insert(pid, debouncer);
+    tauri::async_runtime::spawn(handle_events(pid, project_dir, rx, app_handle.clone()));
+    true
+}
+
+pub fn stop_watching(pid: i32, watchers: &ProjectWatchers) -> bool {
+    // dropping the debouncer stops the watcher and closes the event channel
+    let removed = watchers.watchers.lock().map(|mut watchers| watchers.remove(&pid).is_some()).unwrap_or(false);
+    if removed {
+        log::info!("stopped watching project {}", pid);
+    }
+    removed
+}
+
+pub fn stop_watching_all(watchers: &ProjectWatchers) {
+    if let Ok(mut watchers) = watchers.watchers.lock() {
+        watchers.clear();
+    }
+}
+
+async fn handle_events(
+    pid: i32,
+    project_dir: PathBuf,
+    mut rx: mpsc::UnboundedReceiver<Vec<PathBuf>>,
+    app_handle: AppHandle,
+) {
+    while let Some(paths) = rx.recv().await {
+        // the debouncer can report the same path more than once
+        let paths: Vec<PathBuf> = paths.into_iter().collect::<HashSet<_>>().into_iter().collect();
+        let num_paths = paths.len();
+        log::debug!("{} paths changed in project {}", num_paths, pid);
+        // hashing a large copy can take a while, so don't hold the pool lock for it
+        let pool = app_handle.state::<Mutex<Pool<Sqlite>>>().lock().await.clone();
+        hash_paths(pid, &project_dir, paths, &pool).await;
+        let _ = app_handle.emit("projectFilesChanged", ProjectFilesChanged { pid, num_paths });
+    }
+    log::debug!("watcher for project {} closed", pid);
+}
@@ -14,7 +14,7 @@ interface DFRButtonProps {
 }
 
 function DownloadFileRevisionButton(props: DFRButtonProps) {
-    const { userId } = useAuth();
+    const { getToken, userId } = useAuth();
     const [downloading, setDownloading] = useState(false)
     const { toast } = useToast();
 
@@ -53,6 +53,7 @@ function DownloadFileRevisionButton(props: DFRButtonProps) {
             path: props.path,
             commitId: props.commitId,
             userId: userId,
+            token: await getToken(),
             downloadPath: downloadPath
         })
         if(!res) {
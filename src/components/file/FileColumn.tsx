@@ -8,6 +8,8 @@ export interface File {
   size: number;
   hash: string;
   commit_id: number;
+  old_filepath?: string;
+  references?: string[]; // new or changed files this one needs, only set for uploads
 }
 
 export const columns: ColumnDef<File>[] = [
@@ -60,6 +62,10 @@ export const columns: ColumnDef<File>[] = [
           color = "text-red-400";
           symbol = "(-)";
           break;
+        case 4:
+          color = "text-yellow-200";
+          symbol = "(->)";
+          break;
         default:
           symbol = "(?)";
           break;
@@ -67,7 +73,7 @@ export const columns: ColumnDef<File>[] = [
       return (
         <div className="flex flex-row items-center space-x-2 w-[525px]">
           <div className={cn(color, "text-xs text-wrap break-all w-full")}>
-            {symbol} {file.filepath}
+            {symbol} {file.old_filepath ? `${file.old_filepath} -> ` : ""}{file.filepath}
           </div>
         </div>
       );
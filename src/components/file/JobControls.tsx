@@ -0,0 +1,72 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { useEffect, useState } from "react";
+import { Button } from "../ui/button";
+
+interface QueuedJob {
+    jid: number
+    pid: number
+    kind: string
+    state: "queued" | "running" | "paused" | "done" | "failed" | "cancelled"
+}
+
+interface JobControlsProps {
+    projectId: number
+}
+
+// pause/resume and cancel buttons for the project's running download, reset or upload
+function JobControls(props: JobControlsProps) {
+    const [job, setJob] = useState<QueuedJob | null>(null);
+
+    useEffect(() => {
+        // the job could have started before this page was opened
+        invoke("list_jobs").then((jobs: any) => {
+            const running = (jobs as QueuedJob[]).find((j) => j.pid == props.projectId && (j.state == "running" || j.state == "paused"));
+            setJob((current) => current ?? running ?? null);
+        });
+        const unlistenStarted = listen("jobStarted", (event: any) => {
+            if (event.payload.pid == props.projectId) {
+                setJob(event.payload);
+            }
+        });
+        const unlistenFinished = listen("jobFinished", (event: any) => {
+            setJob((current) => current?.jid == event.payload.id ? null : current);
+        });
+        return () => {
+            unlistenStarted.then((f) => f());
+            unlistenFinished.then((f) => f());
+        };
+    }, [props.projectId]);
+
+    if (job == null) {
+        return <></>;
+    }
+
+    async function togglePause() {
+        if (job == null) return;
+        const paused = job.state == "paused";
+        const changed: boolean = await invoke(paused ? "resume_job" : "pause_job", { id: job.jid });
+        if (changed) {
+            setJob({ ...job, state: paused ? "running" : "paused" });
+        }
+    }
+
+    async function cancel() {
+        if (job == null) return;
+        await invoke("cancel_job", { id: job.jid });
+        setJob({ ...job, state: "cancelled" });
+    }
+
+    return (
+        <div className="flex space-x-2 pl-2">
+            <Button variant={"outline"} disabled={job.state == "cancelled"} onClick={togglePause}>
+                {job.state == "paused" ? "Resume" : "Pause"}
+            </Button>
+            <Button variant={"destructive"} disabled={job.state == "cancelled"} onClick={cancel}>
+                {job.state == "cancelled" ? "Cancelling..." : "Cancel"}
+            </Button>
+        </div>
+    );
+}
+
+export default JobControls;
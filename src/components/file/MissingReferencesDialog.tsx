@@ -0,0 +1,45 @@
+import {
+    AlertDialog,
+    AlertDialogAction,
+    AlertDialogCancel,
+    AlertDialogContent,
+    AlertDialogDescription,
+    AlertDialogFooter,
+    AlertDialogHeader,
+    AlertDialogTitle,
+} from "../ui/alert-dialog";
+import { ScrollArea } from "../ui/scroll-area";
+
+interface MissingReferencesDialogProps {
+    files: string[] // new or changed files the selected ones need, the dialog is hidden while this is empty
+    onSelect: () => void
+    onUpload: () => void
+    onCancel: () => void
+}
+
+// lists new or changed files that selected assemblies use but that aren't selected, so they aren't left out of an upload
+function MissingReferencesDialog(props: MissingReferencesDialogProps) {
+    return (
+        <AlertDialog open={props.files.length > 0}>
+            <AlertDialogContent>
+                <AlertDialogHeader>
+                    <AlertDialogTitle>{props.files.length} files are missing from this upload</AlertDialogTitle>
+                    <AlertDialogDescription>
+                        Files you are uploading use these files, which are new or changed but not selected.
+                        Without them, everyone else will get broken assemblies.
+                    </AlertDialogDescription>
+                </AlertDialogHeader>
+                <ScrollArea className="max-h-48 rounded-md border p-2 text-sm">
+                    {props.files.map((file) => <p key={file}>{file}</p>)}
+                </ScrollArea>
+                <AlertDialogFooter>
+                    <AlertDialogCancel onClick={props.onCancel}>Cancel</AlertDialogCancel>
+                    <AlertDialogAction onClick={props.onUpload}>Upload Anyway</AlertDialogAction>
+                    <AlertDialogAction onClick={props.onSelect}>Select Them Too</AlertDialogAction>
+                </AlertDialogFooter>
+            </AlertDialogContent>
+        </AlertDialog>
+    )
+}
+
+export default MissingReferencesDialog;
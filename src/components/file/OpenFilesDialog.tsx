@@ -0,0 +1,44 @@
+import {
+    AlertDialog,
+    AlertDialogAction,
+    AlertDialogCancel,
+    AlertDialogContent,
+    AlertDialogDescription,
+    AlertDialogFooter,
+    AlertDialogHeader,
+    AlertDialogTitle,
+} from "../ui/alert-dialog";
+import { ScrollArea } from "../ui/scroll-area";
+
+interface OpenFilesDialogProps {
+    files: string[] // open files, the dialog is hidden while this is empty
+    verb: string // what would happen to the files, e.g. "overwritten"
+    onSkip: () => void
+    onCancel: () => void
+}
+
+// lists files that are open in another program before a download or reset, so the user can close them or skip them
+function OpenFilesDialog(props: OpenFilesDialogProps) {
+    return (
+        <AlertDialog open={props.files.length > 0}>
+            <AlertDialogContent>
+                <AlertDialogHeader>
+                    <AlertDialogTitle>{props.files.length} files are open</AlertDialogTitle>
+                    <AlertDialogDescription>
+                        These files are open in SolidWorks or another program and can't be {props.verb}.
+                        Close them and try again, or skip them for now.
+                    </AlertDialogDescription>
+                </AlertDialogHeader>
+                <ScrollArea className="max-h-48 rounded-md border p-2 text-sm">
+                    {props.files.map((file) => <p key={file}>{file}</p>)}
+                </ScrollArea>
+                <AlertDialogFooter>
+                    <AlertDialogCancel onClick={props.onCancel}>Cancel</AlertDialogCancel>
+                    <AlertDialogAction onClick={props.onSkip}>Skip These Files</AlertDialogAction>
+                </AlertDialogFooter>
+            </AlertDialogContent>
+        </AlertDialog>
+    )
+}
+
+export default OpenFilesDialog;
@@ -0,0 +1,113 @@
+import { Button } from "@/components/ui/button";
+import { Card, CardContent, CardDescription, CardFooter, CardHeader, CardTitle } from "@/components/ui/card";
+import { Label } from "@/components/ui/label";
+import { Input } from "../ui/input";
+import { ScrollArea } from "../ui/scroll-area";
+import { Table, TableBody, TableCell, TableHead, TableHeader, TableRow } from "../ui/table";
+import { useToast } from "../ui/use-toast";
+import { invoke } from "@tauri-apps/api/core";
+import { useState } from "react";
+
+export interface Backup {
+    bid: number
+    pid: number
+    rel_path: string
+    hash: string
+    size: number
+    base_commitid: number
+    reason: string
+    created: string
+}
+
+interface BackupsProps {
+    backups: Backup[]
+    retention: number
+}
+function Backups(props: BackupsProps) {
+    const { toast } = useToast();
+    const [backups, setBackups] = useState(props.backups)
+    const [retention, setRetention] = useState(props.retention)
+    const [retentionChangeMade, setRetentionChangeMade] = useState(false)
+
+    async function refresh() {
+        setBackups(await invoke("list_backups", { pid: null }))
+    }
+
+    async function restore(bid: number) {
+        const res = await invoke("restore_backup", { bid: bid })
+        if(res) {
+            toast({ title: "Backup restored." })
+        }
+        else {
+            toast({ title: "An error occurred while restoring the backup." })
+        }
+        await refresh()
+    }
+
+    async function remove(bid: number) {
+        const res = await invoke("delete_backup", { bid: bid })
+        if(!res) {
+            toast({ title: "An error occurred while deleting the backup." })
+        }
+        await refresh()
+    }
+
+    async function confirmRetentionChange() {
+        setRetentionChangeMade(false)
+        await invoke("cmd_set_backup_retention", { days: retention })
+        await refresh()
+        toast({ title: "Backup settings updated." })
+    }
+
+  return (
+    <ScrollArea className="h-[500px]">
+    <div className="flex flex-col space-y-4 w-full">
+    <Card>
+    <CardHeader>
+        <CardTitle>Backups</CardTitle>
+        <CardDescription>Local edits that were never uploaded are backed up here before a download or reset replaces them.</CardDescription>
+    </CardHeader>
+    <CardContent>
+        <div className="flex flex-row space-x-4 place-items-center flex-grow py-2">
+            <Input className="w-24" type="number" min={0} step={1} value={retention} onChange={(e) => {setRetention(Math.max(0, Math.floor(Number(e.target.value)))); setRetentionChangeMade(true)}}/>
+            <Label>Days to keep backups for (0 to keep them forever)</Label>
+        </div>
+    </CardContent>
+    <CardFooter className="flex flex-row space-x-4 items-center justify-end">
+        <Button disabled={!retentionChangeMade} onClick={confirmRetentionChange}>Save Changes</Button>
+    </CardFooter>
+    </Card>
+    <Card>
+    <CardContent className="pt-6">
+        <Table>
+            <TableHeader>
+                <TableRow>
+                    <TableHead>File</TableHead>
+                    <TableHead>Reason</TableHead>
+                    <TableHead>Created</TableHead>
+                    <TableHead></TableHead>
+                </TableRow>
+            </TableHeader>
+            <TableBody>
+                {backups.length == 0 ? <TableRow><TableCell colSpan={4}>No backups.</TableCell></TableRow> : <></>}
+                {backups.map((backup) => (
+                    <TableRow key={backup.bid}>
+                        <TableCell>{backup.rel_path}</TableCell>
+                        <TableCell>{backup.reason}</TableCell>
+                        <TableCell>{backup.created}</TableCell>
+                        <TableCell className="flex flex-row space-x-2 justify-end">
+                            <Button variant={"outline"} onClick={() => restore(backup.bid)}>Restore</Button>
+                            <Button variant={"destructive"} onClick={() => remove(backup.bid)}>Delete</Button>
+                        </TableCell>
+                    </TableRow>
+                ))}
+            </TableBody>
+        </Table>
+    </CardContent>
+    </Card>
+    </div>
+    </ScrollArea>
+  )
+}
+
+export default Backups
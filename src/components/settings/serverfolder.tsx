@@ -7,15 +7,19 @@ import { sep, join } from "@tauri-apps/api/path";
 import { mkdir, exists } from "@tauri-apps/plugin-fs"; 
 import { invoke } from "@tauri-apps/api/core";
 import { Switch } from "../ui/switch";
+import { Input } from "../ui/input";
 import { Dialog, DialogContent, DialogDescription, DialogFooter, DialogHeader, DialogTitle, DialogTrigger } from "../ui/dialog";
 import { useToast } from "../ui/use-toast";
 import { Loader2 } from "lucide-react";
 import { ScrollArea } from "../ui/scroll-area";
+import { runJob } from "@/lib/utils";
 
 interface ServerFolderProps {
     dir: string
     cache: number
     saveCache: boolean
+    maxCache: number
+    jobConcurrency: number
 }
 function ServerFolder(props: ServerFolderProps) {
     // TODO refactor these LMAO
@@ -29,6 +33,11 @@ function ServerFolder(props: ServerFolderProps) {
     const [completed, setCompleted] = useState(false)
     const [useCache, setUseCache] = useState(props.saveCache)
     const [cacheChangeMade, setCacheChangeMade] = useState(false)
+    // in GB, 0 means the cache size isn't limited
+    const [maxCacheSize, setMaxCacheSize] = useState(props.maxCache / 1024 / 1024 / 1024)
+    // how many downloads, uploads and syncs run at once
+    const [jobConcurrency, setJobConcurrency] = useState(props.jobConcurrency)
+    const [jobChangeMade, setJobChangeMade] = useState(false)
 
     async function selectFolder() {
         const folder = await open({
@@ -53,7 +62,7 @@ function ServerFolder(props: ServerFolderProps) {
 
     async function clearCache() {
         setProgressing(true)
-        let res = await invoke("cmd_delete_cache");
+        let res = await runJob("cmd_delete_cache");
         if(res) {
             toast({
                 title: "Cache cleared successfully."
@@ -97,11 +106,21 @@ function ServerFolder(props: ServerFolderProps) {
     async function confirmCacheChange() {
         setCacheChangeMade(false);
         const res = await invoke("cmd_set_cache_setting", {  newCache: useCache })
+        await invoke("cmd_set_cache_max_size", { maxSize: Math.round(maxCacheSize * 1024 * 1024 * 1024) })
+        setCacheSize(await invoke("get_cache_size"))
         toast({
             title: "Cache settings updated."
         })
     }
 
+    async function confirmJobChange() {
+        setJobChangeMade(false);
+        await invoke("cmd_set_job_concurrency", { concurrency: jobConcurrency })
+        toast({
+            title: "Background job settings updated."
+        })
+    }
+
     // TODO refactor this out into a reusable function
     let size = cacheSize / 1024 / 1024 / 1024;
     let type = "GB"
@@ -179,11 +198,30 @@ function ServerFolder(props: ServerFolderProps) {
         <Switch defaultChecked={useCache} onCheckedChange={(e) => {setUseCache(e); setCacheChangeMade(cacheChangeMade => !cacheChangeMade)}}/>
         <Label>Clear cache after downloading</Label>
     </div>
+    <div className="flex flex-row space-x-4 place-items-center flex-grow py-2">
+        <Input className="w-24" type="number" min={0} step={0.5} disabled={useCache} value={maxCacheSize} onChange={(e) => {setMaxCacheSize(Math.max(0, Number(e.target.value))); setCacheChangeMade(true)}}/>
+        <Label>Maximum cache size in GB (0 for no limit)</Label>
+    </div>
     </CardContent>
     <CardFooter className="flex flex-row space-x-4 items-center justify-end">
     <Button className="justify-self-end" disabled={!cacheChangeMade} onClick={confirmCacheChange}>Save Changes</Button>
     </CardFooter>
 </Card>
+<Card>
+    <CardHeader>
+        <CardTitle>Background Jobs</CardTitle>
+        <CardDescription>Syncs, downloads and uploads are queued and keep going after glassyPDM restarts.</CardDescription>
+    </CardHeader>
+    <CardContent>
+    <div className="flex flex-row space-x-4 place-items-center flex-grow py-2">
+        <Input className="w-24" type="number" min={1} step={1} value={jobConcurrency} onChange={(e) => {setJobConcurrency(Math.max(1, Math.round(Number(e.target.value)))); setJobChangeMade(true)}}/>
+        <Label>Jobs that run at once (one per project)</Label>
+    </div>
+    </CardContent>
+    <CardFooter className="flex flex-row space-x-4 items-center justify-end">
+    <Button className="justify-self-end" disabled={!jobChangeMade} onClick={confirmJobChange}>Save Changes</Button>
+    </CardFooter>
+</Card>
 </div>
 </ScrollArea>
   )
@@ -0,0 +1,112 @@
+import { Button } from "@/components/ui/button";
+import { Card, CardContent, CardDescription, CardFooter, CardHeader, CardTitle } from "@/components/ui/card";
+import { Label } from "@/components/ui/label";
+import { Input } from "../ui/input";
+import { ScrollArea } from "../ui/scroll-area";
+import { Table, TableBody, TableCell, TableHead, TableHeader, TableRow } from "../ui/table";
+import { useToast } from "../ui/use-toast";
+import { invoke } from "@tauri-apps/api/core";
+import { useState } from "react";
+
+export interface TrashEntry {
+    tid: number
+    pid: number
+    rel_path: string
+    hash: string
+    reason: string
+    created: string
+}
+
+interface TrashProps {
+    entries: TrashEntry[]
+    retention: number
+}
+function Trash(props: TrashProps) {
+    const { toast } = useToast();
+    const [entries, setEntries] = useState(props.entries)
+    const [retention, setRetention] = useState(props.retention)
+    const [retentionChangeMade, setRetentionChangeMade] = useState(false)
+
+    async function refresh() {
+        setEntries(await invoke("list_trash", { pid: null }))
+    }
+
+    async function restore(tid: number) {
+        const res = await invoke("restore_trash_entry", { tid: tid })
+        if(res) {
+            toast({ title: "File restored." })
+        }
+        else {
+            toast({ title: "An error occurred while restoring the file.", description: "Make sure there isn't already a file at its original location." })
+        }
+        await refresh()
+    }
+
+    async function purge(tid: number | null) {
+        const res = await invoke("purge_trash", { tid: tid })
+        if(!res) {
+            toast({ title: "An error occurred while emptying the trash." })
+        }
+        await refresh()
+    }
+
+    async function confirmRetentionChange() {
+        setRetentionChangeMade(false)
+        await invoke("cmd_set_trash_retention", { days: retention })
+        await refresh()
+        toast({ title: "Trash settings updated." })
+    }
+
+  return (
+    <ScrollArea className="h-[500px]">
+    <div className="flex flex-col space-y-4 w-full">
+    <Card>
+    <CardHeader>
+        <CardTitle>Trash</CardTitle>
+        <CardDescription>Files that are deleted by a download, reset or project deletion are moved here first.</CardDescription>
+    </CardHeader>
+    <CardContent>
+        <div className="flex flex-row space-x-4 place-items-center flex-grow py-2">
+            <Input className="w-24" type="number" min={0} step={1} value={retention} onChange={(e) => {setRetention(Math.max(0, Math.floor(Number(e.target.value)))); setRetentionChangeMade(true)}}/>
+            <Label>Days to keep deleted files for (0 to keep them until the trash is emptied)</Label>
+        </div>
+    </CardContent>
+    <CardFooter className="flex flex-row space-x-4 items-center justify-end">
+        <Button variant={"outline"} disabled={entries.length == 0} onClick={() => purge(null)}>Empty Trash</Button>
+        <Button disabled={!retentionChangeMade} onClick={confirmRetentionChange}>Save Changes</Button>
+    </CardFooter>
+    </Card>
+    <Card>
+    <CardContent className="pt-6">
+        <Table>
+            <TableHeader>
+                <TableRow>
+                    <TableHead>File</TableHead>
+                    <TableHead>Reason</TableHead>
+                    <TableHead>Created</TableHead>
+                    <TableHead></TableHead>
+                </TableRow>
+            </TableHeader>
+            <TableBody>
+                {entries.length == 0 ? <TableRow><TableCell colSpan={4}>The trash is empty.</TableCell></TableRow> : <></>}
+                {entries.map((entry) => (
+                    <TableRow key={entry.tid}>
+                        <TableCell>{entry.rel_path}</TableCell>
+                        <TableCell>{entry.reason}</TableCell>
+                        <TableCell>{entry.created}</TableCell>
+                        <TableCell className="flex flex-row space-x-2 justify-end">
+                            <Button variant={"outline"} onClick={() => restore(entry.tid)}>Restore</Button>
+                            <Button variant={"destructive"} onClick={() => purge(entry.tid)}>Delete</Button>
+                        </TableCell>
+                    </TableRow>
+                ))}
+            </TableBody>
+        </Table>
+    </CardContent>
+    </Card>
+    </div>
+    </ScrollArea>
+  )
+}
+
+export default Trash
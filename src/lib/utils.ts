@@ -1,4 +1,6 @@
 import { useAuth } from "@clerk/clerk-react";
+import { invoke, InvokeArgs } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { type ClassValue, clsx } from "clsx"
 import { twMerge } from "tailwind-merge"
 
@@ -16,4 +18,79 @@ export function useFetch() {
   };
  
   return authenticatedFetch;
-}
\ No newline at end of file
+}
+
+// commands that fail reject with a GlassyError from the backend
+export interface GlassyError {
+  kind: "Db" | "Io" | "Network" | "Server" | "Auth" | "Cache" | "Conflict" | "FileChanged" | "NotFound" | "Setup" | "Cancelled" | "Job" | "Locked";
+  message: string;
+}
+
+export function isCancelled(err: unknown): boolean {
+  return typeof err == "object" && err != null && (err as GlassyError).kind == "Cancelled";
+}
+
+export function errorMessage(err: unknown): string {
+  if (typeof err == "object" && err != null && "message" in err) {
+    return (err as GlassyError).message;
+  }
+  return String(err);
+}
+
+// sent by the backend when a queued job ends
+export interface JobOutcome {
+  id: number;
+  pid: number;
+  kind: string;
+  result: any;
+  error: GlassyError | null;
+}
+
+// progress events a job sends, by event name, see events.rs. the backend tags each with the job's id
+export type JobHandlers = Record<string, (data: any) => void>;
+
+interface JobEvent {
+  jid: number;
+  data: any;
+}
+
+// commands like download_files queue a job and return its id right away.
+// this waits for the job to finish, resolving to its result or rejecting with its GlassyError.
+// `handlers` only hear events from this job, other jobs can be running at the same time
+export async function runJob<T>(command: string, args?: InvokeArgs, handlers: JobHandlers = {}): Promise<T> {
+  let id: number | null = null;
+  let settle: ((outcome: JobOutcome) => void) | null = null;
+  const finished = new Map<number, JobOutcome>();
+  // listen before queueing, the job could finish before invoke returns
+  const unlisten = await listen<JobOutcome>("jobFinished", (event) => {
+    if (event.payload.id == id && settle != null) {
+      settle(event.payload);
+    } else {
+      finished.set(event.payload.id, event.payload);
+    }
+  });
+  // events from before invoke returns are kept until we know which job is ours
+  const early: [string, JobEvent][] = [];
+  const unlistenEvents = await Promise.all(Object.entries(handlers).map(([name, handler]) =>
+    listen<JobEvent>(name, (event) => {
+      if (id == null) {
+        early.push([name, event.payload]);
+      } else if (event.payload.jid == id) {
+        handler(event.payload.data);
+      }
+    })
+  ));
+  try {
+    id = await invoke<number>(command, args);
+    const jid = id;
+    early.filter(([, event]) => event.jid == jid).forEach(([name, event]) => handlers[name](event.data));
+    const outcome = finished.get(jid) ?? await new Promise<JobOutcome>((resolve) => { settle = resolve; });
+    if (outcome.error != null) {
+      throw outcome.error;
+    }
+    return outcome.result as T;
+  } finally {
+    unlisten();
+    unlistenEvents.forEach((f) => f());
+  }
+}
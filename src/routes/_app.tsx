@@ -1,9 +1,9 @@
 import { Navigate, Outlet, createFileRoute, redirect } from '@tanstack/react-router'
-import { ClerkProvider, SignedIn, SignedOut } from "@clerk/clerk-react"
+import { ClerkProvider, SignedIn, SignedOut, useAuth } from "@clerk/clerk-react"
 import { invoke } from '@tauri-apps/api/core';
 import { AlertDialog, AlertDialogContent, AlertDialogHeader, AlertDialogTitle } from '@/components/ui/alert-dialog';
 import { listen } from '@tauri-apps/api/event';
-import { useState } from 'react';
+import { useEffect, useState } from 'react';
 import { Loader2 } from 'lucide-react';
 import { Progress } from '@/components/ui/progress';
 import { Button } from '@/components/ui/button';
@@ -28,6 +28,23 @@ export const Route = createFileRoute('/_app')({
 })
 
 
+// queued jobs that talk to the server run with the user's token, which expires after a minute.
+// keep the job queue's token fresh, this also starts jobs left over from last time
+function JobQueueToken() {
+  const { getToken } = useAuth();
+
+  useEffect(() => {
+    async function refresh() {
+      await invoke("resume_jobs", { token: await getToken() });
+    }
+    refresh();
+    const interval = setInterval(refresh, 45_000);
+    return () => clearInterval(interval);
+  }, []);
+
+  return <></>;
+}
+
 function AppLayout() {
   const { publickey } = Route.useLoaderData();
   const [ updateAvailable, setUpdateAvailable] = useState(false)
@@ -55,6 +72,7 @@ function AppLayout() {
           <Outlet />
           </SignedOut>
         <SignedIn>
+          <JobQueueToken />
           <Outlet />
           <AlertDialog defaultOpen={updateAvailable} open={updateAvailable}>
             <AlertDialogContent>
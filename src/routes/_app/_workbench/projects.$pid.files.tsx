@@ -1,14 +1,39 @@
 import { Button } from '@/components/ui/button';
-import { createFileRoute, Link } from '@tanstack/react-router'
+import { Table, TableBody, TableCell, TableHead, TableHeader, TableRow } from '@/components/ui/table'
+import { useToast } from '@/components/ui/use-toast'
+import { errorMessage, runJob } from '@/lib/utils'
+import { useAuth } from '@clerk/clerk-react'
+import { createFileRoute, Link, useRouter } from '@tanstack/react-router'
 import { invoke } from '@tauri-apps/api/core'
+import { useState } from 'react'
 import { z } from "zod"
 
 const FileSearchSchema = z.object({
   directory: z.string()
 })
 
+interface FileSummary {
+  filepath: string
+  change_type: number
+  in_fs: number
+  lock_user: string | null
+}
+
+interface FileLock {
+  path: string
+  user_id: string
+  created: string
+}
+
+// assemblies, drawings and other files whose references are read, see references.rs
+const REFERENCE_EXTENSIONS = ["sldasm", "slddrw", "step", "stp", "kicad_pro", "kicad_sch"]
+
+function hasReferences(filepath: string) {
+  return REFERENCE_EXTENSIONS.includes(filepath.split('.').pop()?.toLowerCase() ?? "")
+}
+
 interface DirectorySummary {
-  files: any,
+  files: FileSummary[],
   folders: string[]
 }
 export const Route = createFileRoute('/_app/_workbench/projects/$pid/files')({
@@ -17,7 +42,6 @@ export const Route = createFileRoute('/_app/_workbench/projects/$pid/files')({
   loader: async ({ deps: { directory }, params: {pid}}) => {
     const data: DirectorySummary = await invoke("get_files", { projectId: parseInt(pid), directory: directory})
     data.folders.sort()
-    console.log(data)
     return data;
   },
   component: () => <Files />
@@ -27,28 +51,87 @@ function Files() {
   const { pid } = Route.useParams();
   const data = Route.useLoaderData();
   const { directory } = Route.useSearch();
+  const { getToken, userId } = useAuth();
+  const { toast } = useToast();
+  const router = useRouter();
+  const [disabled, setDisabled] = useState(false)
 
-  async function test(){
-    const start = performance.now()
-    const oop = await invoke("get_files", {projectId: parseInt(pid), directory: ""})
+  async function lockCommand(command: string, args: object) {
+    setDisabled(true)
+    try {
+      const taken: FileLock[] | boolean = await invoke(command, { pid: parseInt(pid), user: userId, token: await getToken(), ...args })
+      if (Array.isArray(taken) && command == "lock_files" && taken.length > 0) {
+        toast({ title: "Couldn't check out the file.", description: `${taken[0].user_id} has it checked out.` })
+      }
+    } catch (err) {
+      toast({ title: "Couldn't reach the server.", description: errorMessage(err) })
+    }
+    setDisabled(false)
+    router.invalidate()
+  }
 
-    const end = performance.now()
-    console.log(end- start)
-    console.log(oop)
+  // downloads the file and every file it needs, e.g. an assembly's parts
+  async function downloadWithReferences(filepath: string) {
+    setDisabled(true)
+    try {
+      const finished: boolean = await runJob("download_with_references", { pid: parseInt(pid), filepath: filepath, user: userId, token: await getToken() })
+      toast({ title: finished ? "Download complete" : "Download failed", description: filepath })
+    } catch (err) {
+      toast({ title: "Download failed", description: errorMessage(err) })
+    }
+    setDisabled(false)
+    router.invalidate()
   }
-  const parentDirectory = directory.substring(0, directory.lastIndexOf('\\'))
+
+  const parentDirectory = directory.substring(0, directory.slice(0, -1).lastIndexOf('\\') + 1)
   return (
-    <div className='flex flex-col'>
-      {/*
-      <div>breadcrumb todo</div>
-            <Link from={Route.fullPath} search={{directory: parentDirectory}} className='underline'>..</Link>
-            { data.folders.map((folder) => {
-        return (
-            <Link from={Route.fullPath} search={{directory: directory + folder}} className='underline' key={folder}>{folder}</Link>
-        )
-      })}
-      */}
-      Coming soon
+    <div className='flex flex-col w-full px-4 pt-8 space-y-2'>
+      <div className='flex flex-row items-center justify-between'>
+        <p className='font-semibold'>{directory == "" ? "Project" : directory}</p>
+        <Button variant={'outline'} disabled={disabled} onClick={() => lockCommand("cmd_refresh_locks", {})}>Refresh Checkouts</Button>
+      </div>
+      <Table>
+        <TableHeader>
+          <TableRow>
+            <TableHead>Name</TableHead>
+            <TableHead>Checked Out By</TableHead>
+            <TableHead></TableHead>
+          </TableRow>
+        </TableHeader>
+        <TableBody>
+          {directory != "" &&
+            <TableRow>
+              <TableCell colSpan={3}>
+                <Link from={Route.fullPath} search={{directory: parentDirectory}} className='underline'>..</Link>
+              </TableCell>
+            </TableRow>
+          }
+          {data.folders.map((folder) =>
+            <TableRow key={folder}>
+              <TableCell colSpan={3}>
+                <Link from={Route.fullPath} search={{directory: directory + folder}} className='underline'>{folder}</Link>
+              </TableCell>
+            </TableRow>
+          )}
+          {data.files.map((file) =>
+            <TableRow key={file.filepath}>
+              <TableCell>{file.filepath.substring(directory.length)}</TableCell>
+              <TableCell>{file.lock_user == null ? "" : file.lock_user == userId ? "You" : file.lock_user}</TableCell>
+              <TableCell className='text-right space-x-2'>
+                {hasReferences(file.filepath) &&
+                  <Button variant={'outline'} disabled={disabled} onClick={() => downloadWithReferences(file.filepath)}>Download With References</Button>
+                }
+                {file.lock_user == null &&
+                  <Button variant={'outline'} disabled={disabled} onClick={() => lockCommand("lock_files", { paths: [file.filepath] })}>Check Out</Button>
+                }
+                {file.lock_user == userId &&
+                  <Button variant={'outline'} disabled={disabled} onClick={() => lockCommand("unlock_files", { paths: [file.filepath], force: false })}>Check In</Button>
+                }
+              </TableCell>
+            </TableRow>
+          )}
+        </TableBody>
+      </Table>
     </div>
   )
-}
\ No newline at end of file
+}
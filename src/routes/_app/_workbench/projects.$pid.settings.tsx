@@ -1,21 +1,184 @@
-import { Card, CardDescription, CardHeader, CardTitle } from '@/components/ui/card'
+import { Button } from '@/components/ui/button'
+import { Card, CardContent, CardDescription, CardHeader, CardTitle } from '@/components/ui/card'
+import { Input } from '@/components/ui/input'
+import { ScrollArea } from '@/components/ui/scroll-area'
+import { Switch } from '@/components/ui/switch'
+import { Table, TableBody, TableCell, TableHead, TableHeader, TableRow } from '@/components/ui/table'
+import { useToast } from '@/components/ui/use-toast'
+import { useAuth } from '@clerk/clerk-react'
 import { createFileRoute } from '@tanstack/react-router'
+import { invoke } from '@tauri-apps/api/core'
+import { useState } from 'react'
+
+interface IgnoreRule {
+  rule: string
+  from_file: boolean
+}
+
+interface IgnoreMatch {
+  filepath: string
+  tracked: boolean
+}
 
 export const Route = createFileRoute('/_app/_workbench/projects/$pid/settings')({
+  loader: async ({ params: { pid } }) => {
+    const rules: IgnoreRule[] = await invoke("get_ignore_rules", { pid: parseInt(pid) })
+    const sparse: string[] = await invoke("get_sparse_checkout", { pid: parseInt(pid) })
+    const readonly: boolean = await invoke("cmd_get_readonly_checkout", { pid: parseInt(pid) })
+    return { rules: rules, sparse: sparse, readonly: readonly }
+  },
   component: ProjectSettings
 })
 
 function ProjectSettings() {
+  const { pid } = Route.useParams();
+  const loaderData = Route.useLoaderData();
+  const { toast } = useToast();
+  const { userId } = useAuth();
+  const [rules, setRules] = useState(loaderData.rules)
+  const [newRule, setNewRule] = useState("")
+  const [preview, setPreview] = useState<IgnoreMatch[] | null>(null)
+  const [sparse, setSparse] = useState(loaderData.sparse)
+  const [newPattern, setNewPattern] = useState("")
+  const [readonly, setReadonly] = useState(loaderData.readonly)
+
+  async function refresh() {
+    setRules(await invoke("get_ignore_rules", { pid: parseInt(pid) }))
+  }
+
+  async function previewRule() {
+    setPreview(await invoke("cmd_preview_ignore_rule", { pid: parseInt(pid), rule: newRule }))
+  }
+
+  async function addRule() {
+    const res = await invoke("add_ignore_rule", { pid: parseInt(pid), rule: newRule })
+    if (res) {
+      setNewRule("")
+      setPreview(null)
+    } else {
+      toast({ title: "Couldn't add the rule.", description: "Make sure it is a valid pattern that isn't in the list already." })
+    }
+    await refresh()
+  }
+
+  async function removeRule(rule: string) {
+    await invoke("remove_ignore_rule", { pid: parseInt(pid), rule: rule })
+    await refresh()
+  }
+
+  async function refreshSparse() {
+    setSparse(await invoke("get_sparse_checkout", { pid: parseInt(pid) }))
+  }
+
+  async function addPattern() {
+    const res = await invoke("add_to_sparse_checkout", { pid: parseInt(pid), pattern: newPattern })
+    if (res) {
+      setNewPattern("")
+    } else {
+      toast({ title: "Couldn't add the folder.", description: "Make sure it is a valid pattern that isn't checked out already." })
+    }
+    await refreshSparse()
+  }
+
+  async function removePattern(pattern: string) {
+    await invoke("remove_from_sparse_checkout", { pid: parseInt(pid), pattern: pattern })
+    await refreshSparse()
+  }
+
+  async function updateReadonly(checked: boolean) {
+    if (await invoke("cmd_set_readonly_checkout", { pid: parseInt(pid), readonly: checked, user: userId })) {
+      setReadonly(checked)
+    }
+  }
+
   return (
-    <div className='flex flex-col w-full px-4 pt-8'>
+    <div className='flex flex-col w-full px-4 pt-8 space-y-4'>
+      <Card>
+        <CardHeader>
+          <CardTitle>Read-only Checkout</CardTitle>
+          <CardDescription>
+            Keep uploaded files read-only until you check them out on the Files page, so nobody edits a part someone else is working on.
+            Files someone else has checked out can't be uploaded either way.
+          </CardDescription>
+        </CardHeader>
+        <CardContent>
+          <Switch checked={readonly} onCheckedChange={updateReadonly}/>
+        </CardContent>
+      </Card>
+      <Card>
+        <CardHeader>
+          <CardTitle>Sparse Checkout</CardTitle>
+          <CardDescription>
+            Only download the folders you work on, e.g. <code>Suspension/</code>. Start a pattern with <code>!</code> to leave a folder out.
+            Other files stay on the server and aren't downloaded or shown as deleted. Leave this empty to check out the whole project.
+          </CardDescription>
+        </CardHeader>
+        <CardContent className='space-y-4'>
+          <div className='flex flex-row space-x-2'>
+            <Input placeholder='Suspension/' value={newPattern} onChange={(e) => setNewPattern(e.target.value)}/>
+            <Button disabled={newPattern.trim().length == 0} onClick={addPattern}>Add Folder</Button>
+          </div>
+          {sparse.length == 0 ? <p className='text-sm'>The whole project is checked out.</p> :
+            <Table>
+              <TableBody>
+                {sparse.map((pattern) =>
+                  <TableRow key={pattern}>
+                    <TableCell><code>{pattern}</code></TableCell>
+                    <TableCell className='text-right'>
+                      <Button variant={'outline'} onClick={() => removePattern(pattern)}>Remove</Button>
+                    </TableCell>
+                  </TableRow>
+                )}
+              </TableBody>
+            </Table>
+          }
+        </CardContent>
+      </Card>
       <Card>
         <CardHeader>
           <CardTitle>Ignore List</CardTitle>
           <CardDescription>
             Configure a list of paths and files for glassyPDM to ignore changes.
-            </CardDescription>
+            Rules work like a .gitignore, e.g. <code>*.bak</code>, <code>exports/</code> or <code>!keep.step</code>.
+            Rules in the project's .glassyignore apply to everyone, rules added here only apply on this computer.
+            Files that are already uploaded keep syncing.
+          </CardDescription>
         </CardHeader>
+        <CardContent className='space-y-4'>
+          <div className='flex flex-row space-x-2'>
+            <Input placeholder='*.bak' value={newRule} onChange={(e) => { setNewRule(e.target.value); setPreview(null) }}/>
+            <Button variant={'outline'} disabled={newRule.trim().length == 0} onClick={previewRule}>Preview</Button>
+            <Button disabled={newRule.trim().length == 0} onClick={addRule}>Add Rule</Button>
+          </div>
+          {preview != null &&
+            <ScrollArea className='h-32 rounded-md border p-2 text-sm'>
+              {preview.length == 0 ? <p>No files match this rule.</p> : preview.map((match) =>
+                <p key={match.filepath}>{match.filepath}{match.tracked ? " (uploaded, keeps syncing)" : ""}</p>
+              )}
+            </ScrollArea>
+          }
+          <Table>
+            <TableHeader>
+              <TableRow>
+                <TableHead>Rule</TableHead>
+                <TableHead>From</TableHead>
+                <TableHead></TableHead>
+              </TableRow>
+            </TableHeader>
+            <TableBody>
+              {rules.map((rule, index) =>
+                <TableRow key={index}>
+                  <TableCell><code>{rule.rule}</code></TableCell>
+                  <TableCell>{rule.from_file ? ".glassyignore" : "This computer"}</TableCell>
+                  <TableCell className='text-right'>
+                    {!rule.from_file && <Button variant={'outline'} onClick={() => removeRule(rule.rule)}>Remove</Button>}
+                  </TableCell>
+                </TableRow>
+              )}
+            </TableBody>
+          </Table>
+        </CardContent>
       </Card>
     </div>
   )
-}
\ No newline at end of file
+}
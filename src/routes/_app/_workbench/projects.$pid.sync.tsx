@@ -16,6 +16,7 @@ import { Separator } from "@/components/ui/separator";
 import { useToast } from "@/components/ui/use-toast";
 import { Loader2 } from "lucide-react";
 import { Input } from "@/components/ui/input";
+import { errorMessage, runJob } from "@/lib/utils";
 export const Route = createFileRoute("/_app/_workbench/projects/$pid/sync")({
   component: () => <SyncPage />,
   loader: async ({ params }) => {
@@ -52,7 +53,7 @@ interface ProjectStateOutput {
 
 function SyncPage() {
   const navigate = useNavigate();
-  const { getToken } = useAuth();
+  const { getToken, userId } = useAuth();
   const { upload, download, url, conflict } = Route.useLoaderData();
   const { pid } = Route.useParams();
   const [uploadSize, setUploadSize] = useState(upload);
@@ -106,7 +107,16 @@ function SyncPage() {
       project = remote.body;
     }
 
-    await invoke("sync_changes", { pid: pid_number, remote: project });
+    try {
+      await runJob("sync_changes", { pid: pid_number, remote: project, user: userId, token: await getToken() });
+    } catch(err) {
+      toast({
+        title: "Couldn't sync",
+        description: errorMessage(err),
+      });
+      setSyncInProgress(false);
+      return;
+    }
 
     // TODO type this so its not any
     const uploadOutput: any = await invoke("get_uploads", { pid: pid_number });
@@ -134,6 +144,31 @@ function SyncPage() {
     })
   }
 
+  async function resolveConflict(file: File, resolution: "keep_mine" | "take_theirs" | "keep_both") {
+    const pid_number = parseInt(pid);
+    const args = { pid: pid_number, path: file.filepath, user: userId, token: await getToken() };
+    // taking or keeping their revision downloads it, so it waits in the queue behind the project's other transfers
+    let resolved = false;
+    try {
+      resolved = resolution == "keep_mine" ? await invoke("resolve_conflict_keep_mine", args) : await runJob("resolve_conflict_" + resolution, args);
+    } catch (err) {
+      toast({ title: "Couldn't resolve conflict", description: errorMessage(err) });
+      return;
+    }
+    if (!resolved) {
+      toast({ title: "Couldn't resolve conflict", description: file.filepath });
+      return;
+    }
+
+    const conflictOutput: File[] = await invoke("get_conflicts", { pid: pid_number });
+    const uploadOutput: File[] = await invoke("get_uploads", { pid: pid_number });
+    const downloadOutput: File[] = await invoke("get_downloads", { pid: pid_number });
+    setConflictList(conflictOutput);
+    setConflictExists(conflictOutput.length > 0);
+    setUploadSize(uploadOutput.length);
+    setDownloadSize(downloadOutput.length);
+  }
+
   async function devSync() {
     await syncChanges(syncCommit);
   }
@@ -189,15 +224,21 @@ function SyncPage() {
           <DialogHeader>
             <DialogTitle>File conflicts detected!</DialogTitle>
             <DialogDescription>
-              Please backup these files elsewhere before downloading/uploading
-              changes.
+              These files changed both locally and on the server. Keep your
+              version, take the server's version (your version is backed up),
+              or keep both.
             </DialogDescription>
           </DialogHeader>
           <ScrollArea>
             {conflictList.map((value: File) => {
               return (
-                <div key={value.hash}>
+                <div key={value.filepath}>
                   <p>{value.filepath}</p>
+                  <div className="flex flex-row gap-2 py-2">
+                    <Button size="sm" variant="outline" onClick={() => resolveConflict(value, "keep_mine")}>Keep mine</Button>
+                    <Button size="sm" variant="outline" onClick={() => resolveConflict(value, "take_theirs")}>Take theirs</Button>
+                    <Button size="sm" variant="outline" onClick={() => resolveConflict(value, "keep_both")}>Keep both</Button>
+                  </div>
                   <Separator />
                 </div>
               );
@@ -207,7 +248,7 @@ function SyncPage() {
             onClick={() => setConflictExists(false)}
             variant="destructive"
           >
-            I understand
+            Resolve later
           </Button>
         </DialogContent>
       </Dialog>
@@ -7,6 +7,8 @@ import { ScrollArea } from "@/components/ui/scroll-area";
 import { createFileRoute } from "@tanstack/react-router";
 import { RadioGroup, RadioGroupItem } from "@/components/ui/radio-group";
 import ServerFolder from "@/components/settings/serverfolder";
+import Backups, { Backup } from "@/components/settings/backups";
+import Trash, { TrashEntry } from "@/components/settings/trash";
 import { useState } from "react";
 import { toast } from "sonner";
 import { invoke } from "@tauri-apps/api/core";
@@ -23,6 +25,12 @@ export const Route = createFileRoute('/_app/_workbench/settings')({
         const result = await invoke("init_settings_options");
         const cache = await invoke("get_cache_size");
         const cacheSetting = await invoke("cmd_get_cache_setting");
+        const cacheMaxSize = await invoke("cmd_get_cache_max_size");
+        const jobConcurrency = await invoke("cmd_get_job_concurrency");
+        const backups: Backup[] = await invoke("list_backups", { pid: null });
+        const backupRetention = await invoke("cmd_get_backup_retention");
+        const trash: TrashEntry[] = await invoke("list_trash", { pid: null });
+        const trashRetention = await invoke("cmd_get_trash_retention");
         const devMode = await invoke("is_dev_mode");
         const dir = (result as any).local_dir;
         const debug = (result as any).debug_active;
@@ -31,6 +39,12 @@ export const Route = createFileRoute('/_app/_workbench/settings')({
             url: url,
             dir: dir,
             cacheSetting: cacheSetting,
+            cacheMaxSize: cacheMaxSize,
+            jobConcurrency: jobConcurrency,
+            backups: backups,
+            backupRetention: backupRetention,
+            trash: trash,
+            trashRetention: trashRetention,
             debug: debug == 1 ? true : false,
             devMode: devMode
         }
@@ -69,6 +83,8 @@ function Settings() {
             <TabsList className="flex flex-col justify-start">
                 <h1 className="text-2xl font-semibold p-4">Settings</h1>
                 <TabsTrigger value="folder">Server Folder</TabsTrigger>
+                <TabsTrigger value="backups">Backups</TabsTrigger>
+                <TabsTrigger value="trash">Trash</TabsTrigger>
                 <TabsTrigger value="appdata">App Data</TabsTrigger>
                 <TabsTrigger value="appearance">Appearance</TabsTrigger>
                 <TabsTrigger value="account">Account</TabsTrigger>
@@ -78,7 +94,13 @@ function Settings() {
             </TabsList>
             <div className="w-full">
                 <TabsContent value="folder">
-                    <ServerFolder dir={loaderData.dir as string} cache={loaderData.cache as number} saveCache={loaderData.cacheSetting as boolean}/>
+                    <ServerFolder dir={loaderData.dir as string} cache={loaderData.cache as number} saveCache={loaderData.cacheSetting as boolean} maxCache={loaderData.cacheMaxSize as number} jobConcurrency={loaderData.jobConcurrency as number}/>
+                </TabsContent>
+                <TabsContent value="backups">
+                    <Backups backups={loaderData.backups} retention={loaderData.backupRetention as number}/>
+                </TabsContent>
+                <TabsContent value="trash">
+                    <Trash entries={loaderData.trash} retention={loaderData.trashRetention as number}/>
                 </TabsContent>
                 <TabsContent value="appdata">
                     <Card>
@@ -1,5 +1,7 @@
 import { columns, File } from '@/components/file/FileColumn'
 import { FileTable } from '@/components/file/FileTable'
+import JobControls from '@/components/file/JobControls'
+import OpenFilesDialog from '@/components/file/OpenFilesDialog'
 import { Button } from '@/components/ui/button'
 import { Progress } from '@/components/ui/progress'
 import { useToast } from '@/components/ui/use-toast'
@@ -7,9 +9,9 @@ import { useAuth } from '@clerk/clerk-react'
 import { createFileRoute, Link } from '@tanstack/react-router'
 import { RowSelectionState } from '@tanstack/react-table'
 import { invoke } from '@tauri-apps/api/core'
-import { listen } from '@tauri-apps/api/event'
 import { Loader2 } from 'lucide-react'
 import { useState } from 'react'
+import { errorMessage, isCancelled, runJob } from '@/lib/utils'
 
 export const Route = createFileRoute('/_app/download')({
   validateSearch: (search) =>
@@ -47,8 +49,10 @@ function DownloadPage() {
   const [progress, setProgress] = useState(0);
   const [disabled, setDisabled] = useState(false);
   const [selection, setSelection] = useState(selectionList);
+  const [openFiles, setOpenFiles] = useState<string[]>([]);
 
-  async function handleDownload() {
+  // `skipped` are open files the user chose to leave out, null if they haven't been checked for yet
+  async function handleDownload(skipped: string[] | null) {
     setDisabled(true);
     console.log("hehe")
     console.log(selection)
@@ -81,21 +85,47 @@ function DownloadPage() {
       });
     }
 
-    let hehe = 0;
-    const unlisten = await listen('downloadedFile', (event: any) => {
-      console.log(event)
-      setProgress(100 * ++hehe / event.payload)
-      setStatus(`${hehe} of ${event.payload} file chunks downloaded...`);
-    });
+    // files that are open in solidworks can't be overwritten
+    if (skipped == null) {
+      const open: string[] = await invoke("cmd_find_open_files", { pid: parseInt(pid), filepaths: selectedDownload.map((file) => file.rel_path) });
+      if (open.length > 0) {
+        setOpenFiles(open);
+        setDisabled(false);
+        return;
+      }
+    } else {
+      selectedDownload = selectedDownload.filter((file) => !skipped.includes(file.rel_path));
+    }
 
-    const unlisten2 = await listen('cacheComplete', (event: any) => {
-      console.log(event)
-      setStatus(`Assembling files...`)
-    })
+    let hehe = 0;
+    const handlers = {
+      downloadedFile: (total: number) => {
+        setProgress(100 * ++hehe / total)
+        setStatus(`${hehe} of ${total} file chunks downloaded...`);
+      },
+      cacheComplete: () => {
+        setStatus(`Assembling files...`)
+      },
+      // files opened while the download was running are skipped too
+      filesSkipped: (paths: string[]) => {
+        toast({ title: `Skipped ${paths.length} open files`, description: paths.join(", ") })
+      },
+    };
 
     setStatus("Preparing files to download...");
 
-    let ret = await invoke("download_files", { pid: parseInt(pid), files: selectedDownload, user: userId });
+    let ret = false;
+    try {
+      ret = await runJob("download_files", { pid: parseInt(pid), files: selectedDownload, user: userId, token: await getToken() }, handlers);
+    } catch (err) {
+      setProgress(0);
+      setStatus(isCancelled(err) ? "Download cancelled" : "Download failed");
+      if (!isCancelled(err)) {
+        toast({ title: "Download failed", description: errorMessage(err) });
+      }
+      setDisabled(false);
+      return;
+    }
     if(!ret) {
       setStatus("Download failed")
       setDisabled(false);
@@ -107,8 +137,6 @@ function DownloadPage() {
     });
     setProgress(100); // lol
 
-    unlisten();
-    unlisten2();
     setStatus(`Download complete!`);
     setDisabled(false);
   }
@@ -127,7 +155,7 @@ function DownloadPage() {
           <p className='flex-auto text-center'>{status}</p>
           <div className='flex'>
           <Button
-            onClick={handleDownload}
+            onClick={() => handleDownload(null)}
             disabled={Object.keys(selection).length == 0 || disabled || progress == 100}
             >{
               progress == 100 && !disabled ? "Download Complete" :
@@ -135,11 +163,17 @@ function DownloadPage() {
                 "Download Selected" :
                 <><Loader2 className="mr-2 h-4 w-4 animate-spin" />Please wait</>
               }</Button>
+            <JobControls projectId={parseInt(pid)} />
           </div>
         </div>
       <div className='py-4 space-y-2'>
         <Progress value={progress}/>
       </div>
+      <OpenFilesDialog
+        files={openFiles}
+        verb="overwritten"
+        onSkip={() => { const skipped = openFiles; setOpenFiles([]); handleDownload(skipped) }}
+        onCancel={() => setOpenFiles([])}/>
       <FileTable columns={columns} data={downloads} selection={selection} setSelection={setSelection} includeFilter={false} height='h-[65vh]'/>
       </div>
   )
@@ -9,14 +9,25 @@ import {
   SelectTrigger,
 } from "@/components/ui/select";
 import { useAuth } from "@clerk/clerk-react";
-import { Link, createFileRoute } from "@tanstack/react-router";
+import { Link, createFileRoute, useRouter } from "@tanstack/react-router";
 import { RowSelectionState } from "@tanstack/react-table";
 import { invoke } from "@tauri-apps/api/core";
-import { useState } from "react";
+import { useEffect, useState } from "react";
 import { listen } from "@tauri-apps/api/event";
 import { Textarea } from "@/components/ui/textarea";
 import { useToast } from "@/components/ui/use-toast";
 import { Loader2 } from "lucide-react";
+import { errorMessage, isCancelled, runJob } from "@/lib/utils";
+import JobControls from "@/components/file/JobControls";
+import OpenFilesDialog from "@/components/file/OpenFilesDialog";
+import MissingReferencesDialog from "@/components/file/MissingReferencesDialog";
+
+interface UnfinishedUpload {
+  cjid: number;
+  pid: number;
+  message: string;
+  created: string;
+}
 
 export const Route = createFileRoute("/_app/upload")({
   validateSearch: (search) =>
@@ -28,8 +39,8 @@ export const Route = createFileRoute("/_app/upload")({
   }),
   loader: async ({ deps: { pid } }) => {
     let pid_i32 = parseInt(pid);
-    const url: string = await invoke("get_server_url");
     const uploads: File[] = await invoke("get_uploads", { pid: pid_i32 });
+    const unfinished: UnfinishedUpload | null = await invoke("get_unfinished_upload", { pid: pid_i32 });
 
     // initialize selection list
     let selectionList: RowSelectionState = {};
@@ -39,7 +50,7 @@ export const Route = createFileRoute("/_app/upload")({
     const projectName: string = await invoke("get_project_name", {
       pid: pid_i32,
     });
-    return { uploads, selectionList, projectName, url };
+    return { uploads, selectionList, projectName, unfinished };
   },
   component: () => <UploadPage />,
   gcTime: 0, // do not cache this route's data after its unloaded per docs
@@ -47,7 +58,7 @@ export const Route = createFileRoute("/_app/upload")({
 });
 
 function UploadPage() {
-  const { uploads, selectionList, projectName, url } = Route.useLoaderData();
+  const { uploads, selectionList, projectName, unfinished } = Route.useLoaderData();
   const { getToken, userId } = useAuth();
   const { pid } = Route.useSearch();
   const [action, setAction] = useState("Upload");
@@ -57,13 +68,100 @@ function UploadPage() {
   const [selection, setSelection] = useState(selectionList);
   const [filter, setFilter] = useState([]);
   const [commitMessage, setCommitMessage] = useState("");
+  const [openFiles, setOpenFiles] = useState<string[]>([]);
+  const [missingReferences, setMissingReferences] = useState<string[]>([]);
   const { toast } = useToast();
+  const router = useRouter();
+
+  // the file watcher tells us when files in the project change, so reload the upload list
+  useEffect(() => {
+    const unlisten = listen("projectFilesChanged", (event: any) => {
+      if (event.payload.pid == parseInt(pid) && !disabled) {
+        router.invalidate();
+      }
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [pid, disabled]);
+
+  useEffect(() => {
+    setSelection(selectionList);
+  }, [selectionList]);
 
   if (userId == null) {
     return <div>Loading...</div>;
   }
 
-  async function handleAction() {
+  function showUploadError(res: any) {
+    if(res.error == "ErrInvalidFile") { // TODO specta or enum
+      toast({
+        title: "Upload failed",
+        description:
+          "A file was detected to be different from its synced state. Re-sync and try uploading again.",
+      });
+    }
+    else {
+      toast({
+        title: "Upload failed",
+        description: res.message ??
+          "Check your permissions and Internet connection, and try again.",
+      });
+    }
+  }
+
+  // uploads report progress in bytes so large files don't stall the progress bar
+  function showUploadProgress(progress: { uploaded_bytes: number, total_bytes: number }) {
+    setProgress(100 * progress.uploaded_bytes / Math.max(progress.total_bytes, 1));
+  }
+
+  // an upload that was interrupted before all of it was committed
+  async function resumeUpload() {
+    setDisabled(true);
+    setStatus("Finishing interrupted upload...");
+    let res: any;
+    try {
+      res = await runJob("resume_upload", {
+        pid: parseInt(pid),
+        user: userId,
+        token: await getToken(),
+      }, { uploadProgress: showUploadProgress });
+    } catch (err) {
+      res = { success: false, cancelled: isCancelled(err), message: errorMessage(err) };
+    }
+    if (res.success) {
+      setStatus("Interrupted upload complete!");
+    } else if (res.cancelled) {
+      setStatus("Upload cancelled");
+    } else {
+      showUploadError(res);
+      setStatus("Upload failed");
+    }
+    setProgress(0);
+    setDisabled(false);
+    router.invalidate();
+  }
+
+  async function discardUpload() {
+    await invoke("discard_upload", { pid: parseInt(pid) });
+    router.invalidate();
+  }
+
+  // selects the files the upload was missing, so the user can look over the selection and upload again
+  function selectMissingReferences() {
+    let newSelection: RowSelectionState = { ...selection };
+    uploads.forEach((upload, i) => {
+      if (missingReferences.includes(upload.filepath)) {
+        newSelection[i.toString()] = true;
+      }
+    });
+    setSelection(newSelection);
+    setMissingReferences([]);
+  }
+
+  // `skipped` are open files the user chose not to reset, null if they haven't been checked for yet.
+  // `referencesChecked` is set once the user chose to upload without files the selected ones need
+  async function handleAction(skipped: string[] | null, referencesChecked: boolean = false) {
     const start = performance.now();
     setDisabled(true);
     // TODO unused
@@ -74,7 +172,6 @@ function UploadPage() {
     });
 
     let selectedFiles: string[] = [];
-    let uploadList: any[] = [];
     const filterActive = filter.length > 0;
     for (let i = 0; i < Object.keys(selection).length; i++) {
       const key: number = parseInt(Object.keys(selection)[i]);
@@ -86,15 +183,37 @@ function UploadPage() {
       }
 
       selectedFiles.push(uploads[key].filepath);
-      uploadList.push({
-        path: uploads[key].filepath,
-        hash: uploads[key].hash,
-        changetype: uploads[key].change_type,
-      });
     }
     console.log(selectedFiles);
     console.log(filter)
 
+    // files that are open in solidworks can't be reset
+    if (action == "Reset" && skipped == null) {
+      const open: string[] = await invoke("cmd_find_open_files", { pid: parseInt(pid), filepaths: selectedFiles });
+      if (open.length > 0) {
+        setOpenFiles(open);
+        setDisabled(false);
+        return;
+      }
+    } else if (skipped != null) {
+      selectedFiles = selectedFiles.filter((file) => !skipped.includes(file));
+    }
+
+    // assemblies that use new or changed parts which aren't selected would be broken for everyone else
+    if (action == "Upload" && !referencesChecked) {
+      const missing = new Set<string>();
+      for (const upload of uploads) {
+        if (selectedFiles.includes(upload.filepath)) {
+          (upload.references ?? []).filter((file) => !selectedFiles.includes(file)).forEach((file) => missing.add(file));
+        }
+      }
+      if (missing.size > 0) {
+        setMissingReferences([...missing].sort());
+        setDisabled(false);
+        return;
+      }
+    }
+
     const selectedLength = selectedFiles.length;
     let actionedFiles = 0;
     let verb = "uploaded";
@@ -102,106 +221,81 @@ function UploadPage() {
       verb = "reset";
     }
     setStatus(`0 of ${selectedLength} files ${verb}...`);
-    const unlisten = await listen("fileAction", (event: any) => {
-      console.log(event);
-      setProgress(100 * (++actionedFiles) / selectedLength);
-      setStatus(`${actionedFiles} of ${selectedLength} files ${verb}...`);
-    });
+    const handlers = {
+      fileAction: () => {
+        ++actionedFiles;
+        if (action != "Upload") {
+          setProgress(100 * actionedFiles / selectedLength);
+        }
+        setStatus(`${actionedFiles} of ${selectedLength} files ${verb}...`);
+      },
+      uploadProgress: showUploadProgress,
+      // files opened while the reset was running are skipped too
+      filesSkipped: (paths: string[]) => {
+        toast({ title: `Skipped ${paths.length} open files`, description: paths.join(", ") });
+      },
+    };
     if (action == "Upload") {
 
-      // upload files (as chunks)
-      let res: any = await invoke("upload_files", {
-        pid: parseInt(pid),
-        filepaths: selectedFiles,
-        user: userId,
-      });
+      // upload files (as chunks) and commit them
+      let res: any;
+      try {
+        res = await runJob("upload_and_commit", {
+          pid: parseInt(pid),
+          filepaths: selectedFiles,
+          message: commitMessage,
+          user: userId,
+          token: await getToken(),
+        }, handlers);
+      } catch (err) {
+        res = { success: false, cancelled: isCancelled(err), message: errorMessage(err) };
+      }
       if (!res.success) {
         console.log(res)
-        if(res.error == "ErrInvalidFile") { // TODO specta or enum
-          toast({
-            title: "Upload failed",
-            description:
-              "A file was detected to be different from its synced state. Re-sync and try uploading again.",
-          });
+        if (res.cancelled) {
+          setStatus("Upload cancelled");
+        } else {
+          showUploadError(res);
+          setStatus("Upload failed");
         }
-        else {
-          toast({
-            title: "Upload failed",
-            description:
-              "Check your permissions and Internet connection, and try again.",
-          });
-        }
-
-        setStatus("Upload failed");
-        unlisten();
+        setProgress(0);
         setDisabled(false);
+        router.invalidate();
         return;
       }
-
-      const ENDPOINT = url + "/commit";
-      const UPLOAD_LIMIT = 200;
-      setStatus(
-        `Logging project update${uploadList.length > UPLOAD_LIMIT ? "s" : ""}...`
-      );
-
-      // commit 200 files at a time
-      for (let i = 0; i < uploadList.length; i += UPLOAD_LIMIT) {
-        // append to commit message if needed
-        let msg = commitMessage;
-        if (uploadList.length >= UPLOAD_LIMIT) {
-          msg += ` - Part ${i / UPLOAD_LIMIT + 1}`;
-        }
-
-        // create commit
-        const response = await fetch(ENDPOINT, {
-          method: "POST",
-          mode: "cors",
-          headers: { Authorization: `Bearer ${await getToken()}` },
-          body: JSON.stringify({
-            projectId: parseInt(pid),
-            message: msg,
-            files: uploadList.slice(i, i + UPLOAD_LIMIT),
-          }),
-        });
-        const data = await response.json();
-        console.log(data);
-        if (data.response != "success") {
-          unlisten();
-
-          setStatus(`Upload failed`);
-          setDisabled(false);
-          return;
-        }
-
-        // update db
-        await invoke("update_uploaded", {
+    } else if (action == "Reset") {
+      let result = false;
+      let description = "Try again soon";
+      let cancelled = false;
+      try {
+        result = await runJob("reset_files", {
           pid: parseInt(pid),
-          commit: data.body.commit_id,
-          files: uploadList.slice(i, i + UPLOAD_LIMIT),
-        });
+          filepaths: selectedFiles,
+          user: userId,
+          token: await getToken(),
+        }, handlers);
+      } catch (err) {
+        cancelled = isCancelled(err);
+        description = errorMessage(err);
       }
-    } else if (action == "Reset") {
-      let result = await invoke("reset_files", {
-        pid: parseInt(pid),
-        filepaths: selectedFiles,
-        user: userId,
-      });
       if (result) {
       } else {
-        unlisten();
-        toast({
-          title: "Reset failed",
-          description: "Try again soon"
-        });
-        setStatus(`${action} failed`);
+        if (cancelled) {
+          setStatus(`${action} cancelled`);
+        } else {
+          toast({
+            title: "Reset failed",
+            description: description
+          });
+          setStatus(`${action} failed`);
+        }
+        setProgress(0);
         setDisabled(false);
         return;
       }
       setProgress(100);
     }
 
-    unlisten();
-
     // Once permission has been granted we can send the notification
     const end = performance.now();
     toast({
@@ -234,7 +328,7 @@ function UploadPage() {
             disabled={
               Object.keys(selection).length == 0 || disabled || progress == 100
             }
-            onClick={handleAction}
+            onClick={() => handleAction(null)}
             variant={action == "Reset" ? "destructive" : "default"}
           >
             {progress == 100 && !disabled ? action + " Complete" : progress == 0 ? action + " Selected" : <><Loader2 className="mr-2 h-4 w-4 animate-spin" />Please wait</>}
@@ -249,8 +343,18 @@ function UploadPage() {
               <SelectItem value="Reset">Reset</SelectItem>
             </SelectContent>
           </Select>
+          <JobControls projectId={parseInt(pid)} />
         </div>
       </div>
+      {unfinished ? (
+        <div className="flex flex-row items-center space-x-4 pt-4">
+          <p className="flex-auto">
+            The upload "{unfinished.message}" was interrupted before all of it was committed.
+          </p>
+          <Button disabled={disabled} onClick={resumeUpload}>Resume</Button>
+          <Button disabled={disabled} variant={"outline"} onClick={discardUpload}>Discard</Button>
+        </div>
+      ) : <></>}
       <div className="py-4 space-y-2">
         <Textarea
           placeholder="Write your project update message here."
@@ -259,6 +363,18 @@ function UploadPage() {
         />
         <Progress value={progress} />
       </div>
+      <OpenFilesDialog
+        files={openFiles}
+        verb="reset"
+        onSkip={() => { const skipped = openFiles; setOpenFiles([]); handleAction(skipped); }}
+        onCancel={() => setOpenFiles([])}
+      />
+      <MissingReferencesDialog
+        files={missingReferences}
+        onSelect={selectMissingReferences}
+        onUpload={() => { setMissingReferences([]); handleAction(null, true); }}
+        onCancel={() => setMissingReferences([])}
+      />
       <FileTable
         columns={columns}
         data={uploads}